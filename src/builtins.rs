@@ -0,0 +1,757 @@
+use std::cmp::Ordering;
+use crate::eval::{Evaluator, Error, MAX_RANGE_LENGTH, to_integer, apply2, add, multiply, divide, power, order};
+use crate::integers;
+use crate::statistics;
+use crate::intervals;
+use crate::polynomials;
+use crate::symbolic;
+use crate::random::{self, Random};
+use crate::nodes::Expression;
+use crate::values::{Value, approximate, modulus, to_f64, gcd};
+
+/// A built-in function that calls function values.
+type HigherOrder = dyn Fn(&mut Evaluator, Vec<Value>) -> Result<Vec<Value>, Error>;
+
+/// A built-in function that draws random numbers.
+type Sampler = dyn Fn(&mut Random, Vec<Value>) -> Result<Vec<Value>, Error>;
+
+impl Evaluator {
+    /// Calls the built-in function called `name` with the argument `arg`,
+    /// returning `None` if there is no built-in function with that name.
+    pub fn call_builtin(&mut self, name: &str, arg: Expression) -> Option<Result<Vec<Value>, Error>> {
+        let result = match name {
+            "re" => self.eval1(&re, arg),
+            "im" => self.eval1(&im, arg),
+            "conj" => self.eval1(&conj, arg),
+            "abs" => self.eval1(&abs, arg),
+            "arg" => self.eval1(&argument, arg),
+            "polar" => self.eval1(&polar, arg),
+            "rect" => self.eval1(&rect, arg),
+            "gcd" => self.eval1(&gcd_, arg),
+            "lcm" => self.eval1(&lcm, arg),
+            "isprime" => self.eval1(&is_prime, arg),
+            "nextprime" => self.eval1(&next_prime, arg),
+            "factor" => self.eval1(&factor, arg),
+            "totient" => self.eval1(&totient, arg),
+            "divisors" => self.eval1(&divisors, arg),
+            "powmod" => self.eval1(&pow_mod, arg),
+            "invmod" => self.eval1(&inverse_mod, arg),
+            "modular" => self.modular_of(arg),
+            "choose" => self.eval1(&|x| counting2(x, &integers::choose), arg),
+            "perm" => self.eval1(&|x| counting2(x, &integers::falling_factorial), arg),
+            "multinomial" => self.eval1(&multinomial, arg),
+            "catalan" => self.eval1(&|x| counting1(x, &integers::catalan), arg),
+            "stirling1" => self.eval1(&|x| counting2(x, &integers::stirling_first), arg),
+            "stirling2" => self.eval1(&|x| counting2(x, &integers::stirling_second), arg),
+            "bell" => self.eval1(&|x| counting1(x, &integers::bell), arg),
+            "partitions" => self.eval1(&|x| counting1(x, &integers::partitions), arg),
+            "sum" | "Σ" if is_iteration(&arg) => self.iterate(arg, Value::integer(0), &add),
+            "sum" => self.eval1(&|x| fold_elements(x, Value::integer(0), &add), arg),
+            "prod" | "Π" if is_iteration(&arg) => self.iterate(arg, Value::integer(1), &multiply),
+            "prod" => self.eval1(&|x| fold_elements(x, Value::integer(1), &multiply), arg),
+            "len" => self.eval1(&len, arg),
+            "max" => self.eval1(&|x| extreme(x, Ordering::Greater), arg),
+            "min" => self.eval1(&|x| extreme(x, Ordering::Less), arg),
+            "sort" => self.eval1(&sort, arg),
+            "zip" => self.eval1(&zip, arg),
+            "mean" => self.eval1(&statistics::mean, arg),
+            "median" => self.eval1(&statistics::median, arg),
+            "mode" => self.eval1(&statistics::mode, arg),
+            "variance" => self.eval1(&|x| statistics::variance(x, true), arg),
+            "pvariance" => self.eval1(&|x| statistics::variance(x, false), arg),
+            "stdev" => self.eval1(&|x| statistics::stdev(x, true), arg),
+            "pstdev" => self.eval1(&|x| statistics::stdev(x, false), arg),
+            "quantile" => self.eval1(&statistics::quantile, arg),
+            "covariance" => self.eval1(&statistics::covariance, arg),
+            "correlation" => self.eval1(&statistics::correlation, arg),
+            "linreg" => self.eval1(&statistics::linreg, arg),
+            "binompdf" => self.eval1(&statistics::binompdf, arg),
+            "binomcdf" => self.eval1(&statistics::binomcdf, arg),
+            "geometpdf" => self.eval1(&statistics::geometpdf, arg),
+            "geometcdf" => self.eval1(&statistics::geometcdf, arg),
+            "poissonpdf" => self.eval1(&statistics::poissonpdf, arg),
+            "poissoncdf" => self.eval1(&statistics::poissoncdf, arg),
+            "normalpdf" => self.eval1(&statistics::normalpdf, arg),
+            "normalcdf" => self.eval1(&statistics::normalcdf, arg),
+            "invnorm" => self.eval1(&statistics::invnorm, arg),
+            "rand" => self.draw(arg, &random::rand),
+            "randint" => self.draw(arg, &random::randint),
+            "randrat" => self.draw(arg, &random::randrat),
+            "sample" => self.draw(arg, &random::sample),
+            "interval" => self.interval(arg),
+            "sqrt" => self.eval1(&sqrt, arg),
+            "sin" => self.eval1(&sin, arg),
+            "cos" => self.eval1(&cos, arg),
+            "tan" => self.eval1(&tan, arg),
+            "atan" => self.eval1(&atan, arg),
+            "exp" => self.eval1(&exp, arg),
+            "ln" => self.eval1(&ln, arg),
+            "log" => self.eval1(&log, arg),
+            "deg" => self.eval1(&polynomials::deg, arg),
+            "coeffs" => self.eval1(&polynomials::coeffs, arg),
+            "roots" => self.eval1(&polynomials::roots, arg),
+            "simplify" => self.simplify(&arg).map(|x| vec![x]),
+            "expand" => self.expand(arg),
+            "d" => self.differentiate(arg),
+            "integrate" => self.integrate(arg),
+            "solve" => self.solve_equation(arg),
+            "limit" => self.limit_of(arg),
+            "series" => self.series_of(arg),
+            "findroot" => self.higher_order(arg, &find_root),
+            "minimize" => self.higher_order(arg, &|evaluator, args| extremum(evaluator, args, false)),
+            "maximize" => self.higher_order(arg, &|evaluator, args| extremum(evaluator, args, true)),
+            "map" => self.higher_order(arg, &map),
+            "filter" => self.higher_order(arg, &filter),
+            "fold" => self.higher_order(arg, &fold),
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    /// Calls a built-in function that takes function values as arguments, and so
+    /// needs the evaluator to call them.
+    fn higher_order(&mut self, arg: Expression, f: &HigherOrder) -> Result<Vec<Value>, Error> {
+        let mut values = Vec::new();
+        for x in self.evaluate_expression(arg)? {
+            values.extend(f(self, arguments(x))?);
+        }
+        values.dedup();
+        Ok(values)
+    }
+
+    /// Calls a built-in function that draws from the evaluator's random number
+    /// generator.
+    fn draw(&mut self, arg: Expression, f: &Sampler) -> Result<Vec<Value>, Error> {
+        let mut values = Vec::new();
+        for x in self.evaluate_expression(arg)? {
+            values.extend(f(self.random(), arguments(x))?);
+        }
+        Ok(values)
+    }
+
+    /// Makes a closed interval from a list of two numbers, as in `interval [1, 2]`,
+    /// or an open one from a tuple, as in `interval (0, 1)`.
+    fn interval(&mut self, arg: Expression) -> Result<Vec<Value>, Error> {
+        let (index, mut xs, closed) = match arg {
+            Expression::List(i, xs) => (i, xs, true),
+            Expression::Tuple(i, xs) => (i, xs, false),
+            _ => return Err(Error::MismatchedType),
+        };
+        if xs.len() != 2 {
+            return Err(Error::WrongArgumentCount);
+        }
+        let (upper, lower) = (xs.remove(1), xs.remove(0));
+        self.evaluate_expression(Expression::Interval(index, Box::new(lower), Box::new(upper), closed, closed))
+    }
+
+    /// Differentiates `d(expr, x)` with respect to the variable `x`.
+    fn differentiate(&mut self, arg: Expression) -> Result<Vec<Value>, Error> {
+        match arg {
+            Expression::Tuple(_, xs) => match &xs[..] {
+                [expr, Expression::Variable(_, x)] => {
+                    let derivative = self.derivative(expr, x)?;
+                    Ok(vec![self.value_of(derivative)])
+                },
+                [_, _] => Err(Error::MismatchedType),
+                _ => Err(Error::WrongArgumentCount),
+            },
+            _ => Err(Error::WrongArgumentCount),
+        }
+    }
+
+    /// Integrates `integrate(f, a, b)` over the interval from `a` to `b`.
+    fn integrate(&mut self, arg: Expression) -> Result<Vec<Value>, Error> {
+        let (f, a, b) = match arg {
+            Expression::Tuple(_, xs) => match <[Expression; 3]>::try_from(xs) {
+                Ok([f, a, b]) => (f, a, b),
+                Err(_) => return Err(Error::WrongArgumentCount),
+            },
+            _ => return Err(Error::WrongArgumentCount),
+        };
+        let (a, b) = (self.evaluate_real(a)?, self.evaluate_real(b)?);
+        let mut values = Vec::new();
+        for f in self.evaluate_expression(f)? {
+            values.push(self.integral(f, a, b)?);
+        }
+        values.dedup();
+        Ok(values)
+    }
+
+    /// Solves `solve(equation, x)` for `x`, or `solve(equation)` for its one
+    /// unknown. A system is solved with `solve((equations), (x, y))`, or for all
+    /// its unknowns with `solve((equations))`.
+    fn solve_equation(&mut self, arg: Expression) -> Result<Vec<Value>, Error> {
+        let is_equation = |x: &Expression| matches!(x, Expression::Define(..) | Expression::Equal(..));
+        match arg {
+            Expression::Tuple(_, xs) => match &xs[..] {
+                [equations, Expression::Tuple(_, unknowns)] => {
+                    let equations = match equations {
+                        Expression::Tuple(_, equations) => equations.clone(),
+                        equation => vec![equation.clone()],
+                    };
+                    let unknowns = unknowns.iter().map(|x| match x {
+                        Expression::Variable(_, x) => Ok(x.clone()),
+                        _ => Err(Error::MismatchedType),
+                    }).collect::<Result<Vec<_>, _>>()?;
+                    self.solve_system(&equations, &unknowns)
+                },
+                [equation, Expression::Variable(_, x)] => self.solve(equation, x),
+                equations if equations.iter().all(is_equation) => {
+                    let unknowns = self.unknowns(equations)?;
+                    self.solve_system(equations, &unknowns)
+                },
+                [_, _] => Err(Error::MismatchedType),
+                _ => Err(Error::WrongArgumentCount),
+            },
+            equation => {
+                let x = self.unknown(&equation)?;
+                self.solve(&equation, &x)
+            },
+        }
+    }
+
+    /// Takes `limit(expr, x, a)` from both sides, or `limit(expr, x, a, 1)` from
+    /// above and `limit(expr, x, a, -1)` from below.
+    fn limit_of(&mut self, arg: Expression) -> Result<Vec<Value>, Error> {
+        match arg {
+            Expression::Tuple(_, xs) => match <[Expression; 3]>::try_from(xs) {
+                Ok([expr, Expression::Variable(_, x), a]) => Ok(vec![self.limit(&expr, &x, a, 0)?]),
+                Ok(_) => Err(Error::MismatchedType),
+                Err(xs) => match <[Expression; 4]>::try_from(xs) {
+                    Ok([expr, Expression::Variable(_, x), a, side]) => {
+                        let side = self.evaluate_real(side)?.0.signum() as i32;
+                        Ok(vec![self.limit(&expr, &x, a, side)?])
+                    },
+                    Ok(_) => Err(Error::MismatchedType),
+                    Err(_) => Err(Error::WrongArgumentCount),
+                },
+            },
+            _ => Err(Error::WrongArgumentCount),
+        }
+    }
+
+    /// Evaluates `modular(expr, n)` in arithmetic modulo `n`.
+    fn modular_of(&mut self, arg: Expression) -> Result<Vec<Value>, Error> {
+        match arg {
+            Expression::Tuple(_, xs) => match <[Expression; 2]>::try_from(xs) {
+                Ok([expr, m]) => {
+                    let moduli = self.evaluate_expression(m)?;
+                    self.modular(expr, moduli)
+                },
+                Err(_) => Err(Error::WrongArgumentCount),
+            },
+            _ => Err(Error::WrongArgumentCount),
+        }
+    }
+
+    /// Expands `series(expr, x, a, n)` about `a` up to the `n`th power.
+    fn series_of(&mut self, arg: Expression) -> Result<Vec<Value>, Error> {
+        match arg {
+            Expression::Tuple(_, xs) => match <[Expression; 4]>::try_from(xs) {
+                Ok([expr, Expression::Variable(_, x), a, n]) => {
+                    let n = self.evaluate_integer(n)?;
+                    Ok(vec![self.series(&expr, &x, a, n)?])
+                },
+                Ok(_) => Err(Error::MismatchedType),
+                Err(_) => Err(Error::WrongArgumentCount),
+            },
+            _ => Err(Error::WrongArgumentCount),
+        }
+    }
+
+    /// Evaluates an expression that must have a single integer value.
+    pub fn evaluate_integer(&mut self, expr: Expression) -> Result<isize, Error> {
+        match &self.evaluate_expression(expr)?[..] {
+            [x] => to_integer(x),
+            _ => Err(Error::MismatchedType),
+        }
+    }
+
+    /// Folds the values of a body over an index running through a range of
+    /// integers, as in `sum(k, 1, 10, k^2)`. The index is bound in its own scope,
+    /// and a range longer than `MAX_RANGE_LENGTH` is `TooManyElements`.
+    fn iterate(&mut self, arg: Expression, identity: Value, f: &dyn Fn(Value, Value) -> Result<Vec<Value>, Error>) -> Result<Vec<Value>, Error> {
+        let (index, from, to, body) = match arg {
+            Expression::Tuple(_, xs) => match &xs[..] {
+                [Expression::Variable(_, index), from, to, body] => (index.clone(), from.clone(), to.clone(), body.clone()),
+                [_, _, _, _] => return Err(Error::MismatchedType),
+                _ => return Err(Error::WrongArgumentCount),
+            },
+            _ => return Err(Error::WrongArgumentCount),
+        };
+        let (from, to) = (self.evaluate_integer(from)?, self.evaluate_integer(to)?);
+        if to as i128 - from as i128 >= MAX_RANGE_LENGTH as i128 {
+            return Err(Error::TooManyElements);
+        }
+        let mut values = vec![identity];
+        for k in from..=to {
+            self.increase_scope();
+            self.bind(index.clone(), vec![Value::integer(k)]);
+            let body_values = self.evaluate_expression(body.clone());
+            self.decrease_scope();
+            values = apply2(f, values, body_values?)?;
+        }
+        Ok(values)
+    }
+}
+
+/// Checks whether the argument of `sum` or `prod` is an index, a range and a
+/// body rather than a list of values. The index is any name, since it is bound
+/// in its own scope even when it is defined outside.
+fn is_iteration(arg: &Expression) -> bool {
+    match arg {
+        Expression::Tuple(_, xs) => matches!(&xs[..], [Expression::Variable(..), _, _, _]),
+        _ => false,
+    }
+}
+
+/// Converts a float result back into a rational value.
+pub fn from_f64(x: f64) -> Result<Value, Error> {
+    let (a, b) = approximate(x).ok_or(Error::Overflow)?;
+    Ok(Value::rational(a, b))
+}
+
+fn re(x: Value) -> Result<Vec<Value>, Error> {
+    match x {
+        Value::ComplexNumber(a, b, _, _) => Ok(vec![Value::rational(a, b)]),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+fn im(x: Value) -> Result<Vec<Value>, Error> {
+    match x {
+        Value::ComplexNumber(_, _, c, d) => Ok(vec![Value::rational(c, d)]),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+fn conj(x: Value) -> Result<Vec<Value>, Error> {
+    match x {
+        Value::ComplexNumber(a, b, c, d) => Ok(vec![Value::complex(a, b, -c, d)]),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+fn abs(x: Value) -> Result<Vec<Value>, Error> {
+    match x {
+        Value::ComplexNumber(a, b, c, d) => match modulus((a, b), (c, d)) {
+            Some((r, s)) => Ok(vec![Value::rational(r, s)]),
+            None => Ok(vec![from_f64(to_f64((a, b)).hypot(to_f64((c, d))))?]),
+        },
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+fn argument(x: Value) -> Result<Vec<Value>, Error> {
+    match x {
+        Value::ComplexNumber(a, _, 0, _) if a >= 0 => Ok(vec![Value::integer(0)]),
+        Value::ComplexNumber(a, b, c, d) => Ok(vec![from_f64(to_f64((c, d)).atan2(to_f64((a, b))))?]),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+/// Returns the modulus and argument of a complex number as a tuple.
+fn polar(x: Value) -> Result<Vec<Value>, Error> {
+    let mut values = Vec::new();
+    for r in abs(x.clone())? {
+        for theta in argument(x.clone())? {
+            values.push(Value::Tuple(vec![r.clone(), theta]));
+        }
+    }
+    Ok(values)
+}
+
+/// Builds a complex number from a modulus and argument tuple.
+fn rect(x: Value) -> Result<Vec<Value>, Error> {
+    match x {
+        Value::Tuple(xs) => match &xs[..] {
+            [r @ Value::ComplexNumber(_, _, 0, _), Value::ComplexNumber(0, _, 0, _)] => Ok(vec![r.clone()]),
+            [Value::ComplexNumber(a, b, 0, _), Value::ComplexNumber(c, d, 0, _)] => {
+                let (r, theta) = (to_f64((*a, *b)), to_f64((*c, *d)));
+                let (re, im) = (approximate(r * theta.cos()), approximate(r * theta.sin()));
+                match (re, im) {
+                    (Some((a, b)), Some((c, d))) => Ok(vec![Value::complex(a, b, c, d)]),
+                    _ => Err(Error::Overflow),
+                }
+            },
+            _ => Err(Error::MismatchedType),
+        },
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+/// Takes the principal square root, which is exact when it is rational.
+fn sqrt(x: Value) -> Result<Vec<Value>, Error> {
+    match x {
+        x @ Value::Interval(..) => intervals::sqrt(x),
+        x @ (Value::Polynomial(_) | Value::Symbolic(_)) => symbolic::apply("sqrt", x),
+        x => power(x, Value::rational(1, 2)),
+    }
+}
+
+/// Makes a complex number from a pair of floats.
+pub fn from_f64_pair(re: f64, im: f64) -> Result<Vec<Value>, Error> {
+    match (approximate(re), approximate(im)) {
+        (Some((a, b)), Some((c, d))) => Ok(vec![Value::complex(a, b, c, d)]),
+        _ => Err(Error::Overflow),
+    }
+}
+
+fn sin(x: Value) -> Result<Vec<Value>, Error> {
+    match x {
+        Value::ComplexNumber(0, _, 0, _) => Ok(vec![Value::integer(0)]),
+        Value::ComplexNumber(a, b, c, d) => {
+            let (re, im) = (to_f64((a, b)), to_f64((c, d)));
+            from_f64_pair(re.sin() * im.cosh(), re.cos() * im.sinh())
+        },
+        x @ Value::Interval(..) => intervals::sin(x),
+        x @ (Value::Polynomial(_) | Value::Symbolic(_)) => symbolic::apply("sin", x),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+fn cos(x: Value) -> Result<Vec<Value>, Error> {
+    match x {
+        Value::ComplexNumber(0, _, 0, _) => Ok(vec![Value::integer(1)]),
+        Value::ComplexNumber(a, b, c, d) => {
+            let (re, im) = (to_f64((a, b)), to_f64((c, d)));
+            from_f64_pair(re.cos() * im.cosh(), -re.sin() * im.sinh())
+        },
+        x @ Value::Interval(..) => intervals::cos(x),
+        x @ (Value::Polynomial(_) | Value::Symbolic(_)) => symbolic::apply("cos", x),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+fn tan(x: Value) -> Result<Vec<Value>, Error> {
+    match x {
+        x @ (Value::Polynomial(_) | Value::Symbolic(_)) => symbolic::apply("tan", x),
+        x => divide(sin(x.clone())?.remove(0), cos(x)?.remove(0)),
+    }
+}
+
+/// Takes the arctangent of a real number.
+fn atan(x: Value) -> Result<Vec<Value>, Error> {
+    match x {
+        Value::ComplexNumber(0, _, 0, _) => Ok(vec![Value::integer(0)]),
+        Value::ComplexNumber(a, b, 0, _) => Ok(vec![from_f64(to_f64((a, b)).atan())?]),
+        Value::ComplexNumber(..) => Err(Error::NotReal),
+        x @ (Value::Polynomial(_) | Value::Symbolic(_)) => symbolic::apply("atan", x),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+fn exp(x: Value) -> Result<Vec<Value>, Error> {
+    match x {
+        Value::ComplexNumber(0, _, 0, _) => Ok(vec![Value::integer(1)]),
+        Value::ComplexNumber(a, b, c, d) => {
+            let (modulus, angle) = (to_f64((a, b)).exp(), to_f64((c, d)));
+            from_f64_pair(modulus * angle.cos(), modulus * angle.sin())
+        },
+        x @ (Value::Polynomial(_) | Value::Symbolic(_)) => symbolic::apply("exp", x),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+/// Takes the principal natural logarithm.
+fn ln(x: Value) -> Result<Vec<Value>, Error> {
+    match x {
+        Value::ComplexNumber(0, _, 0, _) => Err(Error::OutOfDomain),
+        Value::ComplexNumber(1, 1, 0, _) => Ok(vec![Value::integer(0)]),
+        Value::ComplexNumber(a, b, c, d) => {
+            let (re, im) = (to_f64((a, b)), to_f64((c, d)));
+            from_f64_pair(re.hypot(im).ln(), im.atan2(re))
+        },
+        x @ (Value::Polynomial(_) | Value::Symbolic(_)) => symbolic::apply("ln", x),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+/// Takes the logarithm to base 10.
+fn log(x: Value) -> Result<Vec<Value>, Error> {
+    match x {
+        x @ (Value::Polynomial(_) | Value::Symbolic(_)) => symbolic::apply("log", x),
+        Value::ComplexNumber(0, _, 0, _) => Err(Error::OutOfDomain),
+        Value::ComplexNumber(a, b, c, d) => {
+            let (re, im) = (to_f64((a, b)), to_f64((c, d)));
+            from_f64_pair(re.hypot(im).log10(), im.atan2(re) / std::f64::consts::LN_10)
+        },
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+
+/// Reads the arguments of a built-in function, which are either a tuple or a
+/// single value.
+pub fn arguments(x: Value) -> Vec<Value> {
+    match x {
+        Value::Tuple(xs) => xs,
+        x => vec![x],
+    }
+}
+
+fn integer_arguments(x: Value) -> Result<Vec<isize>, Error> {
+    arguments(x).iter().map(to_integer).collect()
+}
+
+/// Reads a positive integer argument for the functions that factor numbers.
+fn positive_integer(x: Value) -> Result<u64, Error> {
+    match integer_arguments(x)?[..] {
+        [n] if n > 0 => Ok(n as u64),
+        [_] => Err(Error::OutOfDomain),
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+fn gcd_(x: Value) -> Result<Vec<Value>, Error> {
+    if arguments(x.clone()).iter().any(|x| matches!(x, Value::Polynomial(_))) {
+        return polynomials::gcd(x);
+    }
+    let divisor = integer_arguments(x)?.into_iter().try_fold(0, gcd).ok_or(Error::Overflow)?;
+    Ok(vec![Value::integer(divisor)])
+}
+
+fn lcm(x: Value) -> Result<Vec<Value>, Error> {
+    let mut multiple = 1;
+    for n in integer_arguments(x)? {
+        multiple = integers::lcm(multiple, n).ok_or(Error::Overflow)?;
+    }
+    Ok(vec![Value::integer(multiple)])
+}
+
+fn is_prime(x: Value) -> Result<Vec<Value>, Error> {
+    match integer_arguments(x)?[..] {
+        [n] => Ok(vec![Value::Boolean(n > 0 && integers::is_prime(n as u64))]),
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+fn next_prime(x: Value) -> Result<Vec<Value>, Error> {
+    match integer_arguments(x)?[..] {
+        [n] => {
+            let prime = integers::next_prime(n.max(0) as u64).ok_or(Error::Overflow)?;
+            Ok(vec![Value::integer(isize::try_from(prime).map_err(|_| Error::Overflow)?)])
+        },
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+/// Factors an integer into a tuple of `(prime, power)` pairs, with `(-1, 1)`
+/// first for negative numbers. A polynomial is factored into irreducible
+/// factors over the rationals, or over the Gaussian rationals with `factor(p, i)`.
+fn factor(x: Value) -> Result<Vec<Value>, Error> {
+    match &x {
+        Value::Polynomial(_) => return polynomials::factor(x, false),
+        Value::Tuple(xs) => match &xs[..] {
+            [p @ Value::Polynomial(_), Value::ComplexNumber(0, _, 1, 1)] => return polynomials::factor(p.clone(), true),
+            [Value::Polynomial(_), _] => return Err(Error::MismatchedType),
+            _ => {},
+        },
+        _ => {},
+    }
+    let n = match integer_arguments(x)?[..] {
+        [0] => return Err(Error::OutOfDomain),
+        [n] => n,
+        _ => return Err(Error::WrongArgumentCount),
+    };
+    let mut factors = Vec::new();
+    if n < 0 {
+        factors.push(Value::Tuple(vec![Value::integer(-1), Value::integer(1)]));
+    }
+    for (p, k) in integers::factor(n.unsigned_abs() as u64) {
+        factors.push(Value::Tuple(vec![Value::integer(p as isize), Value::integer(k as isize)]));
+    }
+    Ok(vec![Value::Tuple(factors)])
+}
+
+fn totient(x: Value) -> Result<Vec<Value>, Error> {
+    let n = positive_integer(x)?;
+    Ok(vec![Value::integer(integers::totient(n) as isize)])
+}
+
+fn divisors(x: Value) -> Result<Vec<Value>, Error> {
+    let n = positive_integer(x)?;
+    let divisors = integers::divisors(n).into_iter().map(|d| Value::integer(d as isize)).collect();
+    Ok(vec![Value::Tuple(divisors)])
+}
+
+/// Raises a base to a power modulo a number, taking the modular inverse for
+/// negative powers.
+fn pow_mod(x: Value) -> Result<Vec<Value>, Error> {
+    let (base, exponent, m) = match integer_arguments(x)?[..] {
+        [_, _, 0] => return Err(Error::DivisionByZero),
+        [base, exponent, m] => (base, exponent, m.unsigned_abs() as u64),
+        _ => return Err(Error::WrongArgumentCount),
+    };
+    let base = match exponent {
+        exponent if exponent < 0 => integers::inverse_mod(base, m as isize).ok_or(Error::NoInverse)?,
+        _ => base,
+    };
+    let base = (base as i128).rem_euclid(m as i128) as u64;
+    let result = integers::pow_mod(base, exponent.unsigned_abs() as u64, m);
+    Ok(vec![Value::integer(result as isize)])
+}
+
+fn inverse_mod(x: Value) -> Result<Vec<Value>, Error> {
+    match integer_arguments(x)?[..] {
+        [_, 0] => Err(Error::DivisionByZero),
+        [a, m] => {
+            let inverse = integers::inverse_mod(a, m).ok_or(Error::NoInverse)?;
+            Ok(vec![Value::integer(inverse)])
+        },
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+/// Reads non-negative integer arguments, for the functions that count things.
+fn natural_arguments(x: Value) -> Result<Vec<u64>, Error> {
+    integer_arguments(x)?.into_iter()
+        .map(|n| u64::try_from(n).map_err(|_| Error::OutOfDomain))
+        .collect()
+}
+
+fn counting1(x: Value, f: &dyn Fn(u64) -> Option<isize>) -> Result<Vec<Value>, Error> {
+    match natural_arguments(x)?[..] {
+        [n] => Ok(vec![Value::integer(f(n).ok_or(Error::Overflow)?)]),
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+fn counting2(x: Value, f: &dyn Fn(u64, u64) -> Option<isize>) -> Result<Vec<Value>, Error> {
+    match natural_arguments(x)?[..] {
+        [n, k] => Ok(vec![Value::integer(f(n, k).ok_or(Error::Overflow)?)]),
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+fn multinomial(x: Value) -> Result<Vec<Value>, Error> {
+    let ks = natural_arguments(x)?;
+    Ok(vec![Value::integer(integers::multinomial(&ks).ok_or(Error::Overflow)?)])
+}
+
+/// Reads the elements of a list or tuple argument, or of the arguments themselves.
+pub fn elements(x: Value) -> Vec<Value> {
+    match x {
+        Value::List(xs) | Value::Tuple(xs) => xs,
+        x => vec![x],
+    }
+}
+
+fn fold_elements(x: Value, identity: Value, f: &dyn Fn(Value, Value) -> Result<Vec<Value>, Error>) -> Result<Vec<Value>, Error> {
+    let mut values = vec![identity];
+    for x in elements(x) {
+        values = apply2(f, values, vec![x])?;
+    }
+    Ok(values)
+}
+
+fn len(x: Value) -> Result<Vec<Value>, Error> {
+    match x {
+        Value::List(xs) | Value::Tuple(xs) => Ok(vec![Value::integer(xs.len() as isize)]),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+/// Finds the greatest element for `Ordering::Greater`, or the least for `Ordering::Less`.
+fn extreme(x: Value, ordering: Ordering) -> Result<Vec<Value>, Error> {
+    let mut best: Option<Value> = None;
+    for x in elements(x) {
+        best = match best {
+            Some(y) if order(&x, &y)? != ordering => Some(y),
+            _ => Some(x),
+        };
+    }
+    best.map(|x| vec![x]).ok_or(Error::OutOfDomain)
+}
+
+fn sort(x: Value) -> Result<Vec<Value>, Error> {
+    let mut xs = elements(x);
+    // Every element is compared with the first, so that the comparisons made
+    // while sorting cannot fail, as they would between metres and seconds
+    if let Some(first) = xs.first() {
+        for x in &xs {
+            order(first, x)?;
+        }
+    }
+    xs.sort_by(|x, y| order(x, y).unwrap_or(Ordering::Equal));
+    Ok(vec![Value::List(xs)])
+}
+
+fn zip(x: Value) -> Result<Vec<Value>, Error> {
+    match &arguments(x)[..] {
+        [xs, ys] => {
+            let pairs = elements(xs.clone()).into_iter().zip(elements(ys.clone()))
+                .map(|(x, y)| Value::Tuple(vec![x, y]))
+                .collect();
+            Ok(vec![Value::List(pairs)])
+        },
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+fn map(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Vec<Value>, Error> {
+    match &args[..] {
+        [f, xs] => {
+            let mut list = Vec::new();
+            for x in elements(xs.clone()) {
+                list.extend(evaluator.call_value(f.clone(), x)?);
+            }
+            Ok(vec![Value::List(list)])
+        },
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+fn filter(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Vec<Value>, Error> {
+    match &args[..] {
+        [f, xs] => {
+            let mut list = Vec::new();
+            for x in elements(xs.clone()) {
+                match &evaluator.call_value(f.clone(), x.clone())?[..] {
+                    [Value::Boolean(true)] => list.push(x),
+                    [Value::Boolean(false)] => {},
+                    _ => return Err(Error::MismatchedType),
+                }
+            }
+            Ok(vec![Value::List(list)])
+        },
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+/// Folds a list from the left with a function taking an `(accumulator, element)` tuple.
+fn fold(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Vec<Value>, Error> {
+    match &args[..] {
+        [f, initial, xs] => {
+            let mut values = vec![initial.clone()];
+            for x in elements(xs.clone()) {
+                let mut next_values = Vec::new();
+                for value in values {
+                    next_values.extend(evaluator.call_value(f.clone(), Value::Tuple(vec![value, x.clone()]))?);
+                }
+                next_values.dedup();
+                values = next_values;
+            }
+            Ok(values)
+        },
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+/// Finds a root with `findroot(f, x0)` from a starting point, or with
+/// `findroot(f, a, b)` between two points.
+fn find_root(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Vec<Value>, Error> {
+    match &args[..] {
+        [f, points @ ..] if !points.is_empty() => Ok(vec![evaluator.find_root(f.clone(), points)?]),
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+/// Finds the smallest or largest value of a function on an interval, with
+/// `minimize(f, a, b)` and `maximize(f, a, b)`.
+fn extremum(evaluator: &mut Evaluator, args: Vec<Value>, largest: bool) -> Result<Vec<Value>, Error> {
+    match &args[..] {
+        [f, a, b] => Ok(vec![evaluator.extremum(f.clone(), a, b, largest)?]),
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
@@ -0,0 +1,284 @@
+use crate::eval::{self, Error};
+use crate::nodes::{Expression, FUNCTIONS};
+use crate::values::Value;
+use crate::polynomials::{self, Polynomial};
+use crate::symbolic::to_expression;
+use Expression::*;
+
+fn number(n: isize) -> Expression {
+    Number(0, n, 1)
+}
+
+fn add(x: Expression, y: Expression) -> Expression {
+    Add(0, Box::new(x), Box::new(y))
+}
+
+fn subtract(x: Expression, y: Expression) -> Expression {
+    Subtract(0, Box::new(x), Box::new(y))
+}
+
+fn multiply(x: Expression, y: Expression) -> Expression {
+    Multiply(0, Box::new(x), Box::new(y))
+}
+
+fn divide(x: Expression, y: Expression) -> Expression {
+    Divide(0, Box::new(x), Box::new(y))
+}
+
+fn power(x: Expression, y: Expression) -> Expression {
+    Power(0, Box::new(x), Box::new(y))
+}
+
+pub fn call(name: &str, x: Expression) -> Expression {
+    Call(0, Box::new(Variable(0, name.to_string())), Box::new(x))
+}
+
+/// Whether the variable `x` appears anywhere in an expression.
+pub fn depends_on(expr: &Expression, x: &str) -> bool {
+    match expr {
+        Variable(_, name) => name == x,
+        expr => expr.try_map(&mut |e| match depends_on(e, x) {
+            true => Err(()),
+            false => Ok(e.clone()),
+        }).is_err(),
+    }
+}
+
+/// The derivative of a function that simplification knows, at `u`.
+fn outer_derivative(name: &str, u: &Expression) -> Option<Expression> {
+    let u = u.clone();
+    let derivative = match name {
+        "sin" => call("cos", u),
+        "cos" => Negate(0, Box::new(call("sin", u))),
+        "tan" => divide(number(1), power(call("cos", u), number(2))),
+        "atan" => divide(number(1), add(number(1), power(u, number(2)))),
+        "exp" => call("exp", u),
+        "ln" => divide(number(1), u),
+        "log" => divide(number(1), multiply(u, call("ln", number(10)))),
+        "sqrt" => divide(number(1), multiply(number(2), call("sqrt", u))),
+        "abs" => divide(u.clone(), call("abs", u)),
+        _ => return None,
+    };
+    Some(derivative)
+}
+
+/// Differentiates an expression with respect to `x` by the sum, product,
+/// quotient and chain rules. The names it uses must already be filled in, so
+/// a call of any name but `x` that is not a known function is an error. The
+/// result is not simplified.
+pub fn derivative(expr: &Expression, x: &str) -> Result<Expression, Error> {
+    if !depends_on(expr, x) {
+        return Ok(number(0));
+    }
+    let d = |u: &Expression| derivative(u, x);
+    let product_rule = |u: &Expression, v: &Expression| -> Result<Expression, Error> {
+        Ok(add(multiply(d(u)?, v.clone()), multiply(u.clone(), d(v)?)))
+    };
+    match expr {
+        Variable(..) => Ok(number(1)),
+        Add(_, u, v) => Ok(add(d(u)?, d(v)?)),
+        Subtract(_, u, v) => Ok(subtract(d(u)?, d(v)?)),
+        Negate(_, u) => Ok(Negate(0, Box::new(d(u)?))),
+        Percent(_, u) => Ok(divide(d(u)?, number(100))),
+        Multiply(_, u, v) => product_rule(u, v),
+        Call(_, f, u) => match &**f {
+            Variable(_, name) if FUNCTIONS.contains(&name.as_str()) && !matches!(**u, Tuple(..)) => {
+                let outer = outer_derivative(name, u).ok_or(Error::NotDifferentiable)?;
+                Ok(multiply(outer, d(u)?))
+            },
+            Variable(..) if matches!(**u, Tuple(..)) => Err(Error::NotDifferentiable),
+            // Any other name left unbound is a function without a known derivative
+            Variable(_, name) if name != x => Err(Error::NotDifferentiable),
+            // Anything else is implicit multiplication
+            _ => product_rule(f, u),
+        },
+        Divide(_, u, v) => {
+            let numerator = subtract(multiply(d(u)?, (**v).clone()), multiply((**u).clone(), d(v)?));
+            Ok(divide(numerator, power((**v).clone(), number(2))))
+        },
+        Power(_, u, v) if !depends_on(v, x) => {
+            let lowered = power((**u).clone(), subtract((**v).clone(), number(1)));
+            Ok(multiply(multiply((**v).clone(), lowered), d(u)?))
+        },
+        Power(_, u, v) if !depends_on(u, x) => {
+            Ok(multiply(multiply(expr.clone(), call("ln", (**u).clone())), d(v)?))
+        },
+        // d(u^v) = u^v (v' ln(u) + v u' / u)
+        Power(_, u, v) => {
+            let log_term = multiply(d(v)?, call("ln", (**u).clone()));
+            let power_term = divide(multiply((**v).clone(), d(u)?), (**u).clone());
+            Ok(multiply(expr.clone(), add(log_term, power_term)))
+        },
+        _ => Err(Error::NotDifferentiable),
+    }
+}
+
+/// Whether an expression is a polynomial in `x`, with the other names as constants.
+fn is_polynomial_in(expr: &Expression, x: &str) -> bool {
+    match expr {
+        expr if !depends_on(expr, x) => true,
+        Variable(..) => true,
+        Add(_, u, v) | Subtract(_, u, v) | Multiply(_, u, v) => is_polynomial_in(u, x) && is_polynomial_in(v, x),
+        Negate(_, u) => is_polynomial_in(u, x),
+        Power(_, u, n) => is_polynomial_in(u, x) && matches!(**n, Number(_, _, 1)),
+        _ => false,
+    }
+}
+
+/// The slope `a` of an expression `a x + b` that is linear in `x`.
+fn slope(u: &Expression, x: &str) -> Option<Expression> {
+    let a = derivative(u, x).ok()?.simplify().ok()?;
+    match depends_on(&a, x) || a == number(0) {
+        true => None,
+        false => Some(a),
+    }
+}
+
+/// An antiderivative of a function that simplification knows, at `u`.
+fn outer_antiderivative(name: &str, u: &Expression) -> Option<Expression> {
+    let u = u.clone();
+    let antiderivative = match name {
+        "sin" => Negate(0, Box::new(call("cos", u))),
+        "cos" => call("sin", u),
+        "tan" => Negate(0, Box::new(call("ln", call("abs", call("cos", u))))),
+        "atan" => subtract(multiply(u.clone(), call("atan", u.clone())), divide(call("ln", add(number(1), power(u, number(2)))), number(2))),
+        "exp" => call("exp", u),
+        "ln" => subtract(multiply(u.clone(), call("ln", u.clone())), u),
+        "log" => divide(subtract(multiply(u.clone(), call("ln", u.clone())), u), call("ln", number(10))),
+        "sqrt" => multiply(divide(number(2), number(3)), power(u, divide(number(3), number(2)))),
+        "abs" => divide(multiply(u.clone(), call("abs", u)), number(2)),
+        _ => return None,
+    };
+    Some(antiderivative)
+}
+
+/// The antiderivative of `1 / u^n` for `u` linear in `x`.
+fn reciprocal_antiderivative(u: &Expression, n: &Expression, x: &str) -> Option<Expression> {
+    match u {
+        Power(_, w, m) if !depends_on(m, x) => reciprocal_antiderivative(w, &multiply((**m).clone(), n.clone()).simplify().ok()?, x),
+        u => antiderivative(&power(u.clone(), Negate(0, Box::new(n.clone()))), x),
+    }
+}
+
+/// Finds an antiderivative of an expression with respect to `x` by linearity,
+/// the power rule, the known functions of a linear argument, and integration by
+/// parts for a polynomial times one of those. The result is not simplified.
+pub fn antiderivative(expr: &Expression, x: &str) -> Option<Expression> {
+    if !depends_on(expr, x) {
+        return Some(multiply(expr.clone(), Variable(0, x.to_string())));
+    }
+    let integral = |u: &Expression| antiderivative(u, x);
+    match expr {
+        Variable(..) => Some(divide(power(expr.clone(), number(2)), number(2))),
+        Add(_, u, v) => Some(add(integral(u)?, integral(v)?)),
+        Subtract(_, u, v) => Some(subtract(integral(u)?, integral(v)?)),
+        Negate(_, u) => Some(Negate(0, Box::new(integral(u)?))),
+        Percent(_, u) => Some(divide(integral(u)?, number(100))),
+        Multiply(_, c, u) if !depends_on(c, x) => Some(multiply((**c).clone(), integral(u)?)),
+        Multiply(_, u, c) if !depends_on(c, x) => Some(multiply(integral(u)?, (**c).clone())),
+        // By parts, as the integral of p g is p G minus the integral of p' G
+        Multiply(_, p, g) | Multiply(_, g, p) if is_polynomial_in(p, x) && !is_polynomial_in(g, x) => {
+            let g_integral = integral(g)?.simplify().ok()?;
+            let rest = multiply(derivative(p, x).ok()?, g_integral.clone()).simplify().ok()?;
+            Some(subtract(multiply((**p).clone(), g_integral), integral(&rest)?))
+        },
+        Divide(_, u, c) if !depends_on(c, x) => Some(divide(integral(u)?, (**c).clone())),
+        Divide(_, c, u) if !depends_on(c, x) => Some(multiply((**c).clone(), reciprocal_antiderivative(u, &number(1), x)?)),
+        Power(_, u, n) if !depends_on(n, x) => {
+            let a = slope(u, x)?;
+            match n.simplify().ok()? {
+                Number(_, -1, 1) => Some(divide(call("ln", call("abs", (**u).clone())), a)),
+                Negate(_, one) if *one == number(1) => Some(divide(call("ln", call("abs", (**u).clone())), a)),
+                n => {
+                    let raised = add(n, number(1));
+                    Some(divide(power((**u).clone(), raised.clone()), multiply(raised, a)))
+                },
+            }
+        },
+        Power(_, c, u) if !depends_on(c, x) => {
+            let a = slope(u, x)?;
+            Some(divide(expr.clone(), multiply(a, call("ln", (**c).clone()))))
+        },
+        Call(_, f, u) => match &**f {
+            Variable(_, name) if FUNCTIONS.contains(&name.as_str()) && !matches!(**u, Tuple(..)) => {
+                Some(divide(outer_antiderivative(name, u)?, slope(u, x)?))
+            },
+            Variable(..) if matches!(**u, Tuple(..)) => None,
+            // Anything else is implicit multiplication
+            _ => integral(&multiply((**f).clone(), (**u).clone())),
+        },
+        _ => None,
+    }
+}
+
+fn single(values: Result<Vec<Value>, Error>) -> Option<Value> {
+    values.ok()?.into_iter().next()
+}
+
+/// Evaluates the derivative of a polynomial, given by its coefficients from the
+/// highest power down, at `r`.
+fn derivative_at(coefficients: &[Value], r: &Value) -> Option<Value> {
+    let n = coefficients.len() - 1;
+    let mut sum = Value::integer(0);
+    for (i, c) in coefficients[..n].iter().enumerate() {
+        let k = (n - i) as isize;
+        let term = single(eval::multiply(Value::integer(k), c.clone()))?;
+        let term = single(eval::multiply(term, single(eval::power(r.clone(), Value::integer(k - 1)))?))?;
+        sum = single(eval::add(sum, term))?;
+    }
+    Some(sum)
+}
+
+/// Integrates a quotient `p / q` of polynomials in `x` by partial fractions, when
+/// `q` has distinct rational roots or is a quadratic with no real roots.
+pub fn rational_antiderivative(p: Value, q: Value, x: &str) -> Option<Expression> {
+    let variable = Value::Polynomial(Polynomial::variable(x));
+    let in_x = |p: &Value| Value::Tuple(vec![p.clone(), variable.clone()]);
+    let coefficients = |p: &Value| match single(polynomials::coeffs(in_x(p))) {
+        Some(Value::List(cs)) => Some(cs),
+        _ => None,
+    };
+    let whole = single(polynomials::quotient(p.clone(), q.clone()))?;
+    let rest = single(polynomials::remainder(p, q.clone()))?;
+    let whole_integral = antiderivative(&to_expression(&whole).ok()?, x)?;
+    let divisor = coefficients(&q)?;
+    let roots = polynomials::roots(in_x(&q)).ok()?;
+    let expression = |c: &Value| to_expression(c).ok();
+    let mut sum = whole_integral;
+    if roots.len() + 1 == divisor.len() {
+        // The residue at a simple root r is p(r) / q'(r)
+        for r in roots {
+            let numerator = match &rest {
+                Value::Polynomial(rest) => single(polynomials::call(rest, r.clone()))?,
+                c => c.clone(),
+            };
+            let residue = single(eval::divide(numerator, derivative_at(&divisor, &r)?))?;
+            let log = call("ln", call("abs", subtract(Variable(0, x.to_string()), expression(&r)?)));
+            sum = add(sum, multiply(expression(&residue)?, log));
+        }
+        return Some(sum);
+    }
+    // A quadratic a x^2 + b x + c with no real roots has 4ac - b^2 > 0, and
+    // (m x + n) / q splits into a multiple of q' / q and of 1 / q
+    let (a, b, c) = match &divisor[..] {
+        [a, b, c] => (a.clone(), b.clone(), c.clone()),
+        _ => return None,
+    };
+    let product = single(eval::multiply(Value::integer(4), single(eval::multiply(a.clone(), c))?))?;
+    let discriminant = single(eval::subtract(product, single(eval::multiply(b.clone(), b.clone()))?))?;
+    if eval::to_real(&discriminant).ok()?.0 <= 0 {
+        return None;
+    }
+    let (m, n) = match &coefficients(&rest)?[..] {
+        [n] => (Value::integer(0), n.clone()),
+        [m, n] => (m.clone(), n.clone()),
+        _ => return None,
+    };
+    let k = single(eval::divide(m, single(eval::multiply(Value::integer(2), a.clone()))?))?;
+    let l = single(eval::subtract(n, single(eval::multiply(k.clone(), b.clone()))?))?;
+    let root = power(expression(&discriminant)?, divide(number(1), number(2)));
+    let linear = add(multiply(expression(&single(eval::multiply(Value::integer(2), a))?)?, Variable(0, x.to_string())), expression(&b)?);
+    let log = multiply(expression(&k)?, call("ln", call("abs", expression(&q)?)));
+    let arctangent = multiply(divide(multiply(number(2), expression(&l)?), root.clone()), call("atan", divide(linear, root)));
+    Some(add(sum, add(log, arctangent)))
+}
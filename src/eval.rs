@@ -1,67 +1,260 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use crate::nodes::Expression;
+use crate::nodes::{self, Expression};
 use crate::nodes::ParseError;
+use crate::format::{self, Format};
 use crate::values::Value;
-use crate::values::gcd;
+use crate::values::{rational_add, rational_subtract, rational_multiply, rational_divide};
+use crate::values::{integer_root, to_f64, approximate_bounded};
+use crate::builtins::{from_f64, from_f64_pair, arguments};
+use crate::integers;
+use crate::random::Random;
+use crate::units::{self, Unit};
+use crate::uncertainty;
+use crate::intervals;
+use crate::polynomials::{self, Polynomial};
+use crate::symbolic;
+use crate::calculus;
+use crate::numeric::{self, Complex};
+use crate::systems;
+use crate::series::{self, Extended};
 
 #[derive(Debug)]
 pub enum Error {
-    ParseError(ParseError),
+    // Only read when the error is printed
+    ParseError(#[allow(dead_code)] ParseError),
     MismatchedType,
+    DivisionByZero,
+    Overflow,
+    UnknownConversion,
+    NotAnInteger,
+    OutOfDomain,
+    WrongArgumentCount,
+    NoInverse,
+    NotReal,
+    TooManyElements,
+    DimensionMismatch,
+    NotDivisible,
+    NotDifferentiable,
+    NoSolution,
+    Indeterminate,
+    NotBracketed,
+    NoConvergence,
+    NoLimit,
 }
 
+/// The longest list a range is allowed to make, and the most terms a sum or
+/// product over a range takes.
+pub const MAX_RANGE_LENGTH: usize = 1_000_000;
+
+/// The name that `∞` and `inf` are read as, which limits and series take as
+/// a point at infinity.
+pub const INFINITY: &str = "∞";
+
+/// How many starting points systems of equations are solved numerically from,
+/// and the seed they are drawn with, so that the same system always gives the
+/// same solutions.
+const SYSTEM_STARTS: usize = 60;
+const SYSTEM_SEED: u64 = 48;
+
 pub struct Evaluator {
     definitions: Vec<HashMap<String, Vec<Value>>>,
+    /// The modulus that results are reduced by, if arithmetic is modular.
+    modulus: Option<isize>,
+    /// The generator behind the built-in functions that draw random numbers.
+    random: Random,
+    /// Whether undefined unit names are read as units, which they only are in
+    /// a number with units or a unit to convert into.
+    units: bool,
 }
 
 impl Evaluator {
     pub fn new() -> Evaluator {
         Evaluator {
             definitions: vec![HashMap::new()],
+            modulus: None,
+            random: Random::from_time(),
+            units: false,
         }
     }
 
+    pub fn set_modulus(&mut self, modulus: Option<isize>) {
+        self.modulus = modulus;
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.random = Random::new(seed);
+    }
+
+    pub fn random(&mut self) -> &mut Random {
+        &mut self.random
+    }
+
     pub fn evaluate(&mut self, code: String) -> Result<Vec<Value>, Error> {
         let mut parser = crate::parser::Parser::new(code);
-        let values;
         let expression_result = parser.parse();
         match expression_result {
-            Ok(expression) => values = self.evaluate_expression(expression),
-            Err(e) => {
-                println!("Error: {:?}", e);
-                return Err(Error::ParseError(e));
-            }
+            Ok(expression) => {
+                let output_format = self.output_format(&expression);
+                let values = self.evaluate_expression(expression)?;
+                if output_format.is_some() {
+                    format::set_override(output_format);
+                }
+                Ok(values)
+            },
+            Err(e) => Err(Error::ParseError(e)),
         }
+    }
+
+    /// Evaluates an expression with undefined unit names read as units.
+    fn with_units(&mut self, expr: Expression) -> Result<Vec<Value>, Error> {
+        let units = std::mem::replace(&mut self.units, true);
+        let values = self.evaluate_expression(expr);
+        self.units = units;
         values
     }
 
-    fn evaluate_expression(&mut self, expr: Expression) -> Result<Vec<Value>, Error> {
+    /// Checks whether an expression is a number with units, like `3 m / 2 s` or
+    /// `9.81 m/s^2`, which is a product or quotient of numbers and undefined unit
+    /// names with at least one of each. Elsewhere a unit name like `m` or `g`
+    /// is free to be a variable.
+    fn is_quantity(&self, expr: &Expression) -> bool {
+        let (mut numbers, mut units) = (0, 0);
+        matches!(expr, Expression::Multiply(..) | Expression::Divide(..) | Expression::Call(..))
+            && self.quantity_factors(expr, &mut numbers, &mut units)
+            && numbers > 0 && units > 0
+    }
+
+    /// Counts the numbers and units that a number with units is made of, or
+    /// gives false if it has anything else in it.
+    fn quantity_factors(&self, expr: &Expression, numbers: &mut usize, units: &mut usize) -> bool {
+        match expr {
+            Expression::Number(..) => {
+                *numbers += 1;
+                true
+            },
+            Expression::Variable(_, name) => match self.get_definition(name.clone()).as_deref() {
+                Some([Value::ComplexNumber(..) | Value::Quantity(..)]) => {
+                    *numbers += 1;
+                    true
+                },
+                None if Unit::named(name).is_some() => {
+                    *units += 1;
+                    true
+                },
+                _ => false,
+            },
+            Expression::Multiply(_, x, y) | Expression::Divide(_, x, y) | Expression::Call(_, x, y) => {
+                self.quantity_factors(x, numbers, units) && self.quantity_factors(y, numbers, units)
+            },
+            Expression::Power(_, x, n) => matches!(**n, Expression::Number(..) | Expression::Negate(..)) && self.quantity_factors(x, numbers, units),
+            Expression::Negate(_, x) => self.quantity_factors(x, numbers, units),
+            _ => false,
+        }
+    }
+
+    /// Reads the format that the result of a whole expression is printed in,
+    /// which a conversion only asks for when it is outermost, so that one in a
+    /// function body or a list does not change how the rest is printed.
+    fn output_format(&self, expr: &Expression) -> Option<Format> {
+        match expr {
+            Expression::Convert(_, _, target) => self.conversion_format(*target.clone()),
+            Expression::Define(_, l, r) if matches!(**l, Expression::Variable(..)) => self.output_format(r),
+            _ => None,
+        }
+    }
+
+    pub fn evaluate_expression(&mut self, expr: Expression) -> Result<Vec<Value>, Error> {
+        if !self.units && self.is_quantity(&expr) {
+            return self.with_units(expr);
+        }
         let mut values = Vec::<Value>::new();
         match expr {
             Expression::Define(_, l, r) => {
-                values.extend(self.define(l, r)?);
+                values.extend(self.define(*l, *r)?);
+            },
+            Expression::Convert(_, x, target) => {
+                let x_values = self.evaluate_expression(*x)?;
+                match self.conversion_format(*target.clone()) {
+                    Some(_) => values.extend(x_values),
+                    // Anything that is not a format is a unit to convert into
+                    None => match self.with_units(*target)? {
+                        targets if targets.iter().all(|x| !matches!(x, Value::Quantity(..))) => return Err(Error::UnknownConversion),
+                        targets => values.extend(apply2(&units::convert, x_values, targets)?),
+                    },
+                }
             },
             Expression::Function(_, x, f) => values.push(Value::Function(*x, *f)),
             Expression::Call(_, f, x) => values.extend(self.call(*f, *x)?),
             Expression::Multiply(_, x, y) => values.extend(self.eval2(&multiply, *x, *y)?),
-            Expression::Divide(_, x, y) => values.extend(self.eval2(&divide, *x, *y)?),
+            Expression::Divide(_, x, y) => match self.modulus {
+                Some(m) => values.extend(self.eval2(&|x, y| modular_divide(x, y, m), *x, *y)?),
+                None => values.extend(self.eval2(&divide, *x, *y)?),
+            },
+            Expression::Power(_, x, y) => {
+                let x_values = self.evaluate_expression(*x)?;
+                // Exponents are never reduced by the modulus
+                let modulus = self.modulus.take();
+                let y_values = self.evaluate_expression(*y);
+                self.modulus = modulus;
+                match modulus {
+                    Some(m) => values.extend(apply2(&|x, y| modular_power(x, y, m), x_values, y_values?)?),
+                    None => values.extend(apply2(&power, x_values, y_values?)?),
+                }
+            },
+            Expression::IntegerDivide(_, x, y) => values.extend(self.eval2(&integer_divide, *x, *y)?),
+            Expression::Modulo(_, x, y) => {
+                let y_values = self.evaluate_expression(*y)?;
+                // A quotient or power taken modulo an integer is worked out in
+                // modular arithmetic, so that `1/2 mod 7` is the inverse of 2
+                if is_modular(&x) && !y_values.iter().any(|m| matches!(m, Value::Polynomial(_))) {
+                    values.extend(self.modular(*x, y_values)?);
+                } else {
+                    values.extend(apply2(&modulo, self.evaluate_expression(*x)?, y_values)?);
+                }
+            },
+            Expression::ShiftLeft(_, x, y) => values.extend(self.eval2(&shift_left, *x, *y)?),
+            Expression::ShiftRight(_, x, y) => values.extend(self.eval2(&shift_right, *x, *y)?),
+            Expression::BitAnd(_, x, y) => values.extend(self.eval2(&bit_and, *x, *y)?),
+            Expression::BitOr(_, x, y) => values.extend(self.eval2(&bit_or, *x, *y)?),
+            Expression::BitXor(_, x, y) => values.extend(self.eval2(&bit_xor, *x, *y)?),
+            Expression::Not(_, x) => values.extend(self.eval1(&not, *x)?),
+            Expression::Factorial(_, x) => values.extend(self.eval1(&factorial, *x)?),
+            Expression::Derivative(_, f) => {
+                for f in self.evaluate_expression(*f)? {
+                    values.push(self.derivative_of(f)?);
+                }
+            },
             Expression::PlusMinus(_, x) => {
                 values.extend(self.eval1(&negate, *x.clone())?);
                 values.extend(self.eval1(&|x| Ok(vec![x]), *x)?);
             },
+            Expression::Uncertainty(_, x, e) => values.extend(self.eval2(&uncertainty::measurement, *x, *e)?),
             Expression::Negate(_, x) => values.extend(self.eval1(&negate, *x)?),
             Expression::Add(_, x, y) => values.extend(self.eval2(&add, *x, *y)?),
             Expression::Subtract(_, x, y) => values.extend(self.eval2(&subtract, *x, *y)?),
-            Expression::Number(_, dividend, divisor) => values.push(Value::ComplexNumber(dividend, divisor, 0, 1)),
+            Expression::Number(_, dividend, divisor) => values.push(Value::rational(dividend, divisor)),
+            Expression::Constant(_, name) => match nodes::constant_value(name) {
+                Some((a, b)) => values.push(Value::rational(a, b)),
+                None => return Err(Error::MismatchedType),
+            },
             Expression::ImaginaryConstant(_) => values.push(Value::ComplexNumber(0, 1, 1, 1)),
             Expression::Variable(_, name) => {
-                let result = self.get_definition(name);
-                match result {
-                    Some(value) => values.extend(value),
-                    None => {},
+                if let Some(value) = self.get_definition(name.clone()) {
+                    values.extend(value);
+                } else if let Some(unit) = Unit::named(&name).filter(|_| self.units) {
+                    values.push(Value::Quantity(1, 1, unit));
+                } else {
+                    // A free variable stands for itself, as a polynomial
+                    values.push(Value::Polynomial(Polynomial::variable(&name)));
                 }
             },
             Expression::Boolean(b) => values.push(Value::Boolean(b)),
+            // A tuple of equations is a system, solved for all its unknowns
+            Expression::Tuple(_, xs) if xs.len() > 1 && xs.iter().all(is_equation) => {
+                let unknowns = self.unknowns(&xs)?;
+                values.extend(self.solve_system(&xs, &unknowns)?);
+            },
             Expression::Tuple(_, xs) => {
                 let mut tuple_values = Vec::new();
                 for expr in xs {
@@ -70,12 +263,128 @@ impl Evaluator {
                 }
                 values.push(Value::Tuple(tuple_values))
             },
+            Expression::List(_, xs) => {
+                let mut list_values = Vec::new();
+                for expr in xs {
+                    list_values.extend(self.evaluate_expression(expr)?);
+                }
+                values.push(Value::List(list_values))
+            },
+            Expression::Interval(_, lower, upper, l, r) => values.extend(self.eval2(&|x, y| intervals::between(x, y, (l, r)), *lower, *upper)?),
+            Expression::Range(_, start, next, end) => values.extend(self.range(*start, next.map(|x| *x), *end)?),
+            Expression::Equal(_, x, y) => values.extend(self.equality(*x, *y)?),
+            Expression::NotEqual(_, x, y) => values.extend(self.eval2(&|x, y| Ok(vec![Value::Boolean(!equal(x, y)?)]), *x, *y)?),
+            Expression::LessThan(_, x, y) => values.extend(self.eval2(&|x, y| compare(x, y, &[Ordering::Less]), *x, *y)?),
+            Expression::GreaterThan(_, x, y) => values.extend(self.eval2(&|x, y| compare(x, y, &[Ordering::Greater]), *x, *y)?),
+            Expression::LessThanEqual(_, x, y) => values.extend(self.eval2(&|x, y| compare(x, y, &[Ordering::Less, Ordering::Equal]), *x, *y)?),
+            Expression::GreaterThanEqual(_, x, y) => values.extend(self.eval2(&|x, y| compare(x, y, &[Ordering::Greater, Ordering::Equal]), *x, *y)?),
+            Expression::And(_, x, y) => values.extend(self.eval2(&and, *x, *y)?),
+            Expression::Or(_, x, y) => values.extend(self.eval2(&or, *x, *y)?),
             _ => {},
         }
+        match self.modulus {
+            Some(m) => values.into_iter().map(|x| reduce_modulo(x, m)).collect(),
+            None => Ok(values),
+        }
+    }
+
+    /// Reads the output format asked for by the right side of `in`, which is a
+    /// format name optionally followed by a number of digits, like `decimal 5`.
+    fn conversion_format(&self, target: Expression) -> Option<Format> {
+        match target {
+            Expression::Variable(_, name) => format::current().with(&name, None),
+            Expression::Call(_, name, digits) => match (*name, *digits) {
+                (Expression::Variable(_, name), Expression::Number(_, n, 1)) if n >= 0 => {
+                    format::current().with(&name, Some(n as usize))
+                },
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Evaluates a range into a list running from `start` to `end`, in steps of
+    /// one or of the distance between `start` and `next`.
+    fn range(&mut self, start: Expression, next: Option<Expression>, end: Expression) -> Result<Vec<Value>, Error> {
+        let start = self.evaluate_real(start)?;
+        let end = self.evaluate_real(end)?;
+        let step = match next {
+            Some(next) => rational_subtract(self.evaluate_real(next)?, start).ok_or(Error::Overflow)?,
+            None => (1, 1),
+        };
+        if step.0 == 0 {
+            return Err(Error::OutOfDomain);
+        }
+        let mut list = Vec::new();
+        let mut x = start;
+        while (step.0 > 0 && real_order(x, end) != Ordering::Greater) || (step.0 < 0 && real_order(x, end) != Ordering::Less) {
+            if list.len() == MAX_RANGE_LENGTH {
+                return Err(Error::TooManyElements);
+            }
+            list.push(Value::rational(x.0, x.1));
+            x = rational_add(x, step).ok_or(Error::Overflow)?;
+        }
+        Ok(vec![Value::List(list)])
+    }
+
+    /// Evaluates an expression that must have a single real value.
+    pub fn evaluate_real(&mut self, expr: Expression) -> Result<(isize, isize), Error> {
+        match &self.evaluate_expression(expr)?[..] {
+            [x] => to_real(x),
+            _ => Err(Error::MismatchedType),
+        }
+    }
+
+    /// Calls a function value, binding its parameters to the argument in a new scope.
+    pub fn call_value(&mut self, f: Value, x: Value) -> Result<Vec<Value>, Error> {
+        match f {
+            Value::Function(param, body) => {
+                self.increase_scope();
+                let result = self.bind_parameter(param, x).and_then(|_| self.evaluate_expression(body));
+                self.decrease_scope();
+                result
+            },
+            _ => Err(Error::MismatchedType),
+        }
+    }
+
+    fn bind_parameter(&mut self, param: Expression, x: Value) -> Result<(), Error> {
+        match (param, x) {
+            (Expression::Variable(_, name), x) => {
+                self.bind(name, vec![x]);
+                Ok(())
+            },
+            (Expression::Tuple(_, params), Value::Tuple(xs)) if params.len() == xs.len() => {
+                for (param, x) in params.into_iter().zip(xs) {
+                    self.bind_parameter(param, x)?;
+                }
+                Ok(())
+            },
+            _ => Err(Error::MismatchedType),
+        }
+    }
+
+    /// Evaluates `x` with arithmetic reduced modulo each of `moduli`, so that
+    /// division uses the modular inverse.
+    pub fn modular(&mut self, x: Expression, moduli: Vec<Value>) -> Result<Vec<Value>, Error> {
+        let mut values = Vec::new();
+        for m in moduli {
+            let m = match to_integer(&m)? {
+                0 => return Err(Error::DivisionByZero),
+                m => m,
+            };
+            let modulus = self.modulus.replace(m);
+            let x_values = self.evaluate_expression(x.clone());
+            self.modulus = modulus;
+            for x_value in x_values? {
+                values.push(reduce_modulo(x_value, m)?);
+            }
+        }
+        values.dedup();
         Ok(values)
     }
 
-    fn get_definition(&self, name: String) -> Option<Vec<Value>> {
+    pub fn get_definition(&self, name: String) -> Option<Vec<Value>> {
         for scope in self.definitions.iter().rev() {
             match scope.get(&name) {
                 Some(x) => return Some(x.clone()),
@@ -85,58 +394,698 @@ impl Evaluator {
         None
     }
 
-    fn _increase_scope(&mut self) {
+    pub fn increase_scope(&mut self) {
         self.definitions.push(HashMap::new());
     }
 
-    fn _decrease_scope(&mut self) {
+    pub fn decrease_scope(&mut self) {
         self.definitions.pop();
     }
 
-    fn define(&mut self, l: Box<Expression>, r: Box<Expression>) -> Result<Vec<Value>, Error> {
+    /// Defines a variable in the innermost scope.
+    pub fn bind(&mut self, name: String, values: Vec<Value>) {
+        self.definitions.last_mut().unwrap().insert(name, values);
+    }
+
+    fn define(&mut self, l: Expression, r: Expression) -> Result<Vec<Value>, Error> {
         let mut values = Vec::<Value>::new();
-        match *l {
+        match l {
             Expression::Variable(_, name) => {
-                let value = self.evaluate_expression(*r.clone())?;
-                self.definitions.last_mut().unwrap().insert(name, value);
-                values.extend(self.evaluate_expression(*r)?);
+                // Evaluated once, so that a random value shown is the one stored
+                let value = self.evaluate_expression(r)?;
+                self.definitions.last_mut().unwrap().insert(name, value.clone());
+                values.extend(value);
             },
-            Expression::Call(i, f, x) => {
-                let closure = Expression::Function(i, x.clone(), r);
-                values.extend(self.define(f, Box::new(closure))?);
+            Expression::Call(i, f, x) if matches!(*f, Expression::Variable(..)) => {
+                let closure = Expression::Function(i, x.clone(), Box::new(r));
+                values.extend(self.define(*f, closure)?);
+            },
+            // Anything else is an equation, which is solved for its one unknown,
+            // or checked if it has none
+            l => {
+                let equation = Expression::Define(0, Box::new(l.clone()), Box::new(r.clone()));
+                match &self.unknowns(std::slice::from_ref(&equation))?[..] {
+                    [] => values.extend(self.eval2(&|x, y| Ok(vec![Value::Boolean(equal(x, y)?)]), l, r)?),
+                    [x] => values.extend(self.solve(&equation, x)?),
+                    _ => return Err(Error::MismatchedType),
+                }
             },
-            _ => {},
         }
         Ok(values)
     }
 
+    /// Compares the sides of `l == r`. When they are polynomials or symbolic
+    /// values that differ and there is one unknown, the equation is solved
+    /// for it instead, as in `x^2 == 4`.
+    fn equality(&mut self, l: Expression, r: Expression) -> Result<Vec<Value>, Error> {
+        let (x_values, y_values) = (self.evaluate_expression(l.clone())?, self.evaluate_expression(r.clone())?);
+        let symbolic = x_values.iter().chain(&y_values).any(|x| matches!(x, Value::Polynomial(_) | Value::Symbolic(_)));
+        let comparison = apply2(&|x, y| Ok(vec![Value::Boolean(equal(x, y)?)]), x_values, y_values)?;
+        if symbolic && comparison != [Value::Boolean(true)] {
+            let equation = Expression::Equal(0, Box::new(l), Box::new(r));
+            if let [x] = &self.unknowns(std::slice::from_ref(&equation))?[..] {
+                return self.solve(&equation, x);
+            }
+        }
+        Ok(comparison)
+    }
+
     fn call(&mut self, x: Expression, y: Expression) -> Result<Vec<Value>, Error> {
+        if let Expression::Variable(_, name) = &x {
+            if self.get_definition(name.clone()).is_none() {
+                if let Some(result) = self.call_builtin(name, y.clone()) {
+                    return result;
+                }
+            }
+        }
         let x_values = self.evaluate_expression(x.clone())?;
+        let is_defined = match &x {
+            Expression::Variable(_, name) => self.get_definition(name.clone()).is_some(),
+            Expression::Derivative(..) => true,
+            _ => false,
+        };
         match (&x_values[..], y.clone()) {
             ([Value::Function(param, expr)], _) => self.call_function(param, expr, y),
+            // Only a named polynomial or its derivative is called, so that `x y` is
+            // still a product
+            ([Value::Polynomial(p)], _) if is_defined => {
+                let p = p.clone();
+                self.eval1(&|x| polynomials::call(&p, x), y)
+            },
+            ([Value::Symbolic(expr)], _) if is_defined => {
+                let expr = expr.clone();
+                let mut values = Vec::new();
+                for x in self.evaluate_expression(y)? {
+                    values.extend(self.call_symbolic(&expr, x)?);
+                }
+                values.dedup();
+                Ok(values)
+            },
             _ => self.eval2(&multiply, x, y),
         }
     }
 
     fn call_function(&mut self, param: &Expression, expr: &Expression, y: Expression) -> Result<Vec<Value>, Error> {
-        let new_expr = match param {
-            Expression::Tuple(_, param_tuple) => {
-                if let Expression::Tuple(_, input_tuple) = y {
-                    let mut iter_expr = Box::new(expr.clone());
-                    for (param_item, input_item) in param_tuple.iter().zip(input_tuple) {
-                        iter_expr = iter_expr.sub(param_item, &input_item);
+        let new_expr = substitute(param, expr, &y)?;
+        self.evaluate_expression(new_expr)
+    }
+
+    /// Replaces the defined names in an expression with their values, and calls
+    /// of defined functions with their bodies, leaving only free variables.
+    pub fn inline(&self, expr: &Expression) -> Result<Expression, Error> {
+        match expr {
+            Expression::Variable(_, name) => match self.get_definition(name.clone()).as_deref() {
+                None | Some([Value::Function(..)]) => Ok(expr.clone()),
+                Some([value]) => symbolic::to_expression(value),
+                Some(_) => Err(Error::MismatchedType),
+            },
+            Expression::Call(_, f, x) => match self.function_named(f) {
+                Some((param, body)) => self.inline(&substitute(&param, &body, &self.inline(x)?)?),
+                None => expr.try_map(&mut |x| self.inline(x)),
+            },
+            // Parameters are not free, so a function is left as it is
+            Expression::Function(..) => Ok(expr.clone()),
+            _ => expr.try_map(&mut |x| self.inline(x)),
+        }
+    }
+
+    /// Looks up the parameter and body of the function a name is defined as.
+    fn function_named(&self, f: &Expression) -> Option<(Expression, Expression)> {
+        match f {
+            Expression::Variable(_, name) => match self.get_definition(name.clone()).as_deref() {
+                Some([Value::Function(param, body)]) => Some((param.clone(), body.clone())),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Simplifies an expression with its defined names filled in. A quotient of
+    /// polynomials is evaluated, so that division cancels their common factors,
+    /// and so is an expression with no free variables, which is a number.
+    pub fn simplify(&mut self, expr: &Expression) -> Result<Value, Error> {
+        let simplified = self.inline(expr)?.simplify()?;
+        if simplified.variables().is_empty() {
+            match self.evaluate_expression(simplified.clone()).as_deref() {
+                Ok([value]) => return Ok(value.clone()),
+                // Rationals too large to combine exactly are combined in floating point
+                Err(Error::Overflow) => return symbolic::approximate(&Value::Symbolic(simplified)),
+                _ => {},
+            }
+        }
+        match has_variable_divisor(&simplified) {
+            true => Ok(self.value_of(simplified)),
+            false => Ok(symbolic::from_expression(simplified)),
+        }
+    }
+
+    /// Evaluates an expression and multiplies out the products and powers of
+    /// sums in its values. Polynomials are already kept expanded.
+    pub fn expand(&mut self, expr: Expression) -> Result<Vec<Value>, Error> {
+        let evaluated = match self.evaluate_expression(expr.clone()) {
+            // A quotient of polynomials that do not divide, or a polynomial raised
+            // to a power that is not an integer, is expanded as written
+            Err(Error::NotDivisible | Error::NotAnInteger) => vec![Value::Symbolic(self.inline(&expr)?)],
+            values => values?,
+        };
+        let mut values = Vec::new();
+        for value in evaluated {
+            values.push(match value {
+                Value::Symbolic(x) => self.value_of(symbolic::expand(&x)?.simplify()?),
+                x @ (Value::ComplexNumber(..) | Value::Polynomial(_)) => x,
+                _ => return Err(Error::MismatchedType),
+            });
+        }
+        Ok(values)
+    }
+
+    /// Makes a value of a simplified expression, evaluating it with its variables
+    /// left free if it is a polynomial, so that it can be used like one.
+    pub fn value_of(&mut self, expr: Expression) -> Value {
+        if is_polynomial(&expr) {
+            if let Ok([value @ (Value::ComplexNumber(..) | Value::Polynomial(_))]) = self.evaluate_free(&expr, &expr.variables()).as_deref() {
+                return value.clone();
+            }
+        }
+        symbolic::from_expression(expr)
+    }
+
+    /// Evaluates an expression with the variables `free` standing for themselves,
+    /// even where they have definitions.
+    fn evaluate_free(&mut self, expr: &Expression, free: &[String]) -> Result<Vec<Value>, Error> {
+        self.increase_scope();
+        for name in free {
+            self.bind(name.clone(), vec![Value::Polynomial(Polynomial::variable(name))]);
+        }
+        let values = self.evaluate_expression(expr.clone());
+        self.decrease_scope();
+        values
+    }
+
+    /// Differentiates an expression with respect to `x`, with the names it uses
+    /// other than `x` filled in.
+    pub fn derivative(&mut self, expr: &Expression, x: &str) -> Result<Expression, Error> {
+        self.increase_scope();
+        self.bind(x.to_string(), vec![Value::Polynomial(Polynomial::variable(x))]);
+        let expr = self.inline(expr);
+        self.decrease_scope();
+        calculus::derivative(&expr?, x)?.simplify()
+    }
+
+    /// Takes the derivative `f'` of a function of one variable, or of a
+    /// polynomial or symbolic value in one variable.
+    fn derivative_of(&mut self, f: Value) -> Result<Value, Error> {
+        match f {
+            Value::Function(Expression::Variable(i, x), body) => {
+                let derivative = self.derivative(&body, &x)?;
+                Ok(Value::Function(Expression::Variable(i, x), derivative))
+            },
+            f @ (Value::Polynomial(_) | Value::Symbolic(_)) => {
+                let expr = symbolic::to_expression(&f)?;
+                match &expr.variables()[..] {
+                    [x] => {
+                        let derivative = calculus::derivative(&expr, x)?.simplify()?;
+                        Ok(self.value_of(derivative))
+                    },
+                    _ => Err(Error::MismatchedType),
+                }
+            },
+            _ => Err(Error::MismatchedType),
+        }
+    }
+
+    /// Calls a symbolic value, with its variables in alphabetical order set to
+    /// the arguments.
+    fn call_symbolic(&mut self, expr: &Expression, x: Value) -> Result<Vec<Value>, Error> {
+        let names = expr.variables();
+        let arguments = arguments(x);
+        if names.len() != arguments.len() {
+            return Err(Error::WrongArgumentCount);
+        }
+        self.increase_scope();
+        for (name, x) in names.into_iter().zip(arguments) {
+            self.bind(name, vec![x]);
+        }
+        let values = self.evaluate_expression(expr.clone());
+        self.decrease_scope();
+        values
+    }
+
+    /// The variable and body of a function of one variable, or of a polynomial
+    /// or symbolic value in one variable, with the other names filled in.
+    fn variable_and_body(&mut self, f: &Value) -> Result<(String, Expression), Error> {
+        match f {
+            Value::Function(Expression::Variable(_, x), body) => {
+                self.increase_scope();
+                self.bind(x.clone(), vec![Value::Polynomial(Polynomial::variable(x))]);
+                let body = self.inline(body);
+                self.decrease_scope();
+                Ok((x.clone(), body?.simplify()?))
+            },
+            f @ (Value::Polynomial(_) | Value::Symbolic(_)) => {
+                let expr = symbolic::to_expression(f)?;
+                match &expr.variables()[..] {
+                    [x] => Ok((x.clone(), expr)),
+                    _ => Err(Error::MismatchedType),
+                }
+            },
+            _ => Err(Error::MismatchedType),
+        }
+    }
+
+    /// Evaluates a function at a real point in floating point, from its body
+    /// where it can, and otherwise by calling it with a nearby rational.
+    fn sample(&mut self, f: &Value, body: Option<&(String, Expression)>, t: f64) -> Result<f64, Error> {
+        if let Some(y) = body.and_then(|(x, body)| numeric::evaluate(body, x, t)) {
+            return Ok(y);
+        }
+        let (p, q) = approximate_bounded(t, 1 << 20).ok_or(Error::Overflow)?;
+        match &self.call_value(f.clone(), Value::rational(p, q))?[..] {
+            [y] => Ok(to_f64(to_real(y)?)),
+            _ => Err(Error::MismatchedType),
+        }
+    }
+
+    /// Evaluates a function at a complex point like `sample` does.
+    fn sample_complex(&mut self, f: &Value, body: Option<&(String, Expression)>, z: Complex) -> Result<Complex, Error> {
+        if let Some(y) = body.and_then(|(x, body)| numeric::evaluate_complex(body, x, z)) {
+            return Ok(y);
+        }
+        let (a, b) = approximate_bounded(z.re, 1 << 20).ok_or(Error::Overflow)?;
+        let (c, d) = approximate_bounded(z.im, 1 << 20).ok_or(Error::Overflow)?;
+        match &self.call_value(f.clone(), Value::complex(a, b, c, d))?[..] {
+            [y] => to_complex(y),
+            _ => Err(Error::MismatchedType),
+        }
+    }
+
+    /// Finds a root of a function, near a starting point by Newton's method, or
+    /// between two points where it changes sign by Brent's method.
+    pub fn find_root(&mut self, f: Value, points: &[Value]) -> Result<Value, Error> {
+        let body = self.variable_and_body(&f).ok();
+        match points {
+            [start] => {
+                // The derivative is taken symbolically where it can be, and
+                // otherwise by a central difference
+                let derivative = body.as_ref().and_then(|(x, body)| Some((x.clone(), calculus::derivative(body, x).ok()?.simplify().ok()?)));
+                let mut f_and_derivative = |z: Complex| -> Result<(Complex, Complex), Error> {
+                    let y = self.sample_complex(&f, body.as_ref(), z)?;
+                    let dy = match derivative.as_ref().and_then(|(x, d)| numeric::evaluate_complex(d, x, z)) {
+                        Some(dy) => dy,
+                        None => {
+                            let h = Complex::new(1e-6 * z.abs().max(1.0), 0.0);
+                            let difference = self.sample_complex(&f, body.as_ref(), z + h)? - self.sample_complex(&f, body.as_ref(), z - h)?;
+                            difference / (h + h)
+                        },
+                    };
+                    Ok((y, dy))
+                };
+                let root = numeric::newton(&mut f_and_derivative, to_complex(start)?)?;
+                Ok(from_f64_pair(root.re, root.im)?.remove(0))
+            },
+            [a, b] => {
+                let (a, b) = (to_f64(to_real(a)?), to_f64(to_real(b)?));
+                let root = numeric::brent(&mut |t| self.sample(&f, body.as_ref(), t), a, b)?;
+                from_f64(root)
+            },
+            _ => Err(Error::WrongArgumentCount),
+        }
+    }
+
+    /// Finds the smallest value of a function on `[a, b]`, or the largest if
+    /// `largest` is set, giving where it is and the value there as a tuple.
+    pub fn extremum(&mut self, f: Value, a: &Value, b: &Value, largest: bool) -> Result<Value, Error> {
+        let body = self.variable_and_body(&f).ok();
+        let sign = if largest { -1.0 } else { 1.0 };
+        let (a, b) = (to_f64(to_real(a)?), to_f64(to_real(b)?));
+        let (x, y) = numeric::minimize(&mut |t| Ok(sign * self.sample(&f, body.as_ref(), t)?), a.min(b), a.max(b))?;
+        Ok(Value::Tuple(vec![from_f64(x)?, from_f64(sign * y)?]))
+    }
+
+    /// Finds an antiderivative of an expression in `x`, expanding polynomials and
+    /// splitting quotients of polynomials into partial fractions first.
+    fn antiderivative(&mut self, x: &str, expr: &Expression) -> Option<Expression> {
+        let free = [x.to_string()];
+        if is_polynomial(expr) {
+            if let Ok([value]) = self.evaluate_free(expr, &free).as_deref() {
+                return calculus::antiderivative(&symbolic::to_expression(value).ok()?, x);
+            }
+        }
+        if let Expression::Divide(_, p, q) = expr {
+            if is_polynomial(p) && is_polynomial(q) && expr.variables() == free {
+                let p = self.evaluate_free(p, &free).ok()?.pop()?;
+                let q = self.evaluate_free(q, &free).ok()?.pop()?;
+                if let Some(antiderivative) = calculus::rational_antiderivative(p, q, x) {
+                    return Some(antiderivative);
+                }
+            }
+        }
+        calculus::antiderivative(expr, x)
+    }
+
+    /// Evaluates `F(b) - F(a)` for an antiderivative `F` of an expression in `x`.
+    fn exact_integral(&mut self, x: &str, expr: &Expression, a: (isize, isize), b: (isize, isize)) -> Option<Value> {
+        let antiderivative = self.antiderivative(x, expr)?.simplify().ok()?;
+        let mut at = |t: (isize, isize)| {
+            self.increase_scope();
+            self.bind(x.to_string(), vec![Value::rational(t.0, t.1)]);
+            let values = self.evaluate_expression(antiderivative.clone());
+            self.decrease_scope();
+            values.ok()?.pop()
+        };
+        let (upper, lower) = (at(b)?, at(a)?);
+        subtract(upper, lower).ok()?.pop()
+    }
+
+    /// Integrates a function over `[a, b]`. The result is exact when an
+    /// antiderivative can be found, and otherwise comes from adaptive
+    /// Gauss–Kronrod quadrature, with its error estimate as an uncertainty.
+    /// Quadrature that does not converge is an error. An antiderivative is only
+    /// trusted where quadrature agrees with it, which rules out integrands that
+    /// are singular inside the interval.
+    pub fn integral(&mut self, f: Value, a: (isize, isize), b: (isize, isize)) -> Result<Value, Error> {
+        let integrand = self.variable_and_body(&f).ok();
+        let mut sample = |t: f64| self.sample(&f, integrand.as_ref(), t);
+        let (estimate, error) = numeric::integrate(&mut sample, to_f64(a), to_f64(b))?;
+        if let Some((x, body)) = &integrand {
+            if let Some(exact) = self.exact_integral(x, body, a, b) {
+                if let Ok(value) = to_real(&exact) {
+                    if (to_f64(value) - estimate).abs() <= 1e-6 * (1.0 + estimate.abs()) {
+                        return Ok(exact);
                     }
-                    iter_expr
-                } else {
-                    return Err(Error::MismatchedType);
                 }
+            }
+        }
+        // An error too small to write as a rational is written as the smallest one
+        Ok(uncertainty::measurement(from_f64(estimate)?, from_f64(error.max(1e-12))?)?.remove(0))
+    }
+
+    /// Finds the one variable in an equation that has no definition.
+    pub fn unknown(&self, equation: &Expression) -> Result<String, Error> {
+        let expr = match equation {
+            Expression::Define(_, l, r) | Expression::Equal(_, l, r) => Expression::Subtract(0, l.clone(), r.clone()),
+            expr => expr.clone(),
+        };
+        match &self.inline(&expr)?.variables()[..] {
+            [x] => Ok(x.clone()),
+            _ => Err(Error::MismatchedType),
+        }
+    }
+
+    /// Reads the point a limit is taken at or a series is expanded about, which
+    /// can be `∞` or `-∞`.
+    fn extended_point(&mut self, expr: Expression) -> Result<Extended, Error> {
+        let is_infinity = |x: &Expression| matches!(x, Expression::Variable(_, name) if name == INFINITY);
+        match expr {
+            x if is_infinity(&x) => Ok(Extended::PositiveInfinity),
+            Expression::Negate(_, x) if is_infinity(&x) => Ok(Extended::NegativeInfinity),
+            x => match &self.evaluate_expression(x)?[..] {
+                [a] => Ok(Extended::Finite(a.clone())),
+                _ => Err(Error::MismatchedType),
+            },
+        }
+    }
+
+    /// Finds the limit of an expression as `x` tends to a point, from above
+    /// when `side` is positive and from below when it is negative. Otherwise a
+    /// limit at a finite point is taken from both sides, which have to agree.
+    pub fn limit(&mut self, expr: &Expression, x: &str, point: Expression, side: i32) -> Result<Value, Error> {
+        let point = self.extended_point(point)?;
+        self.increase_scope();
+        self.bind(x.to_string(), vec![Value::Polynomial(Polynomial::variable(x))]);
+        let expr = self.inline(expr);
+        self.decrease_scope();
+        let expr = expr?.simplify()?;
+        let mut constant = |c: Expression| match &self.evaluate_expression(c)?[..] {
+            [c] => Ok(c.clone()),
+            _ => Err(Error::MismatchedType),
+        };
+        let limit = match (side, &point) {
+            (0, Extended::Finite(_)) => {
+                let above = series::limit(&expr, x, &point, 1, &mut constant)?;
+                let below = series::limit(&expr, x, &point, -1, &mut constant)?;
+                if above != below {
+                    return Err(Error::NoLimit);
+                }
+                above
             },
-            _ => expr.sub(param, &y),
+            (side, _) => series::limit(&expr, x, &point, side, &mut constant)?,
+        };
+        let infinity = Expression::Variable(0, INFINITY.to_string());
+        Ok(match limit {
+            Extended::Finite(c) => c,
+            Extended::PositiveInfinity => Value::Symbolic(infinity),
+            Extended::NegativeInfinity => Value::Symbolic(Expression::Negate(0, Box::new(infinity))),
+        })
+    }
+
+    /// Expands an expression as a Taylor series about a point, or a Laurent
+    /// series if it has a pole there, up to the `n`th power of `x - a`, or of
+    /// `1 / x` at infinity. The order cannot be negative.
+    pub fn series(&mut self, expr: &Expression, x: &str, point: Expression, n: isize) -> Result<Value, Error> {
+        if n < 0 {
+            return Err(Error::OutOfDomain);
+        }
+        if n > series::MAX_ORDER {
+            return Err(Error::Overflow);
+        }
+        let point = self.extended_point(point)?;
+        let n = n as i32;
+        self.increase_scope();
+        self.bind(x.to_string(), vec![Value::Polynomial(Polynomial::variable(x))]);
+        let expr = self.inline(expr);
+        self.decrease_scope();
+        let expr = expr?.simplify()?;
+        let mut constant = |c: Expression| match &self.evaluate_expression(c)?[..] {
+            [c] => Ok(c.clone()),
+            _ => Err(Error::MismatchedType),
+        };
+        let series = series::series(&expr, x, &point, n, &mut constant)?;
+        // A series about a point other than zero is kept in powers of `x - a`
+        match point {
+            Extended::Finite(a) if !matches!(a, Value::ComplexNumber(0, _, 0, _)) => Ok(symbolic::from_expression(series)),
+            _ => Ok(self.value_of(series)),
+        }
+    }
+
+    /// Finds the unknowns of a system of equations, in alphabetical order.
+    pub fn unknowns(&self, equations: &[Expression]) -> Result<Vec<String>, Error> {
+        let mut unknowns = Vec::new();
+        for equation in equations {
+            let expr = match equation {
+                Expression::Define(_, l, r) | Expression::Equal(_, l, r) => Expression::Subtract(0, l.clone(), r.clone()),
+                expr => expr.clone(),
+            };
+            unknowns.extend(self.inline(&expr)?.variables());
+        }
+        unknowns.sort();
+        unknowns.dedup();
+        Ok(unknowns)
+    }
+
+    /// Solves an equation `l = r` or `l == r`, or `expr = 0`, for `x`. A
+    /// polynomial equation, or one between quotients of polynomials, is solved
+    /// exactly as far as its degree allows. A polynomial equation that is linear
+    /// in `x` can have other unknowns, which its solution is written in. Any
+    /// other equation is solved numerically for its real solutions near zero.
+    pub fn solve(&mut self, equation: &Expression, x: &str) -> Result<Vec<Value>, Error> {
+        let (l, r) = match equation {
+            Expression::Define(_, l, r) | Expression::Equal(_, l, r) => ((**l).clone(), (**r).clone()),
+            expr => (expr.clone(), Expression::Number(0, 0, 1)),
+        };
+        let free = [x.to_string()];
+        self.increase_scope();
+        self.bind(x.to_string(), vec![Value::Polynomial(Polynomial::variable(x))]);
+        let difference = self.inline(&Expression::Subtract(0, Box::new(l), Box::new(r)));
+        self.decrease_scope();
+        let difference = difference?.simplify()?;
+        let (numerator, divisor) = match as_fraction(&difference) {
+            Some(fraction) => fraction,
+            None => return self.solve_numerically(&difference, x),
+        };
+        let solutions = match &self.evaluate_free(&numerator, &free)?[..] {
+            [Value::ComplexNumber(0, _, 0, _)] => return Err(Error::Indeterminate),
+            [Value::ComplexNumber(..)] => return Err(Error::NoSolution),
+            [Value::Polynomial(p)] if p.variables() == free => polynomials::solve(p)?,
+            [p @ Value::Polynomial(_)] => return solve_linear(p.clone(), x),
+            _ => return Err(Error::MismatchedType),
+        };
+        // A solution that makes the divisor zero is not one
+        let mut allowed = Vec::new();
+        for solution in solutions {
+            self.increase_scope();
+            self.bind(x.to_string(), vec![solution.clone()]);
+            let divisor_values = self.evaluate_expression(divisor.clone());
+            self.decrease_scope();
+            if let Ok([value]) = divisor_values.as_deref() {
+                if !matches!(value, Value::ComplexNumber(0, _, 0, _)) {
+                    allowed.push(solution);
+                }
+            }
+        }
+        match allowed.is_empty() {
+            true => Err(Error::NoSolution),
+            false => Ok(allowed),
+        }
+    }
+
+    /// Finds the real solutions of `expr = 0` numerically, searching outward
+    /// from zero in ranges ten times wider each time until some are found.
+    fn solve_numerically(&mut self, expr: &Expression, x: &str) -> Result<Vec<Value>, Error> {
+        if expr.variables() != [x] || numeric::evaluate(expr, x, 0.0).is_none() {
+            return Err(Error::MismatchedType);
+        }
+        for range in [10.0, 100.0, 1000.0, 10000.0] {
+            let roots = numeric::real_roots(&mut |t| numeric::evaluate(expr, x, t), -range, range, 20_000);
+            if !roots.is_empty() {
+                let mut solutions = roots.into_iter().map(from_f64).collect::<Result<Vec<_>, _>>()?;
+                solutions.dedup();
+                return Ok(solutions);
+            }
+        }
+        Err(Error::NoSolution)
+    }
+
+    /// Solves a system of equations for the unknowns, giving each solution as a
+    /// tuple of their values. A linear system is solved exactly by elimination.
+    /// Any other system of as many equations as unknowns is solved numerically
+    /// by Newton's method from many starting points, for its real solutions
+    /// unless it is polynomial, and a solution close to rationals that satisfy
+    /// the equations exactly is given as those.
+    pub fn solve_system(&mut self, equations: &[Expression], unknowns: &[String]) -> Result<Vec<Value>, Error> {
+        self.increase_scope();
+        for x in unknowns {
+            self.bind(x.clone(), vec![Value::Polynomial(Polynomial::variable(x))]);
+        }
+        let differences: Result<Vec<Expression>, Error> = equations.iter().map(|equation| {
+            let expr = match equation {
+                Expression::Define(_, l, r) | Expression::Equal(_, l, r) => Expression::Subtract(0, l.clone(), r.clone()),
+                expr => expr.clone(),
+            };
+            self.inline(&expr)?.simplify()
+        }).collect();
+        self.decrease_scope();
+        let differences = differences?;
+        let mut rows = Vec::new();
+        for difference in &differences {
+            match self.linear_row(difference, unknowns) {
+                Some(row) => rows.push(row),
+                None => return self.solve_system_numerically(&differences, unknowns),
+            }
+        }
+        Ok(vec![Value::Tuple(systems::solve_linear(rows, unknowns)?)])
+    }
+
+    /// Writes `expr = 0` as the coefficients of the unknowns and the constant on
+    /// the other side, if it is linear in them.
+    fn linear_row(&mut self, expr: &Expression, unknowns: &[String]) -> Option<(Vec<Value>, Value)> {
+        if !is_polynomial(expr) || has_variable_divisor(expr) {
+            return None;
+        }
+        let (coefficients, rest) = match &self.evaluate_free(expr, unknowns).ok()?[..] {
+            [Value::Polynomial(p)] => polynomials::linear_coefficients(p, unknowns)?,
+            [c @ Value::ComplexNumber(..)] => (vec![Value::integer(0); unknowns.len()], c.clone()),
+            _ => return None,
+        };
+        Some((coefficients, negate(rest).ok()?.remove(0)))
+    }
+
+    fn solve_system_numerically(&mut self, differences: &[Expression], unknowns: &[String]) -> Result<Vec<Value>, Error> {
+        if differences.len() != unknowns.len() {
+            return Err(Error::MismatchedType);
+        }
+        let jacobian = differences.iter()
+            .map(|d| unknowns.iter().map(|x| calculus::derivative(d, x)?.simplify()).collect())
+            .collect::<Result<Vec<Vec<Expression>>, Error>>()?;
+        let mut f = |z: &[Complex]| -> Result<numeric::Linearization, Error> {
+            let values: Vec<(&str, Complex)> = unknowns.iter().map(String::as_str).zip(z.iter().copied()).collect();
+            let at = |expr: &Expression| numeric::evaluate_at(expr, &values).ok_or(Error::MismatchedType);
+            let y = differences.iter().map(at).collect::<Result<_, _>>()?;
+            let rows = jacobian.iter().map(|row| row.iter().map(at).collect()).collect::<Result<_, _>>()?;
+            Ok((y, rows))
         };
-        self.evaluate_expression(*new_expr)
+        // Every expression has to be one that can be evaluated numerically
+        f(&vec![Complex::new(0.5, 0.25); unknowns.len()])?;
+        // A polynomial system has finitely many solutions, which can be
+        // complex, but any other is searched only for its real solutions
+        let polynomial = differences.iter().all(is_polynomial);
+        let mut random = Random::new(SYSTEM_SEED);
+        let mut uniform = |r: f64| r * (2.0 * (random.next() >> 11) as f64 / (1u64 << 53) as f64 - 1.0);
+        // Each root keeps how far it can be off, and its exact form if it has one
+        let mut roots: Vec<(Vec<Complex>, f64, Option<Vec<Value>>)> = Vec::new();
+        for k in 0..SYSTEM_STARTS {
+            let radius = [2.0, 10.0, 100.0][k % 3];
+            let start = (0..unknowns.len()).map(|_| match polynomial {
+                true => Complex::new(uniform(radius), uniform(radius)),
+                false => Complex::new(uniform(radius), 0.0),
+            }).collect();
+            let Ok(root) = numeric::newton_system(&mut f, start) else {
+                continue;
+            };
+            let size = root.iter().map(|z| z.abs()).fold(1.0, f64::max);
+            let clean = |t: f64| if t.abs() <= 1e-10 * size { 0.0 } else { t };
+            let root: Vec<Complex> = root.iter().map(|z| Complex::new(clean(z.re), clean(z.im))).collect();
+            if !polynomial && root.iter().any(|z| z.im != 0.0) {
+                continue;
+            }
+            // Newton's method only pins down a root to about the square root of
+            // the precision where the Jacobian is badly conditioned, as it is at
+            // a repeated root
+            let conditioning = f(&root).map_or(f64::INFINITY, |(_, jacobian)| numeric::condition(&jacobian));
+            let tolerance = size * (conditioning * f64::EPSILON).sqrt().clamp(1e-6, 1e-2);
+            let exact = self.exact_solution(differences, unknowns, &root);
+            roots.push((root, tolerance, exact));
+        }
+        // Exact roots come first, so that the roots close to them are dropped
+        roots.sort_by_key(|(_, _, exact)| exact.is_none());
+        let mut distinct: Vec<(Vec<Complex>, f64, Option<Vec<Value>>)> = Vec::new();
+        for (root, tolerance, exact) in roots {
+            let distance = |other: &[Complex]| root.iter().zip(other).map(|(a, b)| (*a - *b).abs()).fold(0.0, f64::max);
+            let repeated = distinct.iter().any(|(other, other_tolerance, other_exact)| {
+                (exact.is_some() && exact == *other_exact) || distance(other) <= tolerance.max(*other_tolerance)
+            });
+            if !repeated {
+                distinct.push((root, tolerance, exact));
+            }
+        }
+        if distinct.is_empty() {
+            return Err(Error::NoSolution);
+        }
+        // Real solutions come first, each kind in order of its coordinates
+        let key = |root: &Vec<Complex>| (root.iter().any(|z| z.im != 0.0), root.iter().flat_map(|z| [z.re, z.im]).collect::<Vec<_>>());
+        distinct.sort_by(|(a, _, _), (b, _, _)| {
+            let (a, b) = (key(a), key(b));
+            a.0.cmp(&b.0).then_with(|| a.1.iter().zip(&b.1).map(|(s, t)| s.total_cmp(t)).find(|o| o.is_ne()).unwrap_or(Ordering::Equal))
+        });
+        distinct.into_iter().map(|(root, _, exact)| {
+            let solution = match exact {
+                Some(solution) => solution,
+                None => root.iter().map(|z| Ok(from_f64_pair(z.re, z.im)?.remove(0))).collect::<Result<_, Error>>()?,
+            };
+            Ok(Value::Tuple(solution))
+        }).collect()
     }
 
-    fn eval1(&mut self, f: &dyn Fn(Value) -> Result<Vec<Value>, Error>, x_expr: Expression) -> Result<Vec<Value>, Error> {
+    /// Looks for rationals with small divisors close to a numerical solution
+    /// that satisfy every equation exactly.
+    fn exact_solution(&mut self, differences: &[Expression], unknowns: &[String], root: &[Complex]) -> Option<Vec<Value>> {
+        let rational = |t: f64| approximate_bounded(t, 10_000);
+        let candidate = root.iter()
+            .map(|z| rational(z.re).zip(rational(z.im)).map(|((a, b), (c, d))| Value::complex(a, b, c, d)))
+            .collect::<Option<Vec<Value>>>()?;
+        self.increase_scope();
+        for (x, value) in unknowns.iter().zip(&candidate) {
+            self.bind(x.clone(), vec![value.clone()]);
+        }
+        let exact = differences.iter().all(|d| matches!(self.evaluate_expression(d.clone()).as_deref(), Ok([Value::ComplexNumber(0, _, 0, _)])));
+        self.decrease_scope();
+        match exact {
+            true => Some(candidate),
+            false => None,
+        }
+    }
+
+    pub fn eval1(&mut self, f: &dyn Fn(Value) -> Result<Vec<Value>, Error>, x_expr: Expression) -> Result<Vec<Value>, Error> {
         let x_values = self.evaluate_expression(x_expr)?;
         let mut values = Vec::<Value>::new();
         for x_value in x_values {
@@ -146,50 +1095,187 @@ impl Evaluator {
         Ok(values)
     }
 
-    fn eval2(&mut self, f: &dyn Fn(Value, Value) -> Result<Vec<Value>, Error>, x_expr: Expression, y_expr: Expression) -> Result<Vec<Value>, Error> {
+    pub fn eval2(&mut self, f: &dyn Fn(Value, Value) -> Result<Vec<Value>, Error>, x_expr: Expression, y_expr: Expression) -> Result<Vec<Value>, Error> {
         let x_values = self.evaluate_expression(x_expr)?;
         let y_values = self.evaluate_expression(y_expr)?;
-        let mut values = Vec::<Value>::new();
-        for x_value in x_values {
-            for y_value in y_values.clone() {
-                values.extend(f(x_value.clone(), y_value)?);
+        apply2(f, x_values, y_values)
+    }
+}
+
+/// Substitutes an argument for the parameters in the body of a function.
+fn substitute(param: &Expression, body: &Expression, x: &Expression) -> Result<Expression, Error> {
+    match (param, x) {
+        (Expression::Tuple(_, params), Expression::Tuple(_, xs)) => {
+            let mut body = body.clone();
+            for (param, x) in params.iter().zip(xs) {
+                body = *body.sub(param, x);
             }
+            Ok(body)
+        },
+        (Expression::Tuple(..), _) => Err(Error::MismatchedType),
+        (param, x) => Ok(*body.sub(param, x)),
+    }
+}
+
+/// Whether an expression is an equation to solve rather than a definition,
+/// which is when the left side is not a name or a function being defined.
+fn is_equation(expr: &Expression) -> bool {
+    match expr {
+        Expression::Define(_, l, _) => match &**l {
+            Expression::Variable(..) => false,
+            Expression::Call(_, f, _) => !matches!(**f, Expression::Variable(..)),
+            _ => true,
+        },
+        _ => false,
+    }
+}
+
+/// Writes an expression made of polynomials and quotients of them as a single
+/// quotient `p / q` of polynomials.
+fn as_fraction(expr: &Expression) -> Option<(Expression, Expression)> {
+    let operation = |f: fn(usize, Box<Expression>, Box<Expression>) -> Expression, x: Expression, y: Expression| f(0, Box::new(x), Box::new(y));
+    match expr {
+        Expression::Add(_, x, y) | Expression::Subtract(_, x, y) => {
+            let ((a, b), (c, d)) = (as_fraction(x)?, as_fraction(y)?);
+            let cross = (operation(Expression::Multiply, a, d.clone()), operation(Expression::Multiply, c, b.clone()));
+            let numerator = match expr {
+                Expression::Add(..) => operation(Expression::Add, cross.0, cross.1),
+                _ => operation(Expression::Subtract, cross.0, cross.1),
+            };
+            Some((numerator, operation(Expression::Multiply, b, d)))
+        },
+        Expression::Multiply(_, x, y) => {
+            let ((a, b), (c, d)) = (as_fraction(x)?, as_fraction(y)?);
+            Some((operation(Expression::Multiply, a, c), operation(Expression::Multiply, b, d)))
+        },
+        Expression::Divide(_, x, y) => {
+            let ((a, b), (c, d)) = (as_fraction(x)?, as_fraction(y)?);
+            Some((operation(Expression::Multiply, a, d), operation(Expression::Multiply, b, c)))
+        },
+        Expression::Negate(_, x) => {
+            let (a, b) = as_fraction(x)?;
+            Some((Expression::Negate(0, Box::new(a)), b))
+        },
+        Expression::Power(_, x, n) => match **n {
+            Expression::Number(_, k, 1) => {
+                let (a, b) = as_fraction(x)?;
+                let (a, b) = if k < 0 { (b, a) } else { (a, b) };
+                let k = Expression::Number(0, k.abs(), 1);
+                Some((operation(Expression::Power, a, k.clone()), operation(Expression::Power, b, k)))
+            },
+            _ => None,
+        },
+        x if is_polynomial(x) && !has_variable_divisor(x) => Some((x.clone(), Expression::Number(0, 1, 1))),
+        _ => None,
+    }
+}
+
+/// Solves `p = 0` for `x` when `p` is linear in `x`, with its other variables
+/// left in the solution.
+fn solve_linear(p: Value, x: &str) -> Result<Vec<Value>, Error> {
+    let variable = Value::Polynomial(Polynomial::variable(x));
+    match &polynomials::coeffs(Value::Tuple(vec![p, variable]))?[..] {
+        [Value::List(coefficients)] => match &coefficients[..] {
+            [a, b] => {
+                let b = negate(b.clone())?.remove(0);
+                divide(b.clone(), a.clone()).or_else(|_| symbolic::divide(b, a.clone()))
+            },
+            _ => Err(Error::MismatchedType),
+        },
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+fn is_polynomial(expr: &Expression) -> bool {
+    match expr {
+        Expression::Number(..) => true,
+        Expression::ImaginaryConstant(_) | Expression::Variable(..) => true,
+        Expression::Power(_, x, n) => is_polynomial(x) && matches!(**n, Expression::Number(_, _, 1)),
+        Expression::Negate(_, x) => is_polynomial(x),
+        Expression::Add(_, x, y) | Expression::Subtract(_, x, y) | Expression::Multiply(_, x, y) | Expression::Divide(_, x, y) => {
+            is_polynomial(x) && is_polynomial(y)
+        },
+        _ => false,
+    }
+}
+
+fn has_variable_divisor(expr: &Expression) -> bool {
+    match expr {
+        Expression::Divide(_, x, y) => !matches!(**y, Expression::Number(..)) || has_variable_divisor(x),
+        Expression::Negate(_, x) => has_variable_divisor(x),
+        Expression::Add(_, x, y) | Expression::Subtract(_, x, y) | Expression::Multiply(_, x, y) => {
+            has_variable_divisor(x) || has_variable_divisor(y)
+        },
+        _ => false,
+    }
+}
+
+/// Applies `f` to every pair of values from `x_values` and `y_values`.
+pub fn apply2(f: &dyn Fn(Value, Value) -> Result<Vec<Value>, Error>, x_values: Vec<Value>, y_values: Vec<Value>) -> Result<Vec<Value>, Error> {
+    let mut values = Vec::<Value>::new();
+    for x_value in x_values {
+        for y_value in y_values.clone() {
+            values.extend(f(x_value.clone(), y_value)?);
         }
-        values.dedup();
-        Ok(values)
     }
+    values.dedup();
+    Ok(values)
 }
 
-fn multiply(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+pub fn multiply(x: Value, y: Value) -> Result<Vec<Value>, Error> {
     match (x, y) {
+        (x @ Value::Quantity(..), y) | (x, y @ Value::Quantity(..)) => units::multiply(x, y),
+        (x @ Value::Measurement(..), y) | (x, y @ Value::Measurement(..)) => uncertainty::multiply(x, y),
+        (x @ Value::Interval(..), y) | (x, y @ Value::Interval(..)) => intervals::multiply(x, y),
+        (x @ Value::Symbolic(..), y) | (x, y @ Value::Symbolic(..)) => symbolic::multiply(x, y),
+        (x @ Value::Polynomial(..), y) | (x, y @ Value::Polynomial(..)) => polynomials::multiply(x, y),
         (Value::ComplexNumber(a1, b1, c1, d1), Value::ComplexNumber(a2, b2, c2, d2)) => {
+            let (x_re, x_im, y_re, y_im) = ((a1, b1), (c1, d1), (a2, b2), (c2, d2));
             // Real Segment
-            let af = a1 * a2 * d1 * d2 - c1 * c2 * b1 * b2;
-            let bf = b1 * b2 * d1 * d2;
-            let cf = a1 * c2 * b2 * d1 + a2 * c1 * b1 * d2;
-            let df = bf;
-            Ok(vec![Value::ComplexNumber(af, bf, cf, df)])
+            let re = rational_subtract(
+                rational_multiply(x_re, y_re).ok_or(Error::Overflow)?,
+                rational_multiply(x_im, y_im).ok_or(Error::Overflow)?,
+            ).ok_or(Error::Overflow)?;
+            // Imaginary Segment
+            let im = rational_add(
+                rational_multiply(x_re, y_im).ok_or(Error::Overflow)?,
+                rational_multiply(x_im, y_re).ok_or(Error::Overflow)?,
+            ).ok_or(Error::Overflow)?;
+            Ok(vec![Value::complex(re.0, re.1, im.0, im.1)])
         },
         _ => Err(Error::MismatchedType),
     }
 }
 
-fn divide(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+pub fn divide(x: Value, y: Value) -> Result<Vec<Value>, Error> {
     match (x, y) {
-        (Value::ComplexNumber(a1, b1, c1, d1), Value::ComplexNumber(a2, b2, c2, d2)) => {
-            // Real Segment
-            let af = b2 * d2 * (a1 * a2 * d1 * d2 + c1 * c2 * b1 * b2);
-            let bf = b1 * d1 * (a2 * a2 * d2 * d2 + c2 * c2 * b2 * b2);
-            let cf = b2 * d2 * (a2 * c1 * b1 * d2 + a1 * c2 * d1 * b2);
-            let df = bf;
-            Ok(vec![Value::ComplexNumber(af, bf, cf, df)])
+        (x @ Value::Quantity(..), y) | (x, y @ Value::Quantity(..)) => units::divide(x, y),
+        (x @ Value::Measurement(..), y) | (x, y @ Value::Measurement(..)) => uncertainty::divide(x, y),
+        (x @ Value::Interval(..), y) | (x, y @ Value::Interval(..)) => intervals::divide(x, y),
+        (x @ Value::Symbolic(..), y) | (x, y @ Value::Symbolic(..)) => symbolic::divide(x, y),
+        (x @ Value::Polynomial(..), y) | (x, y @ Value::Polynomial(..)) => polynomials::divide(x, y),
+        (_, Value::ComplexNumber(0, _, 0, _)) => Err(Error::DivisionByZero),
+        (x, Value::ComplexNumber(a, b, c, d)) => {
+            // Multiply by the conjugate over the squared modulus
+            let modulus = rational_add(
+                rational_multiply((a, b), (a, b)).ok_or(Error::Overflow)?,
+                rational_multiply((c, d), (c, d)).ok_or(Error::Overflow)?,
+            ).ok_or(Error::Overflow)?;
+            let re = rational_divide((a, b), modulus).ok_or(Error::Overflow)?;
+            let im = rational_divide((-c, d), modulus).ok_or(Error::Overflow)?;
+            multiply(x, Value::complex(re.0, re.1, im.0, im.1))
         },
         _ => Err(Error::MismatchedType),
     }
 }
 
-fn negate(x: Value) -> Result<Vec<Value>, Error> {
+pub fn negate(x: Value) -> Result<Vec<Value>, Error> {
     match x {
+        Value::Quantity(a, b, unit) => Ok(vec![Value::Quantity(-a, b, unit)]),
+        x @ Value::Measurement(..) => uncertainty::negate(x),
+        x @ Value::Interval(..) => intervals::negate(x),
+        x @ Value::Symbolic(..) => symbolic::negate(x),
+        x @ Value::Polynomial(..) => polynomials::negate(x),
         Value::ComplexNumber(a, b, c, d) => {
             Ok(vec![Value::ComplexNumber(-a, b, -c, d)])
         }
@@ -197,44 +1283,345 @@ fn negate(x: Value) -> Result<Vec<Value>, Error> {
     }
 }
 
-fn add(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+pub fn add(x: Value, y: Value) -> Result<Vec<Value>, Error> {
     match (x, y) {
+        (x @ Value::Quantity(..), y) | (x, y @ Value::Quantity(..)) => units::add(x, y),
+        (x @ Value::Measurement(..), y) | (x, y @ Value::Measurement(..)) => uncertainty::add(x, y),
+        (x @ Value::Interval(..), y) | (x, y @ Value::Interval(..)) => intervals::add(x, y),
+        (x @ Value::Symbolic(..), y) | (x, y @ Value::Symbolic(..)) => symbolic::add(x, y),
+        (x @ Value::Polynomial(..), y) | (x, y @ Value::Polynomial(..)) => polynomials::add(x, y),
         (Value::ComplexNumber(a1, b1, c1, d1), Value::ComplexNumber(a2, b2, c2, d2)) => {
             // Real Segment
-            let lcm = b1 * gcd(b1, b2) / b2;
-            let bf = lcm;
-            let af1 = a1 * (lcm / b1);
-            let af2 = a2 * (lcm / b2);
-            let af = af1 + af2;
+            let (af, bf) = rational_add((a1, b1), (a2, b2)).ok_or(Error::Overflow)?;
             // Imaginary Segment
-            let lcmi = d1 * gcd(d1, d2) / d2;
-            let df = lcm;
-            let cf1 = c1 * (lcmi / d1);
-            let cf2 = c2 * (lcmi / d2);
-            let cf = cf1 + cf2;
+            let (cf, df) = rational_add((c1, d1), (c2, d2)).ok_or(Error::Overflow)?;
             Ok(vec![Value::ComplexNumber(af, bf, cf, df)])
         },
         _ => Err(Error::MismatchedType),
     }
 }
 
-fn subtract(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+pub fn subtract(x: Value, y: Value) -> Result<Vec<Value>, Error> {
     match (x, y) {
+        (x @ Value::Quantity(..), y) | (x, y @ Value::Quantity(..)) => units::subtract(x, y),
+        (x @ Value::Measurement(..), y) | (x, y @ Value::Measurement(..)) => uncertainty::subtract(x, y),
+        (x @ Value::Interval(..), y) | (x, y @ Value::Interval(..)) => intervals::subtract(x, y),
+        (x @ Value::Symbolic(..), y) | (x, y @ Value::Symbolic(..)) => symbolic::subtract(x, y),
+        (x @ Value::Polynomial(..), y) | (x, y @ Value::Polynomial(..)) => polynomials::subtract(x, y),
         (Value::ComplexNumber(a1, b1, c1, d1), Value::ComplexNumber(a2, b2, c2, d2)) => {
             // Real Segment
-            let lcm = b1 * gcd(b1, b2) / b2;
-            let bf = lcm;
-            let af1 = a1 * (lcm / b1);
-            let af2 = a2 * (lcm / b2);
-            let af = af1 - af2;
+            let (af, bf) = rational_subtract((a1, b1), (a2, b2)).ok_or(Error::Overflow)?;
             // Imaginary Segment
-            let lcmi = d1 * gcd(d1, d2) / d2;
-            let df = lcm;
-            let cf1 = c1 * (lcmi / d1);
-            let cf2 = c2 * (lcmi / d2);
-            let cf = cf1 - cf2;
+            let (cf, df) = rational_subtract((c1, d1), (c2, d2)).ok_or(Error::Overflow)?;
             Ok(vec![Value::ComplexNumber(af, bf, cf, df)])
         },
         _ => Err(Error::MismatchedType),
     }
 }
+
+/// Reads a rational out of a value, for the operations that only work on real numbers.
+pub fn to_real(x: &Value) -> Result<(isize, isize), Error> {
+    match x {
+        Value::ComplexNumber(a, b, 0, _) => Ok((*a, *b)),
+        Value::ComplexNumber(..) => Err(Error::NotReal),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+/// Reads a complex number as a pair of floats.
+fn to_complex(x: &Value) -> Result<Complex, Error> {
+    match x {
+        Value::ComplexNumber(a, b, c, d) => Ok(Complex::new(to_f64((*a, *b)), to_f64((*c, *d)))),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+/// Orders two rationals with positive divisors.
+pub fn real_order((a1, b1): (isize, isize), (a2, b2): (isize, isize)) -> Ordering {
+    (a1 as i128 * b2 as i128).cmp(&(a2 as i128 * b1 as i128))
+}
+
+/// Orders two real values, or two quantities of the same dimension.
+pub fn order(x: &Value, y: &Value) -> Result<Ordering, Error> {
+    match (x, y) {
+        (Value::Quantity(..), _) | (_, Value::Quantity(..)) => units::order(x.clone(), y.clone()),
+        // A surd or constant is ordered by its value
+        (Value::Symbolic(_), _) | (_, Value::Symbolic(_)) => {
+            Ok(real_order(to_real(&symbolic::approximate(x)?)?, to_real(&symbolic::approximate(y)?)?))
+        },
+        _ => Ok(real_order(to_real(x)?, to_real(y)?)),
+    }
+}
+
+/// Tests two values for equality, where quantities are equal if they are the
+/// same amount in different units.
+fn equal(x: Value, y: Value) -> Result<bool, Error> {
+    match (&x, &y) {
+        (Value::Quantity(..), _) | (_, Value::Quantity(..)) => Ok(order(&x, &y)? == Ordering::Equal),
+        _ => Ok(x == y),
+    }
+}
+
+/// Compares two real values, giving true if their order is one of `orderings`.
+fn compare(x: Value, y: Value, orderings: &[Ordering]) -> Result<Vec<Value>, Error> {
+    Ok(vec![Value::Boolean(orderings.contains(&order(&x, &y)?))])
+}
+
+fn and(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    match (x, y) {
+        (Value::Boolean(x), Value::Boolean(y)) => Ok(vec![Value::Boolean(x && y)]),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+fn or(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    match (x, y) {
+        (Value::Boolean(x), Value::Boolean(y)) => Ok(vec![Value::Boolean(x || y)]),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+/// Reads an integer out of a value, for the operators that only work on integers.
+pub fn to_integer(x: &Value) -> Result<isize, Error> {
+    match x {
+        Value::ComplexNumber(a, 1, 0, _) => Ok(*a),
+        Value::ComplexNumber(..) => Err(Error::NotAnInteger),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+/// Divides and rounds towards negative infinity.
+fn integer_divide(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    if let (Value::Polynomial(_), _) | (_, Value::Polynomial(_)) = (&x, &y) {
+        return polynomials::quotient(x, y);
+    }
+    let (x, y) = (to_integer(&x)?, to_integer(&y)?);
+    if y == 0 {
+        return Err(Error::DivisionByZero);
+    }
+    let quotient = x.checked_div_euclid(y).ok_or(Error::Overflow)?;
+    // Euclidean division rounds up instead when the divisor is negative
+    match (y < 0, x.rem_euclid(y) != 0) {
+        (true, true) => Ok(vec![Value::integer(quotient - 1)]),
+        _ => Ok(vec![Value::integer(quotient)]),
+    }
+}
+
+/// Finds the remainder of flooring division, which takes the sign of the divisor.
+/// A polynomial is divided by a polynomial, or has each of its coefficients
+/// reduced by a number.
+fn modulo(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    match (x, y) {
+        (x, y @ Value::Polynomial(_)) => polynomials::remainder(x, y),
+        (Value::Polynomial(p), y) => {
+            Ok(vec![polynomials::map_coefficients(p, &|x| Ok(modulo(x, y.clone())?.remove(0)))?])
+        },
+        (x, y) => {
+            let (x, y) = (to_integer(&x)?, to_integer(&y)?);
+            if y == 0 {
+                return Err(Error::DivisionByZero);
+            }
+            let remainder = x.checked_rem_euclid(y).ok_or(Error::Overflow)?;
+            match (y < 0, remainder != 0) {
+                (true, true) => Ok(vec![Value::integer(remainder + y)]),
+                _ => Ok(vec![Value::integer(remainder)]),
+            }
+        },
+    }
+}
+
+/// Checks whether the left side of `mod` divides or takes a power outside of
+/// any function call, which is when it is evaluated in modular arithmetic.
+fn is_modular(expr: &Expression) -> bool {
+    match expr {
+        Expression::Divide(..) | Expression::Power(..) => true,
+        Expression::Call(..) | Expression::Function(..) => false,
+        x => {
+            let mut found = false;
+            let _ = x.try_map(&mut |e| {
+                found |= is_modular(e);
+                Ok::<_, ()>(e.clone())
+            });
+            found
+        },
+    }
+}
+
+fn shift_left(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    let (x, y) = (to_integer(&x)?, to_integer(&y)?);
+    match y {
+        _ if x == 0 => Ok(vec![Value::integer(0)]),
+        y if y < 0 => shift_right(Value::integer(x), Value::integer(y.checked_neg().ok_or(Error::Overflow)?)),
+        y if y < isize::BITS as isize => {
+            let shifted = (x as i128).checked_mul(1 << y).ok_or(Error::Overflow)?;
+            Ok(vec![Value::integer(isize::try_from(shifted).map_err(|_| Error::Overflow)?)])
+        },
+        _ => Err(Error::Overflow),
+    }
+}
+
+fn shift_right(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    let (x, y) = (to_integer(&x)?, to_integer(&y)?);
+    match y {
+        y if y < 0 => shift_left(Value::integer(x), Value::integer(y.checked_neg().ok_or(Error::Overflow)?)),
+        y => Ok(vec![Value::integer(x >> y.min(isize::BITS as isize - 1))]),
+    }
+}
+
+fn bit_and(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    Ok(vec![Value::integer(to_integer(&x)? & to_integer(&y)?)])
+}
+
+fn bit_or(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    Ok(vec![Value::integer(to_integer(&x)? | to_integer(&y)?)])
+}
+
+fn bit_xor(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    Ok(vec![Value::integer(to_integer(&x)? ^ to_integer(&y)?)])
+}
+
+fn factorial(x: Value) -> Result<Vec<Value>, Error> {
+    match to_integer(&x)? {
+        n if n < 0 => Err(Error::OutOfDomain),
+        n => Ok(vec![Value::integer(integers::factorial(n as u64).ok_or(Error::Overflow)?)]),
+    }
+}
+
+/// Negates a boolean, or flips the bits of an integer.
+fn not(x: Value) -> Result<Vec<Value>, Error> {
+    match x {
+        Value::Boolean(b) => Ok(vec![Value::Boolean(!b)]),
+        x => Ok(vec![Value::integer(!to_integer(&x)?)]),
+    }
+}
+
+/// Raises a number to a power. Integer powers are exact, as are rational powers
+/// of rationals with rational roots, and anything else is approximated.
+pub fn power(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    match (x, y) {
+        (x @ Value::Quantity(..), y) => units::power(x, y),
+        (x @ Value::Measurement(..), y) => uncertainty::power(x, y),
+        (x @ Value::Interval(..), y) => intervals::power(x, y),
+        (x @ Value::Symbolic(..), y) | (x, y @ Value::Symbolic(..)) => symbolic::power(x, y),
+        (x @ Value::Polynomial(..), y) => polynomials::power(x, y),
+        (Value::ComplexNumber(0, _, 0, _), Value::ComplexNumber(p, _, 0, _)) if p < 0 => Err(Error::DivisionByZero),
+        (x @ Value::ComplexNumber(..), Value::ComplexNumber(p, 1, 0, _)) => {
+            let mut result = Value::integer(1);
+            let mut base = x;
+            let mut exponent = p.unsigned_abs();
+            while exponent > 0 {
+                if exponent & 1 == 1 {
+                    result = multiply(result, base.clone())?.remove(0);
+                }
+                exponent >>= 1;
+                if exponent > 0 {
+                    base = multiply(base.clone(), base)?.remove(0);
+                }
+            }
+            match p {
+                p if p < 0 => divide(Value::integer(1), result),
+                _ => Ok(vec![result]),
+            }
+        },
+        (Value::ComplexNumber(a, b, 0, _), Value::ComplexNumber(p, q, 0, _)) if a >= 0 || q == 2 => {
+            // An exact root of the numerator and divisor, times i for negative square roots
+            let root = integer_root(a.abs(), q).zip(integer_root(b, q));
+            match root {
+                Some((root_a, root_b)) => {
+                    let base = match a {
+                        a if a < 0 => Value::complex(0, 1, root_a, root_b),
+                        _ => Value::rational(root_a, root_b),
+                    };
+                    power(base, Value::integer(p))
+                },
+                None => approximate_power(Value::rational(a, b), Value::rational(p, q)),
+            }
+        },
+        (x @ Value::ComplexNumber(..), y @ Value::ComplexNumber(..)) => approximate_power(x, y),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+/// Approximates `x^y` as `e^(y ln x)` using the principal logarithm.
+fn approximate_power(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    match (x, y) {
+        (Value::ComplexNumber(a, b, c, d), Value::ComplexNumber(p, q, r, s)) => {
+            let (x_re, x_im) = (to_f64((a, b)), to_f64((c, d)));
+            let (y_re, y_im) = (to_f64((p, q)), to_f64((r, s)));
+            let (log_modulus, theta) = (x_re.hypot(x_im).ln(), x_im.atan2(x_re));
+            let modulus = (y_re * log_modulus - y_im * theta).exp();
+            let angle = y_im * log_modulus + y_re * theta;
+            let re = from_f64(modulus * angle.cos())?;
+            let im = from_f64(modulus * angle.sin())?;
+            multiply(im, Value::complex(0, 1, 1, 1)).and_then(|im| add(re, im[0].clone()))
+        },
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+/// Reduces the real and imaginary parts of a number modulo `m`, replacing any
+/// divisor with its modular inverse. Results take the sign of the modulus.
+pub fn reduce_modulo(x: Value, m: isize) -> Result<Value, Error> {
+    match x {
+        Value::ComplexNumber(a, b, c, d) => Ok(Value::complex(rational_modulo((a, b), m)?, 1, rational_modulo((c, d), m)?, 1)),
+        Value::Tuple(xs) => Ok(Value::Tuple(xs.into_iter().map(|x| reduce_modulo(x, m)).collect::<Result<_, _>>()?)),
+        Value::List(xs) => Ok(Value::List(xs.into_iter().map(|x| reduce_modulo(x, m)).collect::<Result<_, _>>()?)),
+        Value::Polynomial(p) => polynomials::map_coefficients(p, &|x| reduce_modulo(x, m)),
+        x => Ok(x),
+    }
+}
+
+fn rational_modulo((a, b): (isize, isize), m: isize) -> Result<isize, Error> {
+    let inverse = integers::inverse_mod(b, m).ok_or(Error::NoInverse)?;
+    let remainder = (a as i128 * inverse as i128).rem_euclid(m.unsigned_abs() as i128) as isize;
+    match (m < 0, remainder != 0) {
+        (true, true) => Ok(remainder + m),
+        _ => Ok(remainder),
+    }
+}
+
+/// Divides by multiplying with the inverse modulo `m`, which only exists when the
+/// norm of the divisor is coprime with `m`.
+fn modular_divide(x: Value, y: Value, m: isize) -> Result<Vec<Value>, Error> {
+    if let Value::ComplexNumber(a, 1, c, 1) = y {
+        let norm = (a as i128 * a as i128 + c as i128 * c as i128).rem_euclid(m.unsigned_abs() as i128);
+        if (a, c) != (0, 0) && integers::inverse_mod(norm as isize, m).is_none() {
+            return Err(Error::NoInverse);
+        }
+    }
+    divide(x, y)
+}
+
+/// Raises a number to an integer power by repeated squaring modulo `m`.
+fn modular_power(x: Value, y: Value, m: isize) -> Result<Vec<Value>, Error> {
+    let p = to_integer(&y)?;
+    let mut base = match p {
+        p if p < 0 => reduce_modulo(modular_divide(Value::integer(1), x, m)?.remove(0), m)?,
+        _ => reduce_modulo(x, m)?,
+    };
+    let mut result = Value::integer(1);
+    let mut exponent = p.unsigned_abs();
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = reduce_modulo(multiply(result, base.clone())?.remove(0), m)?;
+        }
+        base = reduce_modulo(multiply(base.clone(), base)?.remove(0), m)?;
+        exponent >>= 1;
+    }
+    Ok(vec![reduce_modulo(result, m)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shifting_zero_left_by_any_amount_is_zero() {
+        assert!(matches!(shift_left(Value::integer(0), Value::integer(1000)).as_deref(), Ok([Value::ComplexNumber(0, _, 0, _)])));
+    }
+
+    #[test]
+    fn shifting_right_by_the_smallest_integer_overflows() {
+        assert!(matches!(shift_right(Value::integer(1), Value::integer(isize::MIN)), Err(Error::Overflow)));
+    }
+}
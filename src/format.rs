@@ -0,0 +1,292 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+
+/// The number of digits shown by the decimal, scientific and engineering forms
+/// when no count is given.
+pub const DEFAULT_DIGITS: usize = 10;
+
+/// The longest repeating block searched for before falling back to decimals.
+const MAX_PERIOD: usize = 100;
+
+/// How complex numbers are written when printed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ComplexForm {
+    Rectangular,
+    Polar,
+    Exponential,
+}
+
+/// How rational numbers are written when printed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NumberForm {
+    Fraction,
+    Mixed,
+    Decimal(usize),
+    Repeating,
+    Scientific(usize),
+    Engineering(usize),
+}
+
+/// The output settings used by `impl Debug for Value`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Format {
+    pub complex: ComplexForm,
+    pub number: NumberForm,
+    pub radix: u32,
+}
+
+impl Default for Format {
+    fn default() -> Format {
+        Format {
+            complex: ComplexForm::Rectangular,
+            number: NumberForm::Fraction,
+            radix: 10,
+        }
+    }
+}
+
+thread_local! {
+    static FORMAT: Cell<Format> = Cell::new(Format::default());
+    static OVERRIDE: Cell<Option<Format>> = const { Cell::new(None) };
+}
+
+/// Returns the format values are currently printed in, which is the session
+/// format unless an expression asked for another one.
+pub fn current() -> Format {
+    OVERRIDE.with(|format| format.get()).unwrap_or_else(session)
+}
+
+pub fn session() -> Format {
+    FORMAT.with(|format| format.get())
+}
+
+pub fn set(format: Format) {
+    FORMAT.with(|cell| cell.set(format));
+}
+
+/// Sets a format for printing the result of a single expression.
+pub fn set_override(format: Option<Format>) {
+    OVERRIDE.with(|cell| cell.set(format));
+}
+
+impl Format {
+    /// Returns this format with the setting named by `name` applied, or `None`
+    /// if no setting has that name. `digits` is used by the settings that take
+    /// a number of digits.
+    pub fn with(self, name: &str, digits: Option<usize>) -> Option<Format> {
+        let mut format = self;
+        let count = digits.unwrap_or(DEFAULT_DIGITS);
+        match (name, digits) {
+            ("rect", None) => format.complex = ComplexForm::Rectangular,
+            ("polar", None) => format.complex = ComplexForm::Polar,
+            ("exp", None) => format.complex = ComplexForm::Exponential,
+            ("fraction", None) => format.number = NumberForm::Fraction,
+            ("mixed", None) => format.number = NumberForm::Mixed,
+            ("repeating", None) => format.number = NumberForm::Repeating,
+            ("decimal", _) => format.number = NumberForm::Decimal(count),
+            ("sci", _) if count > 0 => format.number = NumberForm::Scientific(count),
+            ("eng", _) if count > 0 => format.number = NumberForm::Engineering(count),
+            ("hex", None) => format.radix = 16,
+            ("oct", None) => format.radix = 8,
+            ("bin", None) => format.radix = 2,
+            ("dec", None) => format.radix = 10,
+            ("base", Some(radix @ 2..=36)) => format.radix = radix as u32,
+            _ => return None,
+        }
+        Some(format)
+    }
+}
+
+/// Writes the rational `a / b` in the given format. `b` must be positive.
+pub fn rational((a, b): (isize, isize), format: Format) -> String {
+    if format.radix != 10 {
+        return in_radix(a, b, format.number, format.radix);
+    }
+    match format.number {
+        NumberForm::Fraction => fraction(a, b),
+        NumberForm::Mixed => mixed(a, b, 10),
+        NumberForm::Decimal(n) => decimal(a, b, n, 10),
+        NumberForm::Repeating => repeating(a, b, 10),
+        NumberForm::Scientific(n) => scientific(a, b, n, 1),
+        NumberForm::Engineering(n) => scientific(a, b, n, 3),
+    }
+}
+
+/// Writes `a / b` in another base, marked with a prefix like `0x` or with the base
+/// as a subscript. Fractions are written with a radix point, since a quotient of
+/// two numbers in another base is hard to read.
+fn in_radix(a: isize, b: isize, form: NumberForm, radix: u32) -> String {
+    // The digits are worked out from |a| as a u128, which holds the smallest
+    // isize, and the sign they are written with goes before the prefix
+    let digits = match form {
+        NumberForm::Mixed => mixed(a, b, radix),
+        NumberForm::Decimal(n) => decimal(a, b, n, radix),
+        _ => repeating(a, b, radix),
+    };
+    let (sign, digits) = match digits.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", digits.as_str()),
+    };
+    match radix {
+        16 => format!("{}0x{}", sign, digits),
+        8 => format!("{}0o{}", sign, digits),
+        2 => format!("{}0b{}", sign, digits),
+        _ => format!("{}{}{}", sign, digits, subscript(radix)),
+    }
+}
+
+fn subscript(n: u32) -> String {
+    n.to_string().chars().map(|c| match c {
+        '0' => '₀', '1' => '₁', '2' => '₂', '3' => '₃', '4' => '₄',
+        '5' => '₅', '6' => '₆', '7' => '₇', '8' => '₈', _ => '₉',
+    }).collect()
+}
+
+fn fraction(a: isize, b: isize) -> String {
+    match b {
+        1 => format!("{}", a),
+        _ => format!("{}/{}", a, b),
+    }
+}
+
+fn mixed(a: isize, b: isize, radix: u32) -> String {
+    let whole = to_string(&integer_digits((a / b).unsigned_abs() as u128, radix));
+    let part = to_string(&integer_digits((a % b).unsigned_abs() as u128, radix));
+    let divisor = to_string(&integer_digits(b as u128, radix));
+    match (a / b, a % b) {
+        (_, 0) => format!("{}{}", sign(a), whole),
+        (0, _) => format!("{}{}/{}", sign(a), part, divisor),
+        _ => format!("{}{} {}/{}", sign(a), whole, part, divisor),
+    }
+}
+
+fn sign(a: isize) -> &'static str {
+    if a < 0 { "-" } else { "" }
+}
+
+/// The digits of `|a| / b` from long division, with the radix point after the
+/// first `point` digits.
+struct Expansion {
+    digits: Vec<u8>,
+    point: usize,
+    exact: bool,
+}
+
+fn integer_digits(mut n: u128, radix: u32) -> Vec<u8> {
+    let mut digits = vec![(n % radix as u128) as u8];
+    n /= radix as u128;
+    while n > 0 {
+        digits.push((n % radix as u128) as u8);
+        n /= radix as u128;
+    }
+    digits.reverse();
+    digits
+}
+
+fn expand(a: isize, b: isize, fraction_digits: usize, radix: u32) -> Expansion {
+    let (n, d) = (a.unsigned_abs() as u128, b as u128);
+    let mut digits = integer_digits(n / d, radix);
+    let point = digits.len();
+    let mut remainder = n % d;
+    for _ in 0..fraction_digits {
+        remainder *= radix as u128;
+        digits.push((remainder / d) as u8);
+        remainder %= d;
+    }
+    Expansion { digits, point, exact: remainder == 0 }
+}
+
+/// Rounds `digits` half-up to its first `keep` digits, returning whether the
+/// rounding carried into a new leading digit.
+fn round(digits: &mut Vec<u8>, keep: usize, radix: u32) -> bool {
+    if digits.len() <= keep {
+        return false;
+    }
+    let next = digits[keep];
+    digits.truncate(keep);
+    if 2 * (next as u32) < radix {
+        return false;
+    }
+    for digit in digits.iter_mut().rev() {
+        if *digit as u32 == radix - 1 {
+            *digit = 0;
+        } else {
+            *digit += 1;
+            return false;
+        }
+    }
+    digits.insert(0, 1);
+    true
+}
+
+fn to_string(digits: &[u8]) -> String {
+    digits.iter().map(|d| std::char::from_digit(*d as u32, 36).unwrap_or('?')).collect()
+}
+
+/// Joins integer and fraction digits, leaving out trailing zeros after the point.
+fn join(integer: &[u8], fraction: &[u8]) -> String {
+    let fraction = to_string(fraction);
+    match fraction.trim_end_matches('0') {
+        "" => to_string(integer),
+        fraction => format!("{}.{}", to_string(integer), fraction),
+    }
+}
+
+fn decimal(a: isize, b: isize, n: usize, radix: u32) -> String {
+    let Expansion { mut digits, mut point, exact } = expand(a, b, n + 1, radix);
+    let exact = exact && digits.last() == Some(&0);
+    if round(&mut digits, point + n, radix) {
+        point += 1;
+    }
+    let sign = if digits.iter().all(|d| *d == 0) { "" } else { sign(a) };
+    let ellipsis = if exact { "" } else { "…" };
+    format!("{}{}{}", sign, join(&digits[..point], &digits[point..]), ellipsis)
+}
+
+fn repeating(a: isize, b: isize, radix: u32) -> String {
+    let (n, d) = (a.unsigned_abs() as u128, b as u128);
+    let mut seen = HashMap::new();
+    let mut digits = Vec::new();
+    let mut remainder = n % d;
+    while remainder != 0 && !seen.contains_key(&remainder) && digits.len() < MAX_PERIOD {
+        seen.insert(remainder, digits.len());
+        remainder *= radix as u128;
+        digits.push((remainder / d) as u8);
+        remainder %= d;
+    }
+    let integer = to_string(&integer_digits(n / d, radix));
+    match seen.get(&remainder) {
+        _ if remainder == 0 => format!("{}{}{}", sign(a), integer, fraction_part(&digits)),
+        Some(&start) => format!("{}{}.{}({})", sign(a), integer, to_string(&digits[..start]), to_string(&digits[start..])),
+        None => decimal(a, b, DEFAULT_DIGITS, radix),
+    }
+}
+
+fn fraction_part(digits: &[u8]) -> String {
+    match digits {
+        [] => String::new(),
+        digits => format!(".{}", to_string(digits)),
+    }
+}
+
+/// Writes `a / b` with `n` significant digits and an exponent that is a multiple
+/// of `step`, so a step of 1 gives scientific notation and 3 gives engineering.
+fn scientific(a: isize, b: isize, n: usize, step: i32) -> String {
+    if a == 0 {
+        return String::from("0");
+    }
+    // Enough digits to reach past the first significant digit of any isize quotient
+    let Expansion { digits, point, .. } = expand(a, b, n + 20, 10);
+    let first = digits.iter().position(|d| *d != 0).unwrap_or(0);
+    let mut exponent = point as i32 - 1 - first as i32;
+    let mut significant = digits[first..].to_vec();
+    if round(&mut significant, n, 10) {
+        exponent += 1;
+        significant.truncate(n);
+    }
+    let shift = exponent.rem_euclid(step) as usize;
+    exponent -= shift as i32;
+    significant.resize(significant.len().max(shift + 1), 0);
+    let mantissa = join(&significant[..shift + 1], &significant[shift + 1..]);
+    format!("{}{}e{}", sign(a), mantissa, exponent)
+}
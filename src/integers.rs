@@ -0,0 +1,263 @@
+use gcd::Gcd;
+
+/// Finds the least common multiple, or `None` if it is too large to be stored.
+pub fn lcm(x: isize, y: isize) -> Option<isize> {
+    if x == 0 || y == 0 {
+        return Some(0);
+    }
+    let divisor = x.unsigned_abs().gcd(y.unsigned_abs()) as isize;
+    (x / divisor).checked_mul(y)?.checked_abs()
+}
+
+fn multiply_mod(x: u64, y: u64, m: u64) -> u64 {
+    (x as u128 * y as u128 % m as u128) as u64
+}
+
+/// Raises `base` to a non-negative power modulo `m`.
+pub fn pow_mod(base: u64, mut exponent: u64, m: u64) -> u64 {
+    let mut result = 1 % m;
+    let mut base = base % m;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = multiply_mod(result, base, m);
+        }
+        base = multiply_mod(base, base, m);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// Finds the inverse of `x` modulo `m`, if `x` and `m` are coprime.
+pub fn inverse_mod(x: isize, m: isize) -> Option<isize> {
+    let m = m.unsigned_abs() as i128;
+    let (mut r0, mut r1) = (m, (x as i128).rem_euclid(m));
+    let (mut t0, mut t1) = (0i128, 1i128);
+    while r1 != 0 {
+        let quotient = r0 / r1;
+        (r0, r1) = (r1, r0 - quotient * r1);
+        (t0, t1) = (t1, t0 - quotient * t1);
+    }
+    match r0 {
+        1 => Some(t0.rem_euclid(m) as isize),
+        _ => None,
+    }
+}
+
+/// Tests primality with the Miller-Rabin test, which is deterministic for every
+/// 64-bit number with these bases.
+pub fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    for p in BASES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    let (mut d, mut s) = (n - 1, 0);
+    while d % 2 == 0 {
+        d /= 2;
+        s += 1;
+    }
+    'bases: for a in BASES {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = multiply_mod(x, x, n);
+            if x == n - 1 {
+                continue 'bases;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// Finds the smallest prime greater than `n`.
+pub fn next_prime(n: u64) -> Option<u64> {
+    let mut candidate = n.checked_add(1)?.max(2);
+    while !is_prime(candidate) {
+        candidate = candidate.checked_add(1)?;
+    }
+    Some(candidate)
+}
+
+/// Finds a non-trivial factor of a composite number with Pollard's rho method.
+fn pollard_rho(n: u64) -> u64 {
+    if n.is_multiple_of(2) {
+        return 2;
+    }
+    for c in 1.. {
+        let step = |x: u64| (multiply_mod(x, x, n) + c) % n;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+        while d == 1 {
+            x = step(x);
+            y = step(step(y));
+            d = x.abs_diff(y).gcd(n);
+        }
+        if d != n {
+            return d;
+        }
+    }
+    unreachable!()
+}
+
+/// Factors a positive number into its prime factors, in ascending order and with
+/// their multiplicities.
+pub fn factor(n: u64) -> Vec<(u64, u32)> {
+    let mut primes = Vec::new();
+    let mut stack = vec![n];
+    while let Some(m) = stack.pop() {
+        match m {
+            1 => {},
+            m if is_prime(m) => primes.push(m),
+            m => {
+                let d = pollard_rho(m);
+                stack.push(d);
+                stack.push(m / d);
+            },
+        }
+    }
+    primes.sort();
+    let mut factors: Vec<(u64, u32)> = Vec::new();
+    for p in primes {
+        match factors.last_mut() {
+            Some((q, k)) if *q == p => *k += 1,
+            _ => factors.push((p, 1)),
+        }
+    }
+    factors
+}
+
+/// Splits a positive number into `(s, r)` with `n = s^2 r`, where `r` has no
+/// square factors.
+pub fn square_part(n: u64) -> (u64, u64) {
+    factor(n).into_iter().fold((1, 1), |(s, r), (p, k)| {
+        (s * p.pow(k / 2), r * p.pow(k % 2))
+    })
+}
+
+/// Counts the numbers up to `n` that are coprime with `n`.
+pub fn totient(n: u64) -> u64 {
+    factor(n).iter().fold(n, |phi, (p, _)| phi / p * (p - 1))
+}
+
+/// Lists the positive divisors of a positive number in ascending order.
+pub fn divisors(n: u64) -> Vec<u64> {
+    let mut divisors = vec![1];
+    for (p, k) in factor(n) {
+        let mut powers = Vec::new();
+        let mut power = 1;
+        for _ in 0..k {
+            power *= p;
+            powers.extend(divisors.iter().map(|d| d * power));
+        }
+        divisors.extend(powers);
+    }
+    divisors.sort();
+    divisors
+}
+
+/// Multiplies the `k` numbers counting down from `n`, which gives `n!` when `k`
+/// is `n`.
+pub fn falling_factorial(n: u64, k: u64) -> Option<isize> {
+    let mut product: isize = 1;
+    for i in 0..k {
+        product = product.checked_mul(isize::try_from(n.checked_sub(i)?).ok()?)?;
+    }
+    Some(product)
+}
+
+pub fn factorial(n: u64) -> Option<isize> {
+    falling_factorial(n, n)
+}
+
+/// Counts the ways to choose `k` of `n` things, dividing as it goes so that the
+/// intermediate products stay as small as the result.
+pub fn choose(n: u64, k: u64) -> Option<isize> {
+    if k > n {
+        return Some(0);
+    }
+    let k = k.min(n - k);
+    let mut result: i128 = 1;
+    for i in 0..k {
+        result = result.checked_mul((n - i) as i128)? / (i + 1) as i128;
+    }
+    isize::try_from(result).ok()
+}
+
+/// Counts the ways to split `sum(ks)` things into groups of the sizes `ks`.
+pub fn multinomial(ks: &[u64]) -> Option<isize> {
+    let mut total: u64 = 0;
+    let mut result: isize = 1;
+    for &k in ks {
+        total = total.checked_add(k)?;
+        result = result.checked_mul(choose(total, k)?)?;
+    }
+    Some(result)
+}
+
+/// Finds the `n`th Catalan number by stepping from each to the next, which
+/// stays within range for every answer that does.
+pub fn catalan(n: u64) -> Option<isize> {
+    let mut result: isize = 1;
+    for k in 0..n as i128 {
+        result = isize::try_from(result as i128 * (2 * (2 * k + 1)) / (k + 2)).ok()?;
+    }
+    Some(result)
+}
+
+/// Builds the rows of a triangle of numbers up to row `n`, where each entry is
+/// found from the two entries above it by `next(row, k, left, right)`.
+fn triangle(n: u64, next: &dyn Fn(u64, u64, isize, isize) -> Option<isize>) -> Option<Vec<isize>> {
+    let mut row = vec![1];
+    for i in 1..=n {
+        let mut next_row = vec![0; i as usize + 1];
+        for k in 1..=i {
+            let left = row[k as usize - 1];
+            let right = row.get(k as usize).copied().unwrap_or(0);
+            next_row[k as usize] = next(i - 1, k, left, right)?;
+        }
+        row = next_row;
+    }
+    Some(row)
+}
+
+/// Counts the permutations of `n` things with `k` cycles.
+pub fn stirling_first(n: u64, k: u64) -> Option<isize> {
+    let row = triangle(n, &|i, _, left, right| left.checked_add(isize::try_from(i).ok()?.checked_mul(right)?))?;
+    Some(row.get(k as usize).copied().unwrap_or(0))
+}
+
+/// Counts the ways to partition `n` things into `k` non-empty sets.
+pub fn stirling_second(n: u64, k: u64) -> Option<isize> {
+    let row = triangle(n, &|_, k, left, right| left.checked_add(isize::try_from(k).ok()?.checked_mul(right)?))?;
+    Some(row.get(k as usize).copied().unwrap_or(0))
+}
+
+/// Counts the ways to partition `n` things into any number of sets.
+pub fn bell(n: u64) -> Option<isize> {
+    let row = triangle(n, &|_, k, left, right| left.checked_add(isize::try_from(k).ok()?.checked_mul(right)?))?;
+    row.into_iter().try_fold(0isize, |sum, x| sum.checked_add(x))
+}
+
+/// The largest number whose count of partitions fits in an `isize`.
+const MAX_PARTITIONS: u64 = 405;
+
+/// Counts the ways to write `n` as a sum of positive integers.
+pub fn partitions(n: u64) -> Option<isize> {
+    if n > MAX_PARTITIONS {
+        return None;
+    }
+    let mut counts = vec![0isize; n as usize + 1];
+    counts[0] = 1;
+    for part in 1..=n as usize {
+        for total in part..=n as usize {
+            counts[total] = counts[total].checked_add(counts[total - part])?;
+        }
+    }
+    Some(counts[n as usize])
+}
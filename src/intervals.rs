@@ -0,0 +1,308 @@
+use std::cmp::Ordering;
+use std::f64::consts::PI;
+use crate::eval::{Error, to_real, real_order};
+use crate::values::{Value, reduce, narrow, rational_add, rational_multiply, rational_divide, rational_sqrt, to_f64};
+
+type Rational = (isize, isize);
+
+/// The divisor of the bounds found for results that cannot be computed exactly.
+const PRECISION: isize = 1_000_000_000;
+
+/// One end of an interval. An infinite lower end is negative infinity, and an
+/// infinite upper end is positive infinity.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Endpoint {
+    Closed(isize, isize),
+    Open(isize, isize),
+    Infinite,
+}
+
+/// A point on the extended real line.
+#[derive(Clone, Copy, PartialEq)]
+enum Extended {
+    NegativeInfinity,
+    Finite(Rational),
+    PositiveInfinity,
+}
+
+/// An end of an interval while it is being computed, with whether the interval
+/// includes it.
+#[derive(Clone, Copy)]
+struct Point {
+    value: Extended,
+    closed: bool,
+}
+
+const ZERO: Extended = Extended::Finite((0, 1));
+
+fn finite(x: Rational, closed: bool) -> Point {
+    Point { value: Extended::Finite(x), closed }
+}
+
+fn compare(x: Extended, y: Extended) -> Ordering {
+    match (x, y) {
+        (Extended::Finite(x), Extended::Finite(y)) => real_order(x, y),
+        (x, y) if x == y => Ordering::Equal,
+        (Extended::NegativeInfinity, _) | (_, Extended::PositiveInfinity) => Ordering::Less,
+        _ => Ordering::Greater,
+    }
+}
+
+fn sign(x: Extended) -> Ordering {
+    compare(x, ZERO)
+}
+
+fn add_points(x: Point, y: Point) -> Result<Point, Error> {
+    let value = match (x.value, y.value) {
+        (Extended::Finite(x), Extended::Finite(y)) => Extended::Finite(rational_add(x, y).ok_or(Error::Overflow)?),
+        (Extended::Finite(_), infinity) | (infinity, _) => infinity,
+    };
+    Ok(Point { value, closed: x.closed && y.closed })
+}
+
+/// Multiplies two ends. A product with zero is zero, and is reached whenever
+/// the zero is, since it is zero times anything in the other interval.
+fn multiply_points(x: Point, y: Point) -> Result<Point, Error> {
+    if x.value == ZERO || y.value == ZERO {
+        let closed = (x.value == ZERO && x.closed) || (y.value == ZERO && y.closed) || (x.closed && y.closed);
+        return Ok(Point { value: ZERO, closed });
+    }
+    let value = match (x.value, y.value) {
+        (Extended::Finite(x), Extended::Finite(y)) => Extended::Finite(rational_multiply(x, y).ok_or(Error::Overflow)?),
+        (x, y) if sign(x) == sign(y) => Extended::PositiveInfinity,
+        _ => Extended::NegativeInfinity,
+    };
+    Ok(Point { value, closed: x.closed && y.closed })
+}
+
+fn negate_point(x: Point) -> Result<Point, Error> {
+    let value = match x.value {
+        Extended::NegativeInfinity => Extended::PositiveInfinity,
+        Extended::Finite((a, b)) => Extended::Finite((a.checked_neg().ok_or(Error::Overflow)?, b)),
+        Extended::PositiveInfinity => Extended::NegativeInfinity,
+    };
+    Ok(Point { value, ..x })
+}
+
+/// Picks the least or greatest of some ends, preferring a closed end on a tie.
+fn extreme(points: &[Point], ordering: Ordering) -> Point {
+    let mut best = points[0];
+    for point in &points[1..] {
+        match compare(point.value, best.value) {
+            Ordering::Equal => best.closed |= point.closed,
+            o if o == ordering => best = *point,
+            _ => {},
+        }
+    }
+    best
+}
+
+/// Reads a value as the ends of an interval, where a real number is an interval
+/// holding only itself.
+fn ends(x: &Value) -> Result<(Point, Point), Error> {
+    let point = |end: Endpoint, infinity: Extended| match end {
+        Endpoint::Closed(a, b) => finite((a, b), true),
+        Endpoint::Open(a, b) => finite((a, b), false),
+        Endpoint::Infinite => Point { value: infinity, closed: false },
+    };
+    match x {
+        Value::Interval(lower, upper) => Ok((point(*lower, Extended::NegativeInfinity), point(*upper, Extended::PositiveInfinity))),
+        x => {
+            let x = to_real(x)?;
+            Ok((finite(x, true), finite(x, true)))
+        },
+    }
+}
+
+fn endpoint(x: Point) -> Endpoint {
+    match x.value {
+        Extended::Finite((a, b)) if x.closed => Endpoint::Closed(a, b),
+        Extended::Finite((a, b)) => Endpoint::Open(a, b),
+        _ => Endpoint::Infinite,
+    }
+}
+
+/// Makes an interval from its ends, failing if it would be empty.
+fn interval(lower: Point, upper: Point) -> Result<Value, Error> {
+    match compare(lower.value, upper.value) {
+        Ordering::Greater => Err(Error::OutOfDomain),
+        Ordering::Equal if !(lower.closed && upper.closed) => Err(Error::OutOfDomain),
+        _ => Ok(Value::Interval(endpoint(lower), endpoint(upper))),
+    }
+}
+
+/// Makes the interval between two real numbers, including the ends that are
+/// marked as closed.
+pub fn between(lower: Value, upper: Value, closed: (bool, bool)) -> Result<Vec<Value>, Error> {
+    let (lower, upper) = (to_real(&lower)?, to_real(&upper)?);
+    Ok(vec![interval(finite(lower, closed.0), finite(upper, closed.1))?])
+}
+
+pub fn add(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    let ((x_lower, x_upper), (y_lower, y_upper)) = (ends(&x)?, ends(&y)?);
+    Ok(vec![interval(add_points(x_lower, y_lower)?, add_points(x_upper, y_upper)?)?])
+}
+
+pub fn negate(x: Value) -> Result<Vec<Value>, Error> {
+    let (lower, upper) = ends(&x)?;
+    Ok(vec![interval(negate_point(upper)?, negate_point(lower)?)?])
+}
+
+pub fn subtract(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    add(x, negate(y)?.remove(0))
+}
+
+fn multiply_ends((x_lower, x_upper): (Point, Point), (y_lower, y_upper): (Point, Point)) -> Result<Value, Error> {
+    let products = [
+        multiply_points(x_lower, y_lower)?,
+        multiply_points(x_lower, y_upper)?,
+        multiply_points(x_upper, y_lower)?,
+        multiply_points(x_upper, y_upper)?,
+    ];
+    interval(extreme(&products, Ordering::Less), extreme(&products, Ordering::Greater))
+}
+
+pub fn multiply(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    Ok(vec![multiply_ends(ends(&x)?, ends(&y)?)?])
+}
+
+/// Finds the reciprocals of an interval that does not hold zero, where an open
+/// zero end becomes infinite.
+fn reciprocal((lower, upper): (Point, Point)) -> Result<(Point, Point), Error> {
+    let invert = |x: Point, infinity: Extended| -> Result<Point, Error> {
+        let value = match x.value {
+            Extended::Finite((0, _)) => infinity,
+            Extended::Finite(x) => Extended::Finite(rational_divide((1, 1), x).ok_or(Error::Overflow)?),
+            _ => ZERO,
+        };
+        Ok(Point { value, ..x })
+    };
+    Ok((invert(upper, Extended::NegativeInfinity)?, invert(lower, Extended::PositiveInfinity)?))
+}
+
+/// Divides by an interval, splitting it at zero when zero is inside it, which
+/// gives two intervals.
+pub fn divide(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    let (x, (mut lower, mut upper)) = (ends(&x)?, ends(&y)?);
+    let open_zero = Point { value: ZERO, closed: false };
+    let pieces = match (sign(lower.value), sign(upper.value)) {
+        (Ordering::Equal, Ordering::Equal) => return Err(Error::DivisionByZero),
+        (Ordering::Less, Ordering::Greater) => vec![(lower, open_zero), (open_zero, upper)],
+        _ => {
+            lower.closed &= lower.value != ZERO;
+            upper.closed &= upper.value != ZERO;
+            vec![(lower, upper)]
+        },
+    };
+    let mut values = Vec::new();
+    for piece in pieces {
+        values.push(multiply_ends(x, reciprocal(piece)?)?);
+    }
+    Ok(values)
+}
+
+fn power_point(x: Point, n: u32) -> Result<Point, Error> {
+    let mut result = finite((1, 1), true);
+    for _ in 0..n {
+        result = multiply_points(result, x)?;
+    }
+    Ok(result)
+}
+
+/// Raises an interval to an integer power. Even powers are not just repeated
+/// products, since `x * x` cannot be negative even when `x` can.
+pub fn power(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    let n = match y {
+        Value::ComplexNumber(n, 1, 0, _) => n,
+        Value::ComplexNumber(..) => return Err(Error::NotAnInteger),
+        _ => return Err(Error::MismatchedType),
+    };
+    if n == 0 {
+        return Ok(vec![Value::integer(1)]);
+    }
+    let (lower, upper) = ends(&x)?;
+    let k = u32::try_from(n.unsigned_abs()).map_err(|_| Error::Overflow)?;
+    let (low, high) = (power_point(lower, k)?, power_point(upper, k)?);
+    let result = match (k % 2, sign(lower.value), sign(upper.value)) {
+        (1, _, _) | (_, Ordering::Equal | Ordering::Greater, _) => interval(low, high)?,
+        (_, _, Ordering::Equal | Ordering::Less) => interval(high, low)?,
+        _ => interval(finite((0, 1), true), extreme(&[low, high], Ordering::Greater))?,
+    };
+    match n {
+        n if n < 0 => divide(Value::integer(1), result),
+        _ => Ok(vec![result]),
+    }
+}
+
+/// Finds the square root of an end, rounded down for a lower end and up for an
+/// upper end when it is not rational.
+fn sqrt_point(x: Point, round_up: bool) -> Result<Point, Error> {
+    let (a, b) = match x.value {
+        Extended::Finite(x) => x,
+        _ => return Ok(x),
+    };
+    if let Some(root) = rational_sqrt((a, b)) {
+        return Ok(finite(root, x.closed));
+    }
+    let scaled = a as i128 * (PRECISION as i128 * PRECISION as i128) / b as i128;
+    let root = scaled.unsigned_abs().isqrt() as i128 + round_up as i128;
+    Ok(finite(narrow(root, PRECISION as i128).ok_or(Error::Overflow)?, true))
+}
+
+pub fn sqrt(x: Value) -> Result<Vec<Value>, Error> {
+    let (lower, upper) = ends(&x)?;
+    if sign(lower.value) == Ordering::Less {
+        return Err(Error::OutOfDomain);
+    }
+    Ok(vec![interval(sqrt_point(lower, false)?, sqrt_point(upper, true)?)?])
+}
+
+/// Bounds a float that may be off by a rounding error, widening it by a step of
+/// the precision in each direction.
+fn bounds(x: f64) -> Result<(Point, Point), Error> {
+    let scaled = x * PRECISION as f64;
+    let lower = reduce(scaled.floor() as isize - 1, PRECISION);
+    let upper = reduce(scaled.ceil() as isize + 1, PRECISION);
+    Ok((finite(lower, true), finite(upper, true)))
+}
+
+/// Bounds a function with a period of 2π over an interval, given where in the
+/// period its greatest and least values are.
+fn periodic(x: Value, f: &dyn Fn(f64) -> f64, highest: f64, lowest: f64) -> Result<Vec<Value>, Error> {
+    let (lower, upper) = match ends(&x)? {
+        (Point { value: Extended::Finite(lower), .. }, Point { value: Extended::Finite(upper), .. }) => (to_f64(lower), to_f64(upper)),
+        _ => return between(Value::integer(-1), Value::integer(1), (true, true)),
+    };
+    // Whether a turning point lies in the interval, allowing for rounding
+    let reaches = |turn: f64| ((lower - turn) / (2.0 * PI) - 1e-9).ceil() <= ((upper - turn) / (2.0 * PI) + 1e-9).floor();
+    let ((low, _), (_, high)) = (bounds(f(lower).min(f(upper)))?, bounds(f(lower).max(f(upper)))?);
+    let low = if reaches(lowest) { finite((-1, 1), true) } else { extreme(&[low, finite((-1, 1), true)], Ordering::Greater) };
+    let high = if reaches(highest) { finite((1, 1), true) } else { extreme(&[high, finite((1, 1), true)], Ordering::Less) };
+    Ok(vec![interval(low, high)?])
+}
+
+pub fn sin(x: Value) -> Result<Vec<Value>, Error> {
+    periodic(x, &f64::sin, PI / 2.0, -PI / 2.0)
+}
+
+pub fn cos(x: Value) -> Result<Vec<Value>, Error> {
+    periodic(x, &f64::cos, 0.0, PI)
+}
+
+impl std::fmt::Debug for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Endpoint::Closed(a, b) | Endpoint::Open(a, b) => write!(f, "{:?}", Value::rational(*a, *b)),
+            Endpoint::Infinite => write!(f, "∞"),
+        }
+    }
+}
+
+/// Writes an interval with a bracket for each closed end and a parenthesis for
+/// each open one.
+pub fn write(f: &mut std::fmt::Formatter<'_>, lower: &Endpoint, upper: &Endpoint) -> std::fmt::Result {
+    let left = if let Endpoint::Closed(..) = lower { "[" } else { "(" };
+    let right = if let Endpoint::Closed(..) = upper { "]" } else { ")" };
+    let minus = if let Endpoint::Infinite = lower { "-" } else { "" };
+    write!(f, "{}{}{:?}, {:?}{}", left, minus, lower, upper, right)
+}
@@ -1,5 +1,6 @@
 use crate::tokens::{Token, LexError};
 use crate::parser::Parser;
+use crate::eval::INFINITY;
 
 impl Parser {
     fn lex_word(&mut self) -> Result<Token, LexError> {
@@ -17,69 +18,143 @@ impl Parser {
             "and" => Ok(Token::And),
             "or" => Ok(Token::Or),
             "not" => Ok(Token::Not),
+            "in" => Ok(Token::In),
+            "to" => Ok(Token::To),
+            "unc" => Ok(Token::Unc),
+            "div" => Ok(Token::Div),
+            "mod" => Ok(Token::Mod),
+            "band" => Ok(Token::BitAnd),
+            "bor" => Ok(Token::BitOr),
+            "bxor" => Ok(Token::BitXor),
+            "shl" => Ok(Token::ShiftLeft),
+            "shr" => Ok(Token::ShiftRight),
             "true" => Ok(Token::True),
             "false" => Ok(Token::False),
             "i" => Ok(Token::ImaginaryConstant),
             "pi" => Ok(Token::PiConstant),
             "e" => Ok(Token::EulerConstant),
             "phi" => Ok(Token::PhiConstant),
+            "inf" => Ok(Token::Identifier(INFINITY.to_string())),
             _ => Ok(Token::Identifier(word)),
         }
     }
 
     fn lex_number(&mut self) -> Result<Token, LexError> {
+        if let Some(token) = self.lex_prefixed_number() {
+            return token;
+        }
         let mut word = String::new();
         loop {
             match self.chars[self.index..] {
-                [x, n, ..] if x == '.' && n.is_numeric() => word.push(x),
-                [x, ..] if x == '.' => break,
-                [x, ..] if !x.is_numeric() && x != '_' && x != '.' || x == '.' && word.contains('.') => break,
-                [x, ..] => word.push(x),
+                ['.', n, ..] if !word.contains('.') && n.is_numeric() => word.push('.'),
+                ['.', '(', n, ..] if !word.contains('.') && n.is_numeric() => word.push('.'),
+                [x, ..] if x.is_numeric() || x == '_' => word.push(x),
                 [..] => break,
             }
             self.index += 1;
         }
+
+        // A repeating block of digits, like `0.(3)`
+        if word.contains('.') {
+            if let ['(', ..] = self.chars[self.index..] {
+                let block = self.chars[self.index + 1..].iter().take_while(|x| x.is_numeric()).count();
+                if block > 0 && self.chars.get(self.index + block + 1) == Some(&')') {
+                    word.extend(&self.chars[self.index..self.index + block + 2]);
+                    self.index += block + 2;
+                }
+            }
+        }
+
+        // An exponent, like `1.5e-3`
+        let exponent = match self.chars[self.index..] {
+            ['e' | 'E', n, ..] if n.is_numeric() => 1,
+            ['e' | 'E', '+' | '-', n, ..] if n.is_numeric() => 2,
+            [..] => 0,
+        };
+        if exponent > 0 {
+            word.extend(&self.chars[self.index..self.index + exponent]);
+            self.index += exponent;
+            while let [x, ..] = self.chars[self.index..] {
+                if !x.is_numeric() && x != '_' {
+                    break;
+                }
+                word.push(x);
+                self.index += 1;
+            }
+        }
         Ok(Token::Number(word))
     }
 
+    /// Lexes a number written in another base, like `0xff`, `0o17` or `0b101`.
+    /// A prefix has to be followed by digits of its base, and the number can
+    /// only be a whole one.
+    fn lex_prefixed_number(&mut self) -> Option<Result<Token, LexError>> {
+        let (prefix, radix) = match self.chars[self.index..] {
+            ['0', p @ ('x' | 'X'), ..] => (p, 16),
+            ['0', p @ ('o' | 'O'), ..] => (p, 8),
+            ['0', p @ ('b' | 'B'), ..] => (p, 2),
+            [..] => return None,
+        };
+        let mut word = format!("0{}", prefix.to_ascii_lowercase());
+        self.index += 2;
+        while let [x, ..] = self.chars[self.index..] {
+            if !x.is_digit(radix) && x != '_' {
+                break;
+            }
+            word.push(x);
+            self.index += 1;
+        }
+        if !word[2..].chars().any(|x| x.is_digit(radix)) {
+            return Some(Err(LexError::MissingDigits));
+        }
+        match self.chars[self.index..] {
+            [x, ..] if x.is_alphanumeric() => Some(Err(LexError::MissingDigits)),
+            ['.', n, ..] if n.is_alphanumeric() || n == '(' => Some(Err(LexError::FractionalRadix)),
+            [..] => Some(Ok(Token::Number(word))),
+        }
+    }
+
     fn lex_symbol(&mut self) -> Result<Token, LexError> {
         match self.chars[self.index..] {
-            [] => Ok(Token::EOF),
-            ['+', '/', '-', ..] => { self.index += 3; return Ok(Token::PlusOrMinus); },
-            ['-', '>', ..] => { self.index += 2; return Ok(Token::Arrow); },
-            ['=', '>', ..] => { self.index += 2; return Ok(Token::BigArrow); },
-            ['=', '=', ..] => { self.index += 2; return Ok(Token::DoubleEqual); },
-            ['<', '=', ..] => { self.index += 2; return Ok(Token::LessThanEqual); },
-            ['>', '=', ..] => { self.index += 2; return Ok(Token::GreaterThanEqual); },
-            ['!', '=', ..] => { self.index += 2; return Ok(Token::BangEqual); },
-            ['=', ..] => { self.index += 1; return Ok(Token::Equal); },
-            [':', ..] => { self.index += 1; return Ok(Token::Colon); },
-            ['(', ..] => { self.index += 1; return Ok(Token::LeftParen); },
-            [')', ..] => { self.index += 1; return Ok(Token::RightParen); },
-            ['{', ..] => { self.index += 1; return Ok(Token::LeftBrace); },
-            ['}', ..] => { self.index += 1; return Ok(Token::RightBrace); },
-            ['[', ..] => { self.index += 1; return Ok(Token::LeftBracket); },
-            [']', ..] => { self.index += 1; return Ok(Token::RightBracket); },
-            ['<', ..] => { self.index += 1; return Ok(Token::LessThan); },
-            ['>', ..] => { self.index += 1; return Ok(Token::GreaterThan); },
-            ['|', ..] => { self.index += 1; return Ok(Token::Bar); },
-            ['+', ..] => { self.index += 1; return Ok(Token::Plus); },
-            ['-', ..] => { self.index += 1; return Ok(Token::Minus); },
-            ['*', ..] => { self.index += 1; return Ok(Token::Star); },
-            ['/', ..] => { self.index += 1; return Ok(Token::Slash); },
-            ['^', ..] => { self.index += 1; return Ok(Token::Caret); },
-            ['%', ..] => { self.index += 1; return Ok(Token::Percent); },
-            ['!', ..] => { self.index += 1; return Ok(Token::Bang); },
-            ['.', ..] => { self.index += 1; return Ok(Token::Dot); },
-            [',', ..] => { self.index += 1; return Ok(Token::Comma); },
-            ['\'', ..] => { self.index += 1; return Ok(Token::Apostrophe); },
+            [] => Ok(Token::Eof),
+            ['+', '/', '-', ..] => { self.index += 3; Ok(Token::PlusOrMinus) },
+            ['±', ..] => { self.index += 1; Ok(Token::PlusOrMinus) },
+            ['∞', ..] => { self.index += 1; Ok(Token::Identifier(INFINITY.to_string())) },
+            ['-', '>', ..] => { self.index += 2; Ok(Token::Arrow) },
+            ['=', '>', ..] => { self.index += 2; Ok(Token::BigArrow) },
+            ['=', '=', ..] => { self.index += 2; Ok(Token::DoubleEqual) },
+            ['<', '=', ..] => { self.index += 2; Ok(Token::LessThanEqual) },
+            ['>', '=', ..] => { self.index += 2; Ok(Token::GreaterThanEqual) },
+            ['!', '=', ..] => { self.index += 2; Ok(Token::BangEqual) },
+            ['.', '.', ..] => { self.index += 2; Ok(Token::DotDot) },
+            ['=', ..] => { self.index += 1; Ok(Token::Equal) },
+            [':', ..] => { self.index += 1; Ok(Token::Colon) },
+            ['(', ..] => { self.index += 1; Ok(Token::LeftParen) },
+            [')', ..] => { self.index += 1; Ok(Token::RightParen) },
+            ['{', ..] => { self.index += 1; Ok(Token::LeftBrace) },
+            ['}', ..] => { self.index += 1; Ok(Token::RightBrace) },
+            ['[', ..] => { self.index += 1; Ok(Token::LeftBracket) },
+            [']', ..] => { self.index += 1; Ok(Token::RightBracket) },
+            ['<', ..] => { self.index += 1; Ok(Token::LessThan) },
+            ['>', ..] => { self.index += 1; Ok(Token::GreaterThan) },
+            ['|', ..] => { self.index += 1; Ok(Token::Bar) },
+            ['+', ..] => { self.index += 1; Ok(Token::Plus) },
+            ['-', ..] => { self.index += 1; Ok(Token::Minus) },
+            ['*', ..] => { self.index += 1; Ok(Token::Star) },
+            ['/', ..] => { self.index += 1; Ok(Token::Slash) },
+            ['^', ..] => { self.index += 1; Ok(Token::Caret) },
+            ['%', ..] => { self.index += 1; Ok(Token::Percent) },
+            ['!', ..] => { self.index += 1; Ok(Token::Bang) },
+            ['.', ..] => { self.index += 1; Ok(Token::Dot) },
+            [',', ..] => { self.index += 1; Ok(Token::Comma) },
+            ['\'', ..] => { self.index += 1; Ok(Token::Apostrophe) },
             [..] => { self.index += 1; Err(LexError::UnrecognizedSymbol) },
         }
     }
 
     pub fn iter_token(&mut self) {
         self.token = match self.chars[self.index..] {
-            [] => Ok(Token::EOF),
+            [] => Ok(Token::Eof),
             [x, ..] if x.is_alphabetic() || x == '_' => self.lex_word(),
             [x, ..] if x.is_numeric() => self.lex_number(),
             [x, ..] if x.is_whitespace() => {
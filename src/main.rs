@@ -4,6 +4,20 @@ mod lexer;
 mod parser;
 mod values;
 mod eval;
+mod builtins;
+mod format;
+mod integers;
+mod statistics;
+mod random;
+mod units;
+mod uncertainty;
+mod intervals;
+mod polynomials;
+mod symbolic;
+mod calculus;
+mod numeric;
+mod systems;
+mod series;
 mod repl;
 
 fn main() {
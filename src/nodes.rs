@@ -1,24 +1,55 @@
+use std::cmp::Ordering;
+use crate::eval::Error;
 use crate::tokens::LexError;
+use crate::values::{reduce, gcd, integer_root, rational_add, rational_multiply, rational_divide};
 use Expression::*;
 
+/// The named constants, with the rationals they are evaluated as.
+pub const CONSTANTS: [(&str, isize, isize); 3] = [
+    ("pi", 3141592653589793, 1000000000000000),
+    ("e", 2718281828459045, 1000000000000000),
+    ("phi", 1618033988749895, 1000000000000000),
+];
+
+/// Finds the rational that a named constant is evaluated as.
+pub fn constant_value(name: &str) -> Option<(isize, isize)> {
+    CONSTANTS.iter().find(|(constant, _, _)| *constant == name).map(|(_, a, b)| (*a, *b))
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Expression {
     Number(usize, isize, isize),
+    /// A named constant like `pi`, which is only a number once it is evaluated.
+    Constant(usize, &'static str),
     ImaginaryConstant(usize),
     Boolean(bool),
     Variable(usize, String),
     Call(usize, Box<Expression>, Box<Expression>),
     Percent(usize, Box<Expression>),
     Factorial(usize, Box<Expression>),
+    /// The derivative of a function, written `f'`.
+    Derivative(usize, Box<Expression>),
     Power(usize, Box<Expression>, Box<Expression>),
     Compose(usize, Box<Expression>, Box<Expression>),
     Multiply(usize, Box<Expression>, Box<Expression>),
     Divide(usize, Box<Expression>, Box<Expression>),
+    IntegerDivide(usize, Box<Expression>, Box<Expression>),
+    Modulo(usize, Box<Expression>, Box<Expression>),
     Negate(usize, Box<Expression>),
     PlusMinus(usize, Box<Expression>),
+    Uncertainty(usize, Box<Expression>, Box<Expression>),
     Add(usize, Box<Expression>, Box<Expression>),
     Subtract(usize, Box<Expression>, Box<Expression>),
+    ShiftLeft(usize, Box<Expression>, Box<Expression>),
+    ShiftRight(usize, Box<Expression>, Box<Expression>),
+    BitAnd(usize, Box<Expression>, Box<Expression>),
+    BitOr(usize, Box<Expression>, Box<Expression>),
+    BitXor(usize, Box<Expression>, Box<Expression>),
     Tuple(usize, Vec<Expression>),
+    List(usize, Vec<Expression>),
+    Range(usize, Box<Expression>, Option<Box<Expression>>, Box<Expression>),
+    /// An interval between two numbers, with whether each end is included.
+    Interval(usize, Box<Expression>, Box<Expression>, bool, bool),
     Equal(usize, Box<Expression>, Box<Expression>),
     NotEqual(usize, Box<Expression>, Box<Expression>),
     LessThan(usize, Box<Expression>, Box<Expression>),
@@ -30,37 +61,56 @@ pub enum Expression {
     Not(usize, Box<Expression>),
     Function(usize, Box<Expression>, Box<Expression>),
     Define(usize, Box<Expression>, Box<Expression>),
+    Convert(usize, Box<Expression>, Box<Expression>),
 }
 
 #[derive(Debug)]
 pub enum ParseError {
-    LexError(LexError),
+    // Only read when the error is printed
+    LexError(#[allow(dead_code)] LexError),
     NumberExpected,
+    NumberTooLarge,
     MissingClosingDelimiter,
 }
 
 impl std::fmt::Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Number(_, a, b) if *b == 1 => write!(f, "{}", a),
-            Number(_, a, b) => write!(f, "{} / {}", a, b),
+            Number(_, a, b) => match reduce(*a, *b) {
+                (a, 1) => write!(f, "{}", a),
+                (a, b) => write!(f, "{}/{}", a, b),
+            },
+            Constant(_, name) => write!(f, "{}", name),
             ImaginaryConstant(_) => write!(f, "i"),
             Boolean(b) => write!(f, "{}", b),
             Variable(_, v) => write!(f, "{}", v),
-            Call(_, x, y) => write!(f, "{} {}", x, y),
-            Percent(_, x) => write!(f, "{}%", x),
-            Factorial(_, x) => write!(f, "{}!", x),
-            Power(_, x, y) => write!(f, "{}^{}", x, y),
-            Compose(_, x, y) => write!(f, "{} . {}", x, y),
-            Multiply(_, x, y) => write!(f, "{} * {}", x, y),
-            Divide(_, x, y) => write!(f, "{} / {}", x, y),
-            Negate(_, x) => write!(f, "-{}", x),
-            PlusMinus(_, x) => write!(f, "+/-{}", x),
-            Add(_, x, y) => match *y.clone() {
-                PlusMinus(_, y) => write!(f, "{} +/- {}", x, y),
-                _ => write!(f, "{} + {}", x, y),
+            Call(_, g, x) if matches!((&**g, &**x), (Variable(..), Tuple(..))) => write!(f, "{}{}", g, x),
+            Call(_, g, x) => match &**g {
+                Variable(_, name) if !FUNCTIONS.contains(&name.as_str()) && x.precedence() == 15 => write!(f, "{} {}", g, x),
+                Variable(..) => write!(f, "{}({})", g, x),
+                g => write_product(f, g, x),
+            },
+            Multiply(_, x, y) => write_product(f, x, y),
+            Percent(_, x) => write!(f, "{}%", operand(x, 15)),
+            Factorial(_, x) => write!(f, "{}!", operand(x, 15)),
+            Derivative(_, x) => write!(f, "{}'", operand(x, 14)),
+            Power(_, x, y) => write!(f, "{}^{}", operand(x, 14), operand(y, 14)),
+            Compose(_, x, y) => write!(f, "{} . {}", operand(x, 10), operand(y, 9)),
+            Divide(_, x, y) => write!(f, "{} / {}", operand(x, 10), operand(y, 11)),
+            IntegerDivide(_, x, y) => write!(f, "{} div {}", operand(x, 10), operand(y, 11)),
+            Modulo(_, x, y) => write!(f, "{} mod {}", operand(x, 10), operand(y, 11)),
+            Negate(_, x) => write!(f, "-{}", operand(x, 11)),
+            PlusMinus(_, x) => write!(f, "+/-{}", operand(x, 11)),
+            Add(_, x, y) => match &**y {
+                PlusMinus(_, y) => write!(f, "{} +/- {}", operand(x, 8), operand(y, 11)),
+                _ => write!(f, "{} + {}", operand(x, 8), operand(y, 9)),
             },
-            Subtract(_, x, y) => write!(f, "{} - {}", x, y),
+            Subtract(_, x, y) => write!(f, "{} - {}", operand(x, 8), operand(y, 9)),
+            ShiftLeft(_, x, y) => write!(f, "{} shl {}", operand(x, 7), operand(y, 8)),
+            ShiftRight(_, x, y) => write!(f, "{} shr {}", operand(x, 7), operand(y, 8)),
+            BitAnd(_, x, y) => write!(f, "{} band {}", operand(x, 6), operand(y, 7)),
+            BitOr(_, x, y) => write!(f, "{} bor {}", operand(x, 6), operand(y, 7)),
+            BitXor(_, x, y) => write!(f, "{} bxor {}", operand(x, 6), operand(y, 7)),
             Tuple(_, t) => {
                 write!(f, "(")?;
                 let mut tuple = t.iter().peekable();
@@ -73,41 +123,232 @@ impl std::fmt::Display for Expression {
                 }
                 write!(f, ")")
             },
-            Equal(_, x, y) => write!(f, "{} == {}", x, y),
-            NotEqual(_, x, y) => write!(f, "{} != {}", x, y),
-            LessThan(_, x, y) => write!(f, "{} < {}", x, y),
-            GreaterThan(_, x, y) => write!(f, "{} > {}", x, y),
-            LessThanEqual(_, x, y) => write!(f, "{} <= {}", x, y),
-            GreaterThanEqual(_, x, y) => write!(f, "{} >= {}", x, y),
-            And(_, x, y) => write!(f, "{} and {}", x, y),
-            Or(_, x, y) => write!(f, "{} or {}", x, y),
-            Not(_, x) => write!(f, "not {}", x),
+            List(_, xs) => {
+                write!(f, "[")?;
+                let mut list = xs.iter().peekable();
+                while let Some(expr) = list.next() {
+                    if list.peek().is_none() {
+                        write!(f, "{}", expr)?;
+                    } else {
+                        write!(f, "{}, ", expr)?;
+                    }
+                }
+                write!(f, "]")
+            },
+            Range(_, x, Some(y), z) => write!(f, "[{}, {} .. {}]", x, y, z),
+            Range(_, x, None, z) => write!(f, "[{} .. {}]", x, z),
+            Interval(_, x, y, l, r) => write!(f, "{}{}, {}{}", if *l { "[" } else { "(" }, x, y, if *r { "]" } else { ")" }),
+            Equal(_, x, y) => write!(f, "{} == {}", operand(x, 6), operand(y, 6)),
+            NotEqual(_, x, y) => write!(f, "{} != {}", operand(x, 6), operand(y, 6)),
+            LessThan(_, x, y) => write!(f, "{} < {}", operand(x, 6), operand(y, 6)),
+            GreaterThan(_, x, y) => write!(f, "{} > {}", operand(x, 6), operand(y, 6)),
+            LessThanEqual(_, x, y) => write!(f, "{} <= {}", operand(x, 6), operand(y, 6)),
+            GreaterThanEqual(_, x, y) => write!(f, "{} >= {}", operand(x, 6), operand(y, 6)),
+            And(_, x, y) => write!(f, "{} and {}", operand(x, 4), operand(y, 5)),
+            Or(_, x, y) => write!(f, "{} or {}", operand(x, 3), operand(y, 4)),
+            Not(_, x) => write!(f, "not {}", operand(x, 11)),
             Function(_, x, y) => write!(f, "{} => {}", x, y),
             Define(_, x, y) => write!(f, "{} = {}", x, y),
+            Convert(_, x, y) => write!(f, "{} in {}", x, y),
+            Uncertainty(_, x, y) => write!(f, "{} +/- {} unc", operand(x, 8), operand(y, 9)),
         }
     }
 }
 
+/// Writes an operand, in parentheses if it binds more loosely than `precedence`.
+fn operand(x: &Expression, precedence: u8) -> String {
+    match x.precedence() < precedence {
+        true => format!("({})", x),
+        false => x.to_string(),
+    }
+}
+
+/// Whether a factor is a name, a call of one, or a power of either, which can
+/// follow another factor without a `*`.
+fn is_named(x: &Expression) -> bool {
+    match x {
+        Variable(..) | Constant(..) => true,
+        Call(_, f, _) => matches!(**f, Variable(..)),
+        Power(_, x, _) => is_named(x),
+        _ => false,
+    }
+}
+
+/// Writes a product, leaving out the `*` where implicit multiplication reads the
+/// same way, as in `2x` and `x y^2`.
+fn write_product(f: &mut std::fmt::Formatter<'_>, x: &Expression, y: &Expression) -> std::fmt::Result {
+    let implicit = is_named(y) && match x {
+        Multiply(_, _, z) => is_named(z),
+        x => x.precedence() >= 11,
+    };
+    let named_variable = match y {
+        Variable(_, name) => Some(name),
+        Power(_, y, _) => match &**y {
+            Variable(_, name) => Some(name),
+            _ => None,
+        },
+        _ => None,
+    };
+    match (implicit, x) {
+        // `2e1` would read as a number, so names that start with `e` get a space
+        (true, Number(..)) if named_variable.is_some_and(|name| !name.starts_with(['e', 'E'])) => {
+            write!(f, "{}{}", x, y)
+        },
+        (true, _) => write!(f, "{} {}", x, y),
+        (false, _) => write!(f, "{} * {}", operand(x, 10), operand(y, 11)),
+    }
+}
+
 impl Expression {
+    /// Makes the named constant called `name`, or a variable if there is none.
+    pub fn constant(index: usize, name: &str) -> Expression {
+        match CONSTANTS.iter().find(|(constant, _, _)| *constant == name) {
+            Some((constant, _, _)) => Constant(index, constant),
+            None => Variable(index, name.to_string()),
+        }
+    }
+
+    /// How tightly the expression binds, for deciding where it needs parentheses.
+    fn precedence(&self) -> u8 {
+        match self {
+            Convert(..) => 0,
+            Define(..) => 1,
+            Function(..) => 2,
+            Or(..) => 3,
+            And(..) => 4,
+            Equal(..) | NotEqual(..) | LessThan(..) | GreaterThan(..) | LessThanEqual(..) | GreaterThanEqual(..) => 5,
+            BitAnd(..) | BitOr(..) | BitXor(..) => 6,
+            ShiftLeft(..) | ShiftRight(..) => 7,
+            Add(..) | Subtract(..) | Uncertainty(..) => 8,
+            Compose(..) => 9,
+            Multiply(..) | Divide(..) | IntegerDivide(..) | Modulo(..) => 10,
+            Number(_, a, b) if reduce(*a, *b).1 != 1 => 10,
+            Negate(..) | PlusMinus(..) | Not(..) => 11,
+            Number(_, a, _) if *a < 0 => 11,
+            Call(_, f, _) if !matches!(**f, Variable(..)) => 12,
+            Power(..) => 13,
+            Percent(..) | Factorial(..) | Derivative(..) => 14,
+            _ => 15,
+        }
+    }
+
+    /// Rebuilds the expression with `f` applied to each of its direct parts.
+    pub fn try_map<E>(&self, f: &mut dyn FnMut(&Expression) -> Result<Expression, E>) -> Result<Expression, E> {
+        let mut g = |x: &Expression| f(x).map(Box::new);
+        let expr = match self {
+            Number(..) | Constant(..) | ImaginaryConstant(_) | Boolean(_) | Variable(..) => self.clone(),
+            Call(i, a, b) => Call(*i, g(a)?, g(b)?),
+            Percent(i, a) => Percent(*i, g(a)?),
+            Factorial(i, a) => Factorial(*i, g(a)?),
+            Derivative(i, a) => Derivative(*i, g(a)?),
+            Power(i, a, b) => Power(*i, g(a)?, g(b)?),
+            Compose(i, a, b) => Compose(*i, g(a)?, g(b)?),
+            Multiply(i, a, b) => Multiply(*i, g(a)?, g(b)?),
+            Divide(i, a, b) => Divide(*i, g(a)?, g(b)?),
+            IntegerDivide(i, a, b) => IntegerDivide(*i, g(a)?, g(b)?),
+            Modulo(i, a, b) => Modulo(*i, g(a)?, g(b)?),
+            Negate(i, a) => Negate(*i, g(a)?),
+            PlusMinus(i, a) => PlusMinus(*i, g(a)?),
+            Uncertainty(i, a, b) => Uncertainty(*i, g(a)?, g(b)?),
+            Add(i, a, b) => Add(*i, g(a)?, g(b)?),
+            Subtract(i, a, b) => Subtract(*i, g(a)?, g(b)?),
+            ShiftLeft(i, a, b) => ShiftLeft(*i, g(a)?, g(b)?),
+            ShiftRight(i, a, b) => ShiftRight(*i, g(a)?, g(b)?),
+            BitAnd(i, a, b) => BitAnd(*i, g(a)?, g(b)?),
+            BitOr(i, a, b) => BitOr(*i, g(a)?, g(b)?),
+            BitXor(i, a, b) => BitXor(*i, g(a)?, g(b)?),
+            Tuple(i, xs) => Tuple(*i, xs.iter().map(|x| g(x).map(|x| *x)).collect::<Result<_, _>>()?),
+            List(i, xs) => List(*i, xs.iter().map(|x| g(x).map(|x| *x)).collect::<Result<_, _>>()?),
+            Range(i, a, b, c) => Range(*i, g(a)?, b.as_ref().map(|b| g(b)).transpose()?, g(c)?),
+            Interval(i, a, b, l, r) => Interval(*i, g(a)?, g(b)?, *l, *r),
+            Equal(i, a, b) => Equal(*i, g(a)?, g(b)?),
+            NotEqual(i, a, b) => NotEqual(*i, g(a)?, g(b)?),
+            LessThan(i, a, b) => LessThan(*i, g(a)?, g(b)?),
+            GreaterThan(i, a, b) => GreaterThan(*i, g(a)?, g(b)?),
+            LessThanEqual(i, a, b) => LessThanEqual(*i, g(a)?, g(b)?),
+            GreaterThanEqual(i, a, b) => GreaterThanEqual(*i, g(a)?, g(b)?),
+            And(i, a, b) => And(*i, g(a)?, g(b)?),
+            Or(i, a, b) => Or(*i, g(a)?, g(b)?),
+            Not(i, a) => Not(*i, g(a)?),
+            Function(i, x, a) => Function(*i, x.clone(), g(a)?),
+            Define(i, x, a) => Define(*i, x.clone(), g(a)?),
+            Convert(i, a, b) => Convert(*i, g(a)?, g(b)?),
+        };
+        Ok(expr)
+    }
+
+    /// The free variables of an expression in alphabetical order, leaving out the
+    /// names of the functions it calls.
+    pub fn variables(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        self.collect_variables(&mut names);
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn collect_variables(&self, names: &mut Vec<String>) {
+        match self {
+            Variable(_, name) => names.push(name.clone()),
+            Call(_, f, x) if matches!(&**f, Variable(_, name) if FUNCTIONS.contains(&name.as_str()) || matches!(**x, Tuple(..))) => {
+                x.collect_variables(names)
+            },
+            Function(..) => {},
+            x => {
+                let _ = x.try_map(&mut |e| {
+                    e.collect_variables(names);
+                    Ok::<_, ()>(e.clone())
+                });
+            },
+        }
+    }
+
+    /// Simplifies the arithmetic in an expression that may have free variables.
+    /// Constants are folded, like terms and factors are collected, powers of
+    /// products and logarithms of powers are taken apart, and the result is
+    /// written in a canonical order, like `x^2 - 3x + 2`. Products of sums are
+    /// left as they are. Dividing by zero or folding a number too large to hold
+    /// is an error.
+    pub fn simplify(&self) -> Result<Expression, Error> {
+        match self {
+            Number(..) | ImaginaryConstant(_) | Variable(..) | Call(..) | Percent(..) | Power(..)
+            | Multiply(..) | Divide(..) | Negate(..) | Add(..) | Subtract(..) => Ok(rebuild(&terms(self)?)),
+            _ => self.try_map(&mut |x| x.simplify()),
+        }
+    }
+
     pub fn sub(&self, old: &Expression, new: &Expression) -> Box<Expression> {
         let expr = match (self.clone(), old.clone()) {
             (Variable(_, name), Variable(_, name2)) if name == name2 => new.clone(),
             (Number(_, _, _), _) => self.clone(),
+            (Constant(..), _) => self.clone(),
             (ImaginaryConstant(_), _) => self.clone(),
             (Boolean(_), _) => self.clone(),
             (Variable(_, _), _) => self.clone(),
             (Call(i, a, b), _) => Call(i, a.sub(old, new), b.sub(old, new)),
             (Percent(i, a), _) => Percent(i, a.sub(old, new)),
             (Factorial(i, a), _) => Factorial(i, a.sub(old, new)),
+            (Derivative(i, a), _) => Derivative(i, a.sub(old, new)),
             (Power(i, a, b), _) => Power(i, a.sub(old, new), b.sub(old, new)),
             (Compose(i, a, b), _) => Compose(i, a.sub(old, new), b.sub(old, new)),
             (Multiply(i, a, b), _) => Multiply(i, a.sub(old, new), b.sub(old, new)),
             (Divide(i, a, b), _) => Divide(i, a.sub(old, new), b.sub(old, new)),
+            (IntegerDivide(i, a, b), _) => IntegerDivide(i, a.sub(old, new), b.sub(old, new)),
+            (Modulo(i, a, b), _) => Modulo(i, a.sub(old, new), b.sub(old, new)),
             (Negate(i, a), _) => Negate(i, a.sub(old, new)),
             (PlusMinus(i, a), _) => PlusMinus(i, a.sub(old, new)),
+            (Uncertainty(i, a, b), _) => Uncertainty(i, a.sub(old, new), b.sub(old, new)),
             (Add(i, a, b), _) => Add(i, a.sub(old, new), b.sub(old, new)),
             (Subtract(i, a, b), _) => Subtract(i, a.sub(old, new), b.sub(old, new)),
+            (ShiftLeft(i, a, b), _) => ShiftLeft(i, a.sub(old, new), b.sub(old, new)),
+            (ShiftRight(i, a, b), _) => ShiftRight(i, a.sub(old, new), b.sub(old, new)),
+            (BitAnd(i, a, b), _) => BitAnd(i, a.sub(old, new), b.sub(old, new)),
+            (BitOr(i, a, b), _) => BitOr(i, a.sub(old, new), b.sub(old, new)),
+            (BitXor(i, a, b), _) => BitXor(i, a.sub(old, new), b.sub(old, new)),
             (Tuple(i, xs), _) => Tuple(i, xs.into_iter().map(|x| *x.sub(old, new)).collect()),
+            (List(i, xs), _) => List(i, xs.into_iter().map(|x| *x.sub(old, new)).collect()),
+            (Range(i, a, b, c), _) => Range(i, a.sub(old, new), b.map(|b| b.sub(old, new)), c.sub(old, new)),
+            (Interval(i, a, b, l, r), _) => Interval(i, a.sub(old, new), b.sub(old, new), l, r),
             (Equal(i, a, b), _) => Equal(i, a.sub(old, new), b.sub(old, new)),
             (NotEqual(i, a, b), _) => NotEqual(i, a.sub(old, new), b.sub(old, new)),
             (LessThan(i, a, b), _) => LessThan(i, a.sub(old, new), b.sub(old, new)),
@@ -119,8 +360,426 @@ impl Expression {
             (Not(i, a), _) => Not(i, a.sub(old, new)),
             (Function(i, x, f), _) => Function(i, x, f.sub(old, new)),
             (Define(i, a, b), _) => Define(i, a.sub(old, new), b.sub(old, new)),
+            (Convert(i, a, b), _) => Convert(i, a.sub(old, new), b.sub(old, new)),
         };
 
         Box::new(expr)
     }
 }
+
+type Rational = (isize, isize);
+
+/// A rational coefficient times a product of factors, which a simplified sum is
+/// made of. Each factor is a base that is not a product, with its exponent.
+#[derive(Clone)]
+struct Term {
+    coefficient: Rational,
+    factors: Vec<(Expression, Expression)>,
+}
+
+/// The functions that simplification knows the rules of.
+pub const FUNCTIONS: [&str; 9] = ["sqrt", "exp", "ln", "log", "sin", "cos", "tan", "atan", "abs"];
+
+fn number(c: Rational) -> Expression {
+    let (a, b) = reduce(c.0, c.1);
+    Number(0, a, b)
+}
+
+fn one() -> Expression {
+    Number(0, 1, 1)
+}
+
+/// Reads a number, which a named constant is not.
+fn rational(x: &Expression) -> Option<Rational> {
+    match x {
+        Number(_, a, b) => Some(reduce(*a, *b)),
+        Negate(_, x) => rational(x).map(|(a, b)| (-a, b)),
+        _ => None,
+    }
+}
+
+fn is_one(x: &Expression) -> bool {
+    rational(x) == Some((1, 1))
+}
+
+fn is_constant(x: &Expression, name: &str) -> bool {
+    matches!(x, Constant(_, constant) if *constant == name)
+}
+
+fn key(x: &Expression) -> String {
+    x.to_string()
+}
+
+fn constant(c: Rational) -> Vec<Term> {
+    match c {
+        (0, _) => vec![],
+        (a, b) => vec![Term { coefficient: reduce(a, b), factors: vec![] }],
+    }
+}
+
+fn atom(x: Expression) -> Vec<Term> {
+    vec![Term { coefficient: (1, 1), factors: vec![(x, one())] }]
+}
+
+fn call(name: &str, x: Expression) -> Expression {
+    Call(0, Box::new(Variable(0, name.to_string())), Box::new(x))
+}
+
+/// Reads a sum with no factors as a number.
+fn as_constant(terms: &[Term]) -> Option<Rational> {
+    match terms {
+        [] => Some((0, 1)),
+        [term] if term.factors.is_empty() => Some(term.coefficient),
+        _ => None,
+    }
+}
+
+fn is_negative(terms: &[Term]) -> bool {
+    terms.first().is_some_and(|term| term.coefficient.0 < 0)
+}
+
+fn scale(terms: Vec<Term>, c: Rational) -> Result<Vec<Term>, Error> {
+    if c.0 == 0 {
+        return Ok(vec![]);
+    }
+    terms.into_iter().map(|term| Ok(Term { coefficient: rational_multiply(term.coefficient, c).ok_or(Error::Overflow)?, ..term })).collect()
+}
+
+fn negated(terms: Vec<Term>) -> Result<Vec<Term>, Error> {
+    scale(terms, (-1, 1))
+}
+
+/// Raises a rational to a rational power, giving `None` if the result is not
+/// rational.
+fn rational_power((a, b): Rational, (p, q): Rational) -> Result<Option<Rational>, Error> {
+    if a == 0 {
+        return if p > 0 { Ok(Some((0, 1))) } else { Err(Error::DivisionByZero) };
+    }
+    let roots = match q {
+        1 => Some((a, b)),
+        q if a < 0 && q % 2 == 0 => None,
+        q => integer_root(a.abs(), q).zip(integer_root(b, q)).map(|(c, d)| (c * a.signum(), d)),
+    };
+    let Some(mut base) = roots else {
+        return Ok(None);
+    };
+    let mut result = (1, 1);
+    let mut exponent = p.unsigned_abs();
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = rational_multiply(result, base).ok_or(Error::Overflow)?;
+        }
+        exponent >>= 1;
+        if exponent > 0 {
+            base = rational_multiply(base, base).ok_or(Error::Overflow)?;
+        }
+    }
+    match p < 0 {
+        true => rational_divide((1, 1), result).ok_or(Error::Overflow).map(Some),
+        false => Ok(Some(result)),
+    }
+}
+
+/// Takes an expression apart into a sum of terms, simplifying as it goes.
+fn terms(x: &Expression) -> Result<Vec<Term>, Error> {
+    match x {
+        Number(_, a, b) => Ok(constant((*a, *b))),
+        Add(_, x, y) => collect([terms(x)?, terms(y)?].concat()),
+        Subtract(_, x, y) => collect([terms(x)?, negated(terms(y)?)?].concat()),
+        Negate(_, x) => negated(terms(x)?),
+        Percent(_, x) => scale(terms(x)?, (1, 100)),
+        Multiply(_, x, y) => product(terms(x)?, terms(y)?),
+        Divide(_, x, y) => product(terms(x)?, power(terms(y)?, constant((-1, 1)))?),
+        Power(_, x, y) => power(terms(x)?, terms(y)?),
+        Call(_, f, x) => match &**f {
+            Variable(_, name) if matches!(**x, Tuple(..)) => Ok(atom(call(name, x.simplify()?))),
+            Variable(_, name) if FUNCTIONS.contains(&name.as_str()) => function(name, terms(x)?),
+            _ => product(terms(f)?, terms(x)?),
+        },
+        Constant(..) | ImaginaryConstant(_) | Variable(..) => Ok(atom(x.clone())),
+        x => Ok(atom(x.simplify()?)),
+    }
+}
+
+/// Adds up the terms with the same factors and puts them in order.
+fn collect(terms: Vec<Term>) -> Result<Vec<Term>, Error> {
+    let mut collected: Vec<(String, Term)> = Vec::new();
+    for term in terms {
+        let factors = key(&rebuild_factors(&term.factors));
+        match collected.iter_mut().find(|(k, _)| *k == factors) {
+            Some((_, t)) => t.coefficient = rational_add(t.coefficient, term.coefficient).ok_or(Error::Overflow)?,
+            None => collected.push((factors, term)),
+        }
+    }
+    let mut terms: Vec<Term> = collected.into_iter().map(|(_, t)| t).filter(|t| t.coefficient.0 != 0).collect();
+    terms.sort_by(term_order);
+    Ok(terms)
+}
+
+/// The total of the numeric exponents of a term's variables and functions.
+fn degree(term: &Term) -> Rational {
+    term.factors.iter()
+        .filter(|(base, _)| !matches!(base, Number(..) | Constant(..) | ImaginaryConstant(_)))
+        .fold((0, 1), |total, (_, exponent)| rational(exponent).and_then(|e| rational_add(total, e)).unwrap_or(total))
+}
+
+/// Orders terms from the highest degree down, and then by their factors, so that
+/// `x^2` comes before `x y`, which comes before `y^2`.
+fn term_order(a: &Term, b: &Term) -> Ordering {
+    let (p, q) = (degree(a), degree(b));
+    (q.0 as i128 * p.1 as i128).cmp(&(p.0 as i128 * q.1 as i128)).then_with(|| {
+        for ((x, m), (y, n)) in a.factors.iter().zip(&b.factors) {
+            let order = factor_order(&(x.clone(), one()), &(y.clone(), one())).then_with(|| key(n).cmp(&key(m)));
+            if order != Ordering::Equal {
+                return order;
+            }
+        }
+        a.factors.len().cmp(&b.factors.len())
+    })
+}
+
+/// Orders factors with numbers first, then variables, then everything else.
+fn factor_order((a, _): &(Expression, Expression), (b, _): &(Expression, Expression)) -> Ordering {
+    let rank = |x: &Expression| match x {
+        Number(..) | Constant(..) | ImaginaryConstant(_) => 0,
+        Variable(..) => 1,
+        _ => 2,
+    };
+    rank(a).cmp(&rank(b)).then_with(|| key(a).cmp(&key(b)))
+}
+
+/// Drops factors raised to zero, and folds rational powers of numbers and
+/// integer powers of `i` into the coefficient.
+fn normalize(mut term: Term) -> Result<Term, Error> {
+    let mut factors = Vec::new();
+    for (base, exponent) in std::mem::take(&mut term.factors) {
+        match (&base, rational(&exponent)) {
+            (_, Some((0, _))) => {},
+            (ImaginaryConstant(_), Some((n, 1))) => {
+                if n.rem_euclid(4) >= 2 {
+                    term.coefficient.0 = -term.coefficient.0;
+                }
+                if n % 2 != 0 {
+                    factors.push((base, one()));
+                }
+            },
+            (_, Some(p)) => match rational(&base).map(|c| rational_power(c, p)).transpose()?.flatten() {
+                Some(c) => term.coefficient = rational_multiply(term.coefficient, c).ok_or(Error::Overflow)?,
+                None => factors.push((base, exponent)),
+            },
+            _ => factors.push((base, exponent)),
+        }
+    }
+    factors.sort_by(factor_order);
+    term.factors = factors;
+    Ok(term)
+}
+
+/// Multiplies two terms, adding the exponents of equal bases.
+fn multiply_terms(x: Term, y: Term) -> Result<Term, Error> {
+    let mut term = Term { coefficient: rational_multiply(x.coefficient, y.coefficient).ok_or(Error::Overflow)?, factors: x.factors };
+    for (base, exponent) in y.factors {
+        match term.factors.iter().position(|(b, _)| key(b) == key(&base)) {
+            Some(i) => {
+                let sum = collect([terms(&term.factors[i].1)?, terms(&exponent)?].concat())?;
+                term.factors[i].1 = rebuild(&sum);
+            },
+            None => term.factors.push((base, exponent)),
+        }
+    }
+    normalize(term)
+}
+
+/// Multiplies out a term whose only factor is a sum, so that sums never nest.
+fn splice(term: Term) -> Result<Vec<Term>, Error> {
+    match &term.factors[..] {
+        _ if term.coefficient.0 == 0 => Ok(vec![]),
+        [(base @ (Add(..) | Subtract(..)), exponent)] if is_one(exponent) => scale(terms(base)?, term.coefficient),
+        _ => Ok(vec![term]),
+    }
+}
+
+/// Splits a sum into its content, signed like its first term, and the sum divided
+/// by it, so that `-2x - 4` is `-2` times `x + 2`.
+fn content(terms: &[Term]) -> Result<(Rational, Expression), Error> {
+    let mut c = (0, 1);
+    for term in terms {
+        let (a, b) = term.coefficient;
+        let divisor = gcd(c.1, b).ok_or(Error::Overflow)?;
+        c = (gcd(c.0, a).ok_or(Error::Overflow)?, (c.1 / divisor).checked_mul(b).ok_or(Error::Overflow)?);
+    }
+    if is_negative(terms) {
+        c.0 = -c.0;
+    }
+    let divided = scale(terms.to_vec(), rational_divide((1, 1), c).ok_or(Error::Overflow)?)?;
+    Ok((c, rebuild(&divided)))
+}
+
+/// Reads a sum as a single term, where a sum of several terms is one factor.
+fn as_term(terms: Vec<Term>) -> Result<Term, Error> {
+    match &terms[..] {
+        [term] => Ok(term.clone()),
+        _ => {
+            let (c, sum) = content(&terms)?;
+            Ok(Term { coefficient: c, factors: vec![(sum, one())] })
+        },
+    }
+}
+
+/// Multiplies two sums, multiplying out only a number times a sum.
+fn product(x: Vec<Term>, y: Vec<Term>) -> Result<Vec<Term>, Error> {
+    match (as_constant(&x), as_constant(&y)) {
+        (Some(c), _) => scale(y, c),
+        (_, Some(c)) => scale(x, c),
+        _ => splice(multiply_terms(as_term(x)?, as_term(y)?)?),
+    }
+}
+
+/// Makes a single factor, raised to a power.
+fn factor(base: Expression, exponent: Expression) -> Result<Vec<Term>, Error> {
+    splice(normalize(Term { coefficient: (1, 1), factors: vec![(base, exponent)] })?)
+}
+
+/// Raises a sum to a power. Integer powers of a term are taken apart, as in
+/// `(2x^2)^3 = 8x^6`, but fractional powers only of a number or a single name,
+/// since `(x^2)^(1/2)` is not always `x`.
+fn power(base: Vec<Term>, exponent: Vec<Term>) -> Result<Vec<Term>, Error> {
+    let n = as_constant(&exponent);
+    match n {
+        Some((0, _)) => return Ok(constant((1, 1))),
+        Some((1, 1)) => return Ok(base),
+        _ => {},
+    }
+    // e^(n ln x) = x^n
+    if let ([b], [t]) = (&base[..], &exponent[..]) {
+        if let ((1, 1), [(e, one)], [(Call(_, f, x), log)]) = (b.coefficient, &b.factors[..], &t.factors[..]) {
+            if is_constant(e, "e") && is_one(one) && is_one(log) && matches!(&**f, Variable(_, name) if name == "ln") {
+                return power(terms(x)?, constant(t.coefficient));
+            }
+        }
+    }
+    match (&base[..], n) {
+        ([], Some((p, _))) if p > 0 => Ok(vec![]),
+        ([], Some(_)) => Err(Error::DivisionByZero),
+        ([term], Some((p, 1))) => {
+            let mut term = term.clone();
+            term.coefficient = rational_power(term.coefficient, (p, 1))?.ok_or(Error::Overflow)?;
+            for (_, e) in term.factors.iter_mut() {
+                *e = rebuild(&scale(terms(e)?, (p, 1))?);
+            }
+            splice(normalize(term)?)
+        },
+        ([term], Some(q)) if term.factors.is_empty() => factor(number(term.coefficient), number(q)),
+        ([term], Some(q)) if term.coefficient == (1, 1) && term.factors.len() == 1 && is_one(&term.factors[0].1) => {
+            factor(term.factors[0].0.clone(), number(q))
+        },
+        ([term], None) if term.coefficient == (1, 1) && term.factors.len() == 1 => {
+            let (b, e) = &term.factors[0];
+            factor(b.clone(), rebuild(&product(terms(e)?, exponent)?))
+        },
+        ([term], None) if term.factors.is_empty() => factor(number(term.coefficient), rebuild(&exponent)),
+        ([_, _, ..], Some((p, 1))) => {
+            let (c, sum) = content(&base)?;
+            let term = Term { coefficient: rational_power(c, (p, 1))?.ok_or(Error::Overflow)?, factors: vec![(sum, number((p, 1)))] };
+            Ok(vec![term])
+        },
+        _ => factor(rebuild(&base), rebuild(&exponent)),
+    }
+}
+
+/// Applies a function that simplification knows the rules of.
+fn function(name: &str, x: Vec<Term>) -> Result<Vec<Term>, Error> {
+    match name {
+        "sqrt" => power(x, constant((1, 2))),
+        "exp" => power(atom(Expression::constant(0, "e")), x),
+        "ln" | "log" => logarithm(name, x),
+        "sin" | "tan" | "atan" if x.is_empty() => Ok(vec![]),
+        "cos" if x.is_empty() => Ok(constant((1, 1))),
+        "abs" if as_constant(&x).is_some() => Ok(as_constant(&x).map(|(a, b)| constant((a.abs(), b))).unwrap_or_default()),
+        "sin" | "tan" | "atan" if is_negative(&x) => negated(function(name, negated(x)?)?),
+        "cos" | "abs" if is_negative(&x) => function(name, negated(x)?),
+        name => Ok(atom(call(name, rebuild(&x)))),
+    }
+}
+
+/// Applies `log(c x) = log(c) + log(x)` for a positive number `c`, and
+/// `log(a^y) = y log(a)` where `a` is a positive number or a named constant,
+/// and for the natural logarithm, `ln(e) = 1`. The rest of the product is left
+/// under the logarithm, since `ln(x^2)` is not `2 ln(x)` for a negative `x`.
+fn logarithm(name: &str, x: Vec<Term>) -> Result<Vec<Term>, Error> {
+    match &x[..] {
+        [term] if term.coefficient.0 > 0 => {
+            let mut sum = match term.coefficient {
+                (1, 1) => vec![],
+                c => atom(call(name, number(c))),
+            };
+            let mut rest = Vec::new();
+            for (base, exponent) in &term.factors {
+                match base {
+                    base if name == "ln" && is_constant(base, "e") => sum.extend(terms(exponent)?),
+                    Constant(..) => sum.extend(product(terms(exponent)?, atom(call(name, base.clone())))?),
+                    base if rational(base).is_some_and(|(a, _)| a > 0) => {
+                        sum.extend(product(terms(exponent)?, atom(call(name, base.clone())))?);
+                    },
+                    _ => rest.push((base.clone(), exponent.clone())),
+                }
+            }
+            if !rest.is_empty() {
+                sum.extend(atom(call(name, rebuild_factors(&rest))));
+            }
+            collect(sum)
+        },
+        _ => Ok(atom(call(name, rebuild(&x)))),
+    }
+}
+
+fn rebuild_factors(factors: &[(Expression, Expression)]) -> Expression {
+    let mut factors = factors.iter().map(|(base, exponent)| match is_one(exponent) {
+        true => base.clone(),
+        false => Power(0, Box::new(base.clone()), Box::new(exponent.clone())),
+    });
+    let first = factors.next().unwrap_or_else(one);
+    factors.fold(first, |product, x| Multiply(0, Box::new(product), Box::new(x)))
+}
+
+/// Writes a term, with negative powers and the coefficient's divisor under a
+/// division, as in `3x / (2y)`.
+fn rebuild_term(term: &Term) -> Expression {
+    let (a, b) = term.coefficient;
+    if term.factors.is_empty() {
+        return number(term.coefficient);
+    }
+    let (above, below): (Vec<_>, Vec<_>) = term.factors.iter().cloned().partition(|(_, exponent)| !is_negative(&terms(exponent).unwrap_or_default()));
+    let below: Vec<_> = below.into_iter().map(|(base, exponent)| (base.clone(), Negate(0, Box::new(exponent.clone())).simplify().unwrap_or(Negate(0, Box::new(exponent))))).collect();
+    let mut factors = above.iter().map(|x| rebuild_factors(std::slice::from_ref(x)));
+    let first = match (a, factors.next()) {
+        (1, Some(x)) => x,
+        (-1, Some(x)) => Negate(0, Box::new(x)),
+        (a, Some(x)) => Multiply(0, Box::new(number((a, 1))), Box::new(x)),
+        (a, None) => number((a, 1)),
+    };
+    let numerator = factors.fold(first, |product, x| Multiply(0, Box::new(product), Box::new(x)));
+    let denominator = match (b, &below[..]) {
+        (1, []) => return numerator,
+        (b, []) => number((b, 1)),
+        (1, below) => rebuild_factors(below),
+        (b, below) => Multiply(0, Box::new(number((b, 1))), Box::new(rebuild_factors(below))),
+    };
+    Divide(0, Box::new(numerator), Box::new(denominator))
+}
+
+/// Writes a sum of terms, subtracting the negative ones after the first.
+fn rebuild(terms: &[Term]) -> Expression {
+    let mut terms = terms.iter();
+    let first = match terms.next() {
+        Some(term) => rebuild_term(term),
+        None => return number((0, 1)),
+    };
+    terms.fold(first, |sum, term| match term.coefficient.0 < 0 {
+        true => {
+            let positive = Term { coefficient: (-term.coefficient.0, term.coefficient.1), factors: term.factors.clone() };
+            Subtract(0, Box::new(sum), Box::new(rebuild_term(&positive)))
+        },
+        false => Add(0, Box::new(sum), Box::new(rebuild_term(term))),
+    })
+}
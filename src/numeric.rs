@@ -0,0 +1,766 @@
+use crate::eval::Error;
+use crate::nodes::{Expression, FUNCTIONS};
+use Expression::*;
+
+/// Gives the floating-point value of a named constant.
+fn constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" => Some(std::f64::consts::PI),
+        "e" => Some(std::f64::consts::E),
+        "phi" => Some((1.0 + 5f64.sqrt()) / 2.0),
+        _ => None,
+    }
+}
+
+/// Evaluates an expression in floating point with the variable `x` set to `t`,
+/// or gives `None` if it uses anything other than arithmetic, the functions
+/// that simplification knows, and `x`.
+pub fn evaluate(expr: &Expression, x: &str, t: f64) -> Option<f64> {
+    let value = |u: &Expression| evaluate(u, x, t);
+    let result = match expr {
+        Number(_, a, b) => *a as f64 / *b as f64,
+        Constant(_, name) => constant(name)?,
+        Variable(_, name) if name == x => t,
+        Negate(_, u) => -value(u)?,
+        Percent(_, u) => value(u)? / 100.0,
+        Add(_, u, v) => value(u)? + value(v)?,
+        Subtract(_, u, v) => value(u)? - value(v)?,
+        Multiply(_, u, v) => value(u)? * value(v)?,
+        Divide(_, u, v) => value(u)? / value(v)?,
+        Power(_, u, v) => match value(v)? {
+            n if n.fract() == 0.0 && n.abs() <= i32::MAX as f64 => value(u)?.powi(n as i32),
+            n => value(u)?.powf(n),
+        },
+        Call(_, f, u) => match &**f {
+            Variable(_, name) if FUNCTIONS.contains(&name.as_str()) => {
+                let u = value(u)?;
+                match name.as_str() {
+                    "sqrt" => u.sqrt(),
+                    "exp" => u.exp(),
+                    "ln" => u.ln(),
+                    "log" => u.log10(),
+                    "sin" => u.sin(),
+                    "cos" => u.cos(),
+                    "tan" => u.tan(),
+                    "atan" => u.atan(),
+                    _ => u.abs(),
+                }
+            },
+            Variable(..) if matches!(**u, Tuple(..)) => return None,
+            // Anything else is implicit multiplication
+            f => value(f)? * value(u)?,
+        },
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Evaluates an expression like `evaluate` does, but in complex floating point,
+/// with the variable `x` set to `z`.
+pub fn evaluate_complex(expr: &Expression, x: &str, z: Complex) -> Option<Complex> {
+    evaluate_at(expr, &[(x, z)])
+}
+
+/// Evaluates an expression in complex floating point with each of the given
+/// variables set to its value.
+pub fn evaluate_at(expr: &Expression, values: &[(&str, Complex)]) -> Option<Complex> {
+    let value = |u: &Expression| evaluate_at(u, values);
+    let result = match expr {
+        Number(_, a, b) => Complex::real(*a as f64 / *b as f64),
+        Constant(_, name) => Complex::real(constant(name)?),
+        ImaginaryConstant(_) => Complex::new(0.0, 1.0),
+        Variable(_, name) => values.iter().find(|(x, _)| *x == name.as_str())?.1,
+        Negate(_, u) => -value(u)?,
+        Percent(_, u) => value(u)? / Complex::real(100.0),
+        Add(_, u, v) => value(u)? + value(v)?,
+        Subtract(_, u, v) => value(u)? - value(v)?,
+        Multiply(_, u, v) => value(u)? * value(v)?,
+        Divide(_, u, v) => value(u)? / value(v)?,
+        Power(_, u, v) => match value(v)? {
+            n if n.im == 0.0 && n.re.fract() == 0.0 && n.re.abs() <= 64.0 => value(u)?.powi(n.re as i32),
+            n => value(u)?.pow(n),
+        },
+        Call(_, f, u) => match &**f {
+            Variable(_, name) if FUNCTIONS.contains(&name.as_str()) => {
+                let u = value(u)?;
+                match name.as_str() {
+                    "sqrt" => u.sqrt(),
+                    "exp" => u.exp(),
+                    "ln" => u.ln(),
+                    "log" => u.ln() / Complex::real(std::f64::consts::LN_10),
+                    "sin" => u.sin(),
+                    "cos" => u.cos(),
+                    "tan" => u.sin() / u.cos(),
+                    "atan" => u.atan(),
+                    _ => Complex::real(u.abs()),
+                }
+            },
+            Variable(..) if matches!(**u, Tuple(..)) => return None,
+            // Anything else is implicit multiplication
+            f => value(f)? * value(u)?,
+        },
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// The nodes of the 15-point Kronrod rule on `[-1, 1]` from the middle out,
+/// where every other one, starting from the middle, is a node of the 7-point
+/// Gauss rule.
+const KRONROD_NODES: [f64; 8] = [
+    0.0,
+    0.20778495500789848,
+    0.4058451513773972,
+    0.5860872354676911,
+    0.7415311855993945,
+    0.8648644233597691,
+    0.9491079123427585,
+    0.9914553711208126,
+];
+
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.20948214108472782,
+    0.20443294007529889,
+    0.19035057806478542,
+    0.1690047266392679,
+    0.14065325971552592,
+    0.10479001032225019,
+    0.06309209262997856,
+    0.022935322010529224,
+];
+
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.4179591836734694,
+    0.3818300505051189,
+    0.27970539148927664,
+    0.1294849661688697,
+];
+
+/// The most pieces quadrature splits an interval into before giving up.
+const MAX_INTERVALS: usize = 500;
+
+/// A piece of the interval of integration with its estimated integral and error.
+struct Piece {
+    a: f64,
+    b: f64,
+    integral: f64,
+    error: f64,
+}
+
+/// Integrates over `[a, b]` with the 7-point Gauss and 15-point Kronrod rules,
+/// taking the difference between them as the error.
+fn gauss_kronrod(f: &mut dyn FnMut(f64) -> Result<f64, Error>, a: f64, b: f64) -> Result<Piece, Error> {
+    let (middle, half) = ((a + b) / 2.0, (b - a) / 2.0);
+    let centre = f(middle)?;
+    let (mut kronrod, mut gauss) = (centre * KRONROD_WEIGHTS[0], centre * GAUSS_WEIGHTS[0]);
+    for k in 1..8 {
+        let offset = half * KRONROD_NODES[k];
+        let pair = f(middle - offset)? + f(middle + offset)?;
+        kronrod += pair * KRONROD_WEIGHTS[k];
+        if k % 2 == 0 {
+            gauss += pair * GAUSS_WEIGHTS[k / 2];
+        }
+    }
+    let (integral, error) = (kronrod * half, ((kronrod - gauss) * half).abs());
+    if !integral.is_finite() {
+        return Err(Error::OutOfDomain);
+    }
+    Ok(Piece { a, b, integral, error })
+}
+
+/// Integrates `f` over `[a, b]` by adaptive Gauss–Kronrod quadrature, splitting
+/// the piece with the largest error in half until the total error is small
+/// enough. Gives the integral and its estimated error, or `NoConvergence` if
+/// the error is still too large after `MAX_INTERVALS` pieces.
+pub fn integrate(f: &mut dyn FnMut(f64) -> Result<f64, Error>, a: f64, b: f64) -> Result<(f64, f64), Error> {
+    let mut pieces = vec![gauss_kronrod(f, a, b)?];
+    loop {
+        let integral: f64 = pieces.iter().map(|p| p.integral).sum();
+        let error: f64 = pieces.iter().map(|p| p.error).sum();
+        if error <= 1e-12_f64.max(1e-10 * integral.abs()) {
+            return Ok((integral, error));
+        }
+        if pieces.len() >= MAX_INTERVALS {
+            return Err(Error::NoConvergence);
+        }
+        let worst = (0..pieces.len()).max_by(|&i, &j| pieces[i].error.total_cmp(&pieces[j].error)).unwrap_or(0);
+        let piece = pieces.swap_remove(worst);
+        let middle = (piece.a + piece.b) / 2.0;
+        pieces.push(gauss_kronrod(f, piece.a, middle)?);
+        pieces.push(gauss_kronrod(f, middle, piece.b)?);
+    }
+}
+
+/// A complex number in floating point, for the formulas and iterations that
+/// find the roots of polynomials numerically.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    fn real(re: f64) -> Complex {
+        Complex::new(re, 0.0)
+    }
+
+    pub fn abs(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    /// Raises to the power `1 / n` on the principal branch.
+    fn root(self, n: f64) -> Complex {
+        let (r, theta) = (self.abs().powf(1.0 / n), self.im.atan2(self.re) / n);
+        Complex::new(r * theta.cos(), r * theta.sin())
+    }
+
+    fn sqrt(self) -> Complex {
+        self.root(2.0)
+    }
+
+    fn cbrt(self) -> Complex {
+        self.root(3.0)
+    }
+
+    fn exp(self) -> Complex {
+        let modulus = self.re.exp();
+        Complex::new(modulus * self.im.cos(), modulus * self.im.sin())
+    }
+
+    /// The principal natural logarithm.
+    fn ln(self) -> Complex {
+        Complex::new(self.abs().ln(), self.im.atan2(self.re))
+    }
+
+    fn sin(self) -> Complex {
+        Complex::new(self.re.sin() * self.im.cosh(), self.re.cos() * self.im.sinh())
+    }
+
+    fn cos(self) -> Complex {
+        Complex::new(self.re.cos() * self.im.cosh(), -self.re.sin() * self.im.sinh())
+    }
+
+    /// The principal arctangent, `i/2 ln((i + z) / (i - z))`.
+    fn atan(self) -> Complex {
+        let i = Complex::new(0.0, 1.0);
+        Complex::new(0.0, 0.5) * ((i + self) / (i - self)).ln()
+    }
+
+    fn powi(self, n: i32) -> Complex {
+        let mut result = Complex::real(1.0);
+        for _ in 0..n.unsigned_abs() {
+            result = result * self;
+        }
+        match n < 0 {
+            true => Complex::real(1.0) / result,
+            false => result,
+        }
+    }
+
+    /// Raises to a complex power on the principal branch.
+    fn pow(self, w: Complex) -> Complex {
+        match self.abs() == 0.0 {
+            true => Complex::real(0.0),
+            false => (w * self.ln()).exp(),
+        }
+    }
+}
+
+impl std::ops::Add for Complex {
+    type Output = Complex;
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl std::ops::Sub for Complex {
+    type Output = Complex;
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl std::ops::Mul for Complex {
+    type Output = Complex;
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(self.re * other.re - self.im * other.im, self.re * other.im + self.im * other.re)
+    }
+}
+
+impl std::ops::Div for Complex {
+    type Output = Complex;
+    fn div(self, other: Complex) -> Complex {
+        let modulus = other.re * other.re + other.im * other.im;
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / modulus,
+            (self.im * other.re - self.re * other.im) / modulus,
+        )
+    }
+}
+
+impl std::ops::Neg for Complex {
+    type Output = Complex;
+    fn neg(self) -> Complex {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+/// Evaluates a polynomial, given by its coefficients from the constant up, and
+/// its derivative at `z` by Horner's rule.
+fn horner(coefficients: &[Complex], z: Complex) -> (Complex, Complex) {
+    let (mut p, mut dp) = (Complex::real(0.0), Complex::real(0.0));
+    for &c in coefficients.iter().rev() {
+        dp = dp * z + p;
+        p = p * z + c;
+    }
+    (p, dp)
+}
+
+/// Solves `z^3 + p z + q = 0` by Cardano's formula.
+fn depressed_cubic(p: Complex, q: Complex) -> [Complex; 3] {
+    let half = Complex::real(0.5);
+    let discriminant = (q * q * Complex::real(0.25) + p * p * p / Complex::real(27.0)).sqrt();
+    let mut c = (-q * half + discriminant).cbrt();
+    if c.abs() < 1e-300 {
+        c = (-q * half - discriminant).cbrt();
+    }
+    if c.abs() < 1e-300 {
+        return [Complex::real(0.0); 3];
+    }
+    // The three cube roots differ by the cube roots of unity
+    let omega = Complex::new(-0.5, 3f64.sqrt() / 2.0);
+    [c, c * omega, c * omega * omega].map(|u| u - p / (Complex::real(3.0) * u))
+}
+
+/// Solves `z^4 + p z^2 + q z + r = 0` by Ferrari's method, which splits it into
+/// two quadratics with the help of a root `m` of the resolvent cubic.
+fn depressed_quartic(p: Complex, q: Complex, r: Complex) -> [Complex; 4] {
+    if q.abs() < 1e-300 {
+        // A quadratic in z^2
+        let d = (p * p - Complex::real(4.0) * r).sqrt();
+        let (u, v) = (((-p + d) * Complex::real(0.5)).sqrt(), ((-p - d) * Complex::real(0.5)).sqrt());
+        return [u, -u, v, -v];
+    }
+    // 8m^3 + 8p m^2 + (2p^2 - 8r) m - q^2 = 0, made monic and depressed
+    let (b, c, d) = (p, (p * p * Complex::real(2.0) - r * Complex::real(8.0)) / Complex::real(8.0), -(q * q) / Complex::real(8.0));
+    let shift = b / Complex::real(3.0);
+    let depressed_p = c - b * b / Complex::real(3.0);
+    let depressed_q = Complex::real(2.0) * b * b * b / Complex::real(27.0) - b * c / Complex::real(3.0) + d;
+    let m = depressed_cubic(depressed_p, depressed_q).into_iter()
+        .map(|t| t - shift)
+        .max_by(|x, y| x.abs().total_cmp(&y.abs()))
+        .unwrap_or(Complex::real(0.0));
+    let s = (Complex::real(2.0) * m).sqrt();
+    let mut roots = [Complex::real(0.0); 4];
+    for (k, sign) in [1.0, -1.0].into_iter().enumerate() {
+        let sign = Complex::real(sign);
+        let rest = (-(Complex::real(2.0) * p + Complex::real(2.0) * m + sign * Complex::real(2.0) * q / s)).sqrt();
+        roots[2 * k] = (sign * s + rest) * Complex::real(0.5);
+        roots[2 * k + 1] = (sign * s - rest) * Complex::real(0.5);
+    }
+    roots
+}
+
+/// Finds all the roots of a polynomial at once by the Durand–Kerner iteration.
+fn durand_kerner(coefficients: &[Complex]) -> Vec<Complex> {
+    let n = coefficients.len() - 1;
+    let lead = coefficients[n];
+    // The usual starting points are the powers of 0.4 + 0.9i
+    let mut roots = vec![Complex::real(1.0); n];
+    for k in 1..n {
+        roots[k] = roots[k - 1] * Complex::new(0.4, 0.9);
+    }
+    for _ in 0..500 {
+        let mut change: f64 = 0.0;
+        for i in 0..n {
+            let mut divisor = lead;
+            for j in (0..n).filter(|&j| j != i) {
+                divisor = divisor * (roots[i] - roots[j]);
+            }
+            let step = horner(coefficients, roots[i]).0 / divisor;
+            roots[i] = roots[i] - step;
+            change = change.max(step.abs());
+        }
+        if change < 1e-15 {
+            break;
+        }
+    }
+    roots
+}
+
+/// Finds all the complex roots of a polynomial, given by its coefficients from
+/// the constant up, with the cubic and quartic formulas or numerically for
+/// higher degrees. Each root is polished with a few steps of Newton's method.
+pub fn polynomial_roots(coefficients: &[Complex]) -> Vec<Complex> {
+    let n = coefficients.len() - 1;
+    let monic: Vec<Complex> = coefficients.iter().map(|&c| c / coefficients[n]).collect();
+    let roots = match n {
+        0 => vec![],
+        1 => vec![-monic[0]],
+        2 => {
+            let d = (monic[1] * monic[1] - Complex::real(4.0) * monic[0]).sqrt();
+            vec![(-monic[1] + d) * Complex::real(0.5), (-monic[1] - d) * Complex::real(0.5)]
+        },
+        3 => {
+            // z = t - a / 3 removes the square term
+            let (a, b, c) = (monic[2], monic[1], monic[0]);
+            let shift = a / Complex::real(3.0);
+            let p = b - a * a / Complex::real(3.0);
+            let q = Complex::real(2.0) * a * a * a / Complex::real(27.0) - a * b / Complex::real(3.0) + c;
+            depressed_cubic(p, q).into_iter().map(|t| t - shift).collect()
+        },
+        4 => {
+            // z = t - a / 4 removes the cube term
+            let (a, b, c, d) = (monic[3], monic[2], monic[1], monic[0]);
+            let shift = a / Complex::real(4.0);
+            let p = b - Complex::real(3.0) * a * a / Complex::real(8.0);
+            let q = a * a * a / Complex::real(8.0) - a * b / Complex::real(2.0) + c;
+            let r = d - Complex::real(3.0) * a * a * a * a / Complex::real(256.0) + a * a * b / Complex::real(16.0) - a * c / Complex::real(4.0);
+            depressed_quartic(p, q, r).into_iter().map(|t| t - shift).collect()
+        },
+        _ => durand_kerner(&monic),
+    };
+    roots.into_iter().map(|mut z| {
+        for _ in 0..3 {
+            let (p, dp) = horner(&monic, z);
+            if dp.abs() == 0.0 || p.abs() == 0.0 {
+                break;
+            }
+            z = z - p / dp;
+        }
+        z
+    }).collect()
+}
+
+/// The most steps the iterative methods take before giving up.
+const MAX_ITERATIONS: usize = 200;
+
+/// Finds a root of `f` between `a` and `b`, where `f` changes sign, by Brent's
+/// method, which takes secant and inverse quadratic interpolation steps where
+/// they help and bisects where they do not. A sign change where `f` ends up
+/// large next to its values at `a` and `b` is a pole, not a root, and gives
+/// `NoConvergence`.
+pub fn brent(f: &mut dyn FnMut(f64) -> Result<f64, Error>, a: f64, b: f64) -> Result<f64, Error> {
+    let (mut a, mut b) = (a, b);
+    let (mut fa, mut fb) = (f(a)?, f(b)?);
+    if fa == 0.0 {
+        return Ok(a);
+    }
+    if fa.signum() == fb.signum() && fb != 0.0 {
+        return Err(Error::NotBracketed);
+    }
+    let scale = fa.abs().max(fb.abs());
+    let root = |x: f64, fx: f64| match fx.abs() <= 1e-3 * scale {
+        true => Ok(x),
+        false => Err(Error::NoConvergence),
+    };
+    // The root stays between b, the best estimate, and c
+    let (mut c, mut fc) = (b, fb);
+    let (mut d, mut e) = (b - a, b - a);
+    for _ in 0..MAX_ITERATIONS {
+        if fb.signum() == fc.signum() {
+            (c, fc) = (a, fa);
+            (d, e) = (b - a, b - a);
+        }
+        if fc.abs() < fb.abs() {
+            (a, b, c) = (b, c, b);
+            (fa, fb, fc) = (fb, fc, fb);
+        }
+        let tolerance = 2.0 * f64::EPSILON * b.abs() + 0.5e-15;
+        let middle = (c - b) / 2.0;
+        if middle.abs() <= tolerance || fb == 0.0 {
+            return root(b, fb);
+        }
+        if e.abs() >= tolerance && fa.abs() > fb.abs() {
+            let s = fb / fa;
+            let (mut p, mut q) = match a == c {
+                // A secant step
+                true => (2.0 * middle * s, 1.0 - s),
+                // An inverse quadratic interpolation step
+                false => {
+                    let (q, r) = (fa / fc, fb / fc);
+                    (s * (2.0 * middle * q * (q - r) - (b - a) * (r - 1.0)), (q - 1.0) * (r - 1.0) * (s - 1.0))
+                },
+            };
+            if p > 0.0 {
+                q = -q;
+            }
+            p = p.abs();
+            if 2.0 * p < (3.0 * middle * q - (tolerance * q).abs()).min((e * q).abs()) {
+                (e, d) = (d, p / q);
+            } else {
+                (d, e) = (middle, middle);
+            }
+        } else {
+            (d, e) = (middle, middle);
+        }
+        (a, fa) = (b, fb);
+        b += if d.abs() > tolerance { d } else { tolerance.copysign(middle) };
+        fb = f(b)?;
+    }
+    Err(Error::NoConvergence)
+}
+
+/// Finds a root of `f` near `z` by Newton's method, where `f` gives its value
+/// and derivative at a point. Complex starting points find complex roots.
+pub fn newton(f: &mut dyn FnMut(Complex) -> Result<(Complex, Complex), Error>, z: Complex) -> Result<Complex, Error> {
+    let mut z = z;
+    for _ in 0..MAX_ITERATIONS {
+        let (y, dy) = f(z)?;
+        if y.abs() == 0.0 {
+            return Ok(z);
+        }
+        let step = y / dy;
+        if !step.re.is_finite() || !step.im.is_finite() {
+            return Err(Error::NoConvergence);
+        }
+        z = z - step;
+        if step.abs() <= 1e-14 * z.abs().max(1.0) {
+            return Ok(z);
+        }
+    }
+    Err(Error::NoConvergence)
+}
+
+/// Solves the linear system `a z = b` by Gaussian elimination with partial
+/// pivoting, failing if `a` is singular.
+fn linear_solve(mut a: Vec<Vec<Complex>>, mut b: Vec<Complex>) -> Option<Vec<Complex>> {
+    let n = b.len();
+    for j in 0..n {
+        let pivot = (j..n).max_by(|&r, &s| a[r][j].abs().total_cmp(&a[s][j].abs()))?;
+        if a[pivot][j].abs() == 0.0 {
+            return None;
+        }
+        a.swap(j, pivot);
+        b.swap(j, pivot);
+        let (top, bottom) = a.split_at_mut(j + 1);
+        for (r, row) in bottom.iter_mut().enumerate() {
+            let factor = row[j] / top[j][j];
+            for (x, y) in row.iter_mut().zip(&top[j]).skip(j) {
+                *x = *x - factor * *y;
+            }
+            b[j + 1 + r] = b[j + 1 + r] - factor * b[j];
+        }
+    }
+    let mut z = vec![Complex::real(0.0); n];
+    for j in (0..n).rev() {
+        let sum = (j + 1..n).fold(b[j], |sum, k| sum - a[j][k] * z[k]);
+        z[j] = sum / a[j][j];
+    }
+    Some(z)
+}
+
+/// The condition number of a square matrix in the maximum row-sum norm, which
+/// is infinite for a singular matrix.
+pub fn condition(a: &[Vec<Complex>]) -> f64 {
+    let n = a.len();
+    let norm = |rows: &[Vec<Complex>]| rows.iter().map(|row| row.iter().map(|z| z.abs()).sum::<f64>()).fold(0.0, f64::max);
+    let mut inverse = vec![vec![Complex::real(0.0); n]; n];
+    for j in 0..n {
+        let mut unit = vec![Complex::real(0.0); n];
+        unit[j] = Complex::real(1.0);
+        let Some(column) = linear_solve(a.to_vec(), unit) else {
+            return f64::INFINITY;
+        };
+        for (row, z) in inverse.iter_mut().zip(column) {
+            row[j] = z;
+        }
+    }
+    norm(a) * norm(&inverse)
+}
+
+/// The values of a system of equations at a point and its Jacobian matrix
+/// there, with one row of derivatives per equation.
+pub type Linearization = (Vec<Complex>, Vec<Vec<Complex>>);
+
+/// Finds a root of a system of `n` equations in `n` unknowns near `z` by
+/// Newton's method, where `f` gives the system's linearization at a point.
+pub fn newton_system(f: &mut dyn FnMut(&[Complex]) -> Result<Linearization, Error>, z: Vec<Complex>) -> Result<Vec<Complex>, Error> {
+    let norm = |z: &[Complex]| z.iter().map(|z| z.abs()).fold(0.0, f64::max);
+    let mut z = z;
+    for _ in 0..MAX_ITERATIONS {
+        let (y, jacobian) = f(&z)?;
+        if norm(&y) == 0.0 {
+            return Ok(z);
+        }
+        let step = linear_solve(jacobian, y).ok_or(Error::NoConvergence)?;
+        if step.iter().any(|s| !s.re.is_finite() || !s.im.is_finite()) {
+            return Err(Error::NoConvergence);
+        }
+        for (z, s) in z.iter_mut().zip(&step) {
+            *z = *z - *s;
+        }
+        if norm(&step) <= 1e-14 * norm(&z).max(1.0) {
+            return Ok(z);
+        }
+    }
+    Err(Error::NoConvergence)
+}
+
+/// Finds a minimum of `f` in `[a, b]` by Brent's method, which combines golden
+/// section search with parabolic interpolation. Gives where the minimum is and
+/// the value there.
+fn brent_minimum(f: &mut dyn FnMut(f64) -> Result<f64, Error>, a: f64, b: f64) -> Result<(f64, f64), Error> {
+    const GOLDEN: f64 = 0.381_966_011_250_105_1;
+    let (mut a, mut b) = (a, b);
+    let mut x = a + GOLDEN * (b - a);
+    let (mut w, mut v) = (x, x);
+    let mut fx = f(x)?;
+    let (mut fw, mut fv) = (fx, fx);
+    let (mut d, mut e): (f64, f64) = (0.0, 0.0);
+    for _ in 0..MAX_ITERATIONS {
+        let middle = (a + b) / 2.0;
+        let tolerance = f64::EPSILON.sqrt() * x.abs() + 1e-12;
+        if (x - middle).abs() <= 2.0 * tolerance - (b - a) / 2.0 {
+            return Ok((x, fx));
+        }
+        let mut golden = true;
+        if e.abs() > tolerance {
+            // Fit a parabola through x, w and v
+            let r = (x - w) * (fx - fv);
+            let mut q = (x - v) * (fx - fw);
+            let mut p = (x - v) * q - (x - w) * r;
+            q = 2.0 * (q - r);
+            if q > 0.0 {
+                p = -p;
+            }
+            q = q.abs();
+            if p.abs() < (0.5 * q * e).abs() && p > q * (a - x) && p < q * (b - x) {
+                e = d;
+                d = p / q;
+                let u = x + d;
+                if u - a < 2.0 * tolerance || b - u < 2.0 * tolerance {
+                    d = tolerance.copysign(middle - x);
+                }
+                golden = false;
+            }
+        }
+        if golden {
+            e = if x >= middle { a - x } else { b - x };
+            d = GOLDEN * e;
+        }
+        let u = if d.abs() >= tolerance { x + d } else { x + tolerance.copysign(d) };
+        let fu = f(u)?;
+        if fu <= fx {
+            if u >= x { a = x } else { b = x }
+            (v, w, x) = (w, x, u);
+            (fv, fw, fx) = (fw, fx, fu);
+        } else {
+            if u < x { a = u } else { b = u }
+            if fu <= fw || w == x {
+                (v, w) = (w, u);
+                (fv, fw) = (fw, fu);
+            } else if fu <= fv || v == x || v == w {
+                (v, fv) = (u, fu);
+            }
+        }
+    }
+    Err(Error::NoConvergence)
+}
+
+/// Finds the smallest value of `f` on `[a, b]`. The interval is sampled first,
+/// so that Brent's method starts next to the lowest sample rather than in
+/// whichever local minimum it happens upon, and the ends are checked too.
+pub fn minimize(f: &mut dyn FnMut(f64) -> Result<f64, Error>, a: f64, b: f64) -> Result<(f64, f64), Error> {
+    const SAMPLES: usize = 100;
+    let width = (b - a) / SAMPLES as f64;
+    let mut lowest = (a, f(a)?);
+    for k in 1..=SAMPLES {
+        let t = if k == SAMPLES { b } else { a + width * k as f64 };
+        let y = f(t)?;
+        if y < lowest.1 {
+            lowest = (t, y);
+        }
+    }
+    let (lower, upper) = ((lowest.0 - width).max(a), (lowest.0 + width).min(b));
+    let (x, y) = brent_minimum(f, lower, upper)?;
+    match y <= lowest.1 {
+        true => Ok((x, y)),
+        false => Ok(lowest),
+    }
+}
+
+/// Finds the real roots of `f` in `[a, b]` by looking for changes of sign
+/// between `steps` equally spaced points and narrowing each down with Brent's
+/// method. A change of sign across a pole is not a root, so roots must make
+/// `f` small.
+pub fn real_roots(f: &mut dyn FnMut(f64) -> Option<f64>, a: f64, b: f64, steps: usize) -> Vec<f64> {
+    let mut roots = Vec::new();
+    let width = (b - a) / steps as f64;
+    let mut previous = f(a);
+    for k in 1..=steps {
+        let (lower, upper) = (a + width * (k - 1) as f64, a + width * k as f64);
+        let next = f(upper);
+        match (previous, next) {
+            (Some(0.0), _) => roots.push(lower),
+            (Some(y1), Some(y2)) if y1.is_finite() && y2.is_finite() && y1.signum() != y2.signum() && y2 != 0.0 => {
+                let root = brent(&mut |t| f(t).ok_or(Error::OutOfDomain), lower, upper);
+                if let Ok(root) = root {
+                    if f(root).is_some_and(|y| y.abs() < 1e-6 * (1.0 + y1.abs().min(y2.abs()))) {
+                        roots.push(root);
+                    }
+                }
+            },
+            _ => {},
+        }
+        previous = next;
+    }
+    if previous == Some(0.0) {
+        roots.push(b);
+    }
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brent_finds_a_bracketed_root() {
+        let root = brent(&mut |x| Ok(x * x - 2.0), 0.0, 2.0).unwrap();
+        assert!((root - 2f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn brent_needs_a_sign_change() {
+        assert!(matches!(brent(&mut |x| Ok(x * x + 1.0), -1.0, 1.0), Err(Error::NotBracketed)));
+    }
+
+    #[test]
+    fn brent_does_not_report_a_pole() {
+        assert!(matches!(brent(&mut |x| Ok(1.0 / x), -1.0, 1.0), Err(Error::NoConvergence)));
+        assert!(matches!(brent(&mut |x| Ok(x.tan()), 1.0, 2.0), Err(Error::NoConvergence)));
+    }
+
+    #[test]
+    fn newton_finds_real_and_complex_roots() {
+        let root = newton(&mut |z| Ok((z * z - Complex::real(2.0), z + z)), Complex::real(1.0)).unwrap();
+        assert!((root - Complex::real(2f64.sqrt())).abs() < 1e-12);
+        let root = newton(&mut |z| Ok((z * z + Complex::real(1.0), z + z)), Complex::new(0.5, 0.5)).unwrap();
+        assert!((root - Complex::new(0.0, 1.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn newton_gives_up_at_a_flat_point() {
+        let result = newton(&mut |z| Ok((z * z + Complex::real(1.0), z + z)), Complex::real(0.0));
+        assert!(matches!(result, Err(Error::NoConvergence)));
+    }
+
+    #[test]
+    fn integrate_converges_with_a_small_error() {
+        let (integral, error) = integrate(&mut |x| Ok(x.sin()), 0.0, std::f64::consts::PI).unwrap();
+        assert!((integral - 2.0).abs() < 1e-12);
+        assert!(error < 1e-10);
+        let (integral, _) = integrate(&mut |x| Ok(1.0 / x.sqrt()), 0.0, 1.0).unwrap();
+        assert!((integral - 2.0).abs() < 1e-8);
+    }
+
+    #[test]
+    fn integrate_fails_across_a_pole() {
+        assert!(matches!(integrate(&mut |x| Ok(x.tan()), 0.0, 2.0), Err(Error::NoConvergence)));
+    }
+}
@@ -1,11 +1,15 @@
 use crate::tokens::{LexError, Token};
 use crate::nodes::{ParseError, Expression};
+use crate::values::narrow;
 
 #[derive(Clone)]
 pub struct Parser {
     pub chars: Vec<char>,
     pub index: usize,
     pub token: Result<Token, LexError>,
+    /// How many parentheses deep the parser is. Inside them, the elements of a
+    /// tuple can be equations, as in `(x + y = 3, x - y = 1)`.
+    depth: usize,
 }
 
 impl Parser {
@@ -13,14 +17,31 @@ impl Parser {
         let mut parser = Parser {
             chars: code.chars().collect(),
             index: 0,
-            token: Ok(Token::EOF),
+            token: Ok(Token::Eof),
+            depth: 0,
         };
         parser.iter_token();
         parser
     }
 
     pub fn parse(&mut self) -> Result<Expression, ParseError> {
-        self.parse_define()
+        self.parse_conversion()
+    }
+
+    fn parse_conversion(&mut self) -> Result<Expression, ParseError> {
+        let mut expr = self.parse_define()?;
+        loop {
+            match self.token() {
+                Ok(Token::In | Token::To) => {
+                    self.iter_token();
+                    let target = self.parse_addition()?;
+                    expr = Expression::Convert(self.index, Box::new(expr), Box::new(target));
+                },
+                Ok(_) => break,
+                Err(e) => return Err(ParseError::LexError(e)),
+            }
+        }
+        Ok(expr)
     }
 
     fn parse_define(&mut self) -> Result<Expression, ParseError> {
@@ -49,8 +70,22 @@ impl Parser {
         }
     }
 
-    fn parse_tuple(&mut self) -> Result<Expression, ParseError> {
+    /// Parses a single closure, whose body ends at the next comma so that closures
+    /// can be passed in tuples and lists.
+    fn parse_lambda(&mut self) -> Result<Expression, ParseError> {
         let expr = self.parse_or()?;
+        match self.token() {
+            Ok(Token::BigArrow) => {
+                self.iter_token();
+                Ok(Expression::Function(self.index, Box::new(expr), Box::new(self.parse_lambda()?)))
+            },
+            Ok(_) => Ok(expr),
+            Err(e) => Err(ParseError::LexError(e)),
+        }
+    }
+
+    fn parse_tuple(&mut self) -> Result<Expression, ParseError> {
+        let expr = self.parse_element()?;
         match self.token() {
             Ok(Token::Comma) => {},
             _ => return Ok(expr)
@@ -64,13 +99,13 @@ impl Parser {
                     self.iter_token();
                     
                     match self.token() {
-                        Ok(Token::RightParen | Token::RightBrace | Token::RightBracket | Token::EOF) => break,
+                        Ok(Token::RightParen | Token::RightBrace | Token::RightBracket | Token::Eof) => break,
                         Ok(_) => {},
                         Err(e) => return Err(ParseError::LexError(e)),
                     }
 
-                    let or = self.parse_or()?;
-                    elements.push(or);
+                    let element = self.parse_element()?;
+                    elements.push(element);
                 },
                 Ok(_) => break,
                 Err(e) => return Err(ParseError::LexError(e)),
@@ -79,6 +114,20 @@ impl Parser {
         Ok(Expression::Tuple(self.index, elements))
     }
 
+    /// Parses an element of a tuple, which inside parentheses can be an equation.
+    fn parse_element(&mut self) -> Result<Expression, ParseError> {
+        let expr = self.parse_lambda()?;
+        match self.token() {
+            Ok(Token::Equal) if self.depth > 0 => {
+                self.iter_token();
+                let value = self.parse_lambda()?;
+                Ok(Expression::Define(self.index, Box::new(expr), Box::new(value)))
+            },
+            Ok(_) => Ok(expr),
+            Err(e) => Err(ParseError::LexError(e)),
+        }
+    }
+
     fn parse_or(&mut self) -> Result<Expression, ParseError> {
         let mut expr = self.parse_and()?;
         loop {
@@ -112,38 +161,38 @@ impl Parser {
     }
 
     fn parse_comparison(&mut self) -> Result<Expression, ParseError> {
-        let mut expr = self.parse_addition()?;
+        let mut expr = self.parse_bitwise()?;
         let mut prev_rvalue: Expression;
 
         match self.token() {
             Ok(Token::DoubleEqual) => {
                 self.iter_token();
-                prev_rvalue = self.parse_addition()?;
+                prev_rvalue = self.parse_bitwise()?;
                 expr = Expression::Equal(self.index, Box::new(expr), Box::new(prev_rvalue.clone()));
             },
             Ok(Token::BangEqual) => {
                 self.iter_token();
-                prev_rvalue = self.parse_addition()?;
+                prev_rvalue = self.parse_bitwise()?;
                 expr = Expression::NotEqual(self.index, Box::new(expr), Box::new(prev_rvalue.clone()));
             },
             Ok(Token::LessThan) => {
                 self.iter_token();
-                prev_rvalue = self.parse_addition()?;
+                prev_rvalue = self.parse_bitwise()?;
                 expr = Expression::LessThan(self.index, Box::new(expr), Box::new(prev_rvalue.clone()));
             },
             Ok(Token::GreaterThan) => {
                 self.iter_token();
-                prev_rvalue = self.parse_addition()?;
+                prev_rvalue = self.parse_bitwise()?;
                 expr = Expression::GreaterThan(self.index, Box::new(expr), Box::new(prev_rvalue.clone()));
             },
             Ok(Token::LessThanEqual) => {
                 self.iter_token();
-                prev_rvalue = self.parse_addition()?;
+                prev_rvalue = self.parse_bitwise()?;
                 expr = Expression::LessThanEqual(self.index, Box::new(expr), Box::new(prev_rvalue.clone()));
             },
             Ok(Token::GreaterThanEqual) => {
                 self.iter_token();
-                prev_rvalue = self.parse_addition()?;
+                prev_rvalue = self.parse_bitwise()?;
                 expr = Expression::GreaterThanEqual(self.index, Box::new(expr), Box::new(prev_rvalue.clone()));
             },
             Ok(_) => return Ok(expr),
@@ -154,39 +203,39 @@ impl Parser {
             match self.token() {
                 Ok(Token::DoubleEqual) => {
                     self.iter_token();
-                    let addition = self.parse_addition()?;
-                    expr = Expression::And(self.index, Box::new(expr), Box::new(Expression::Equal(self.index, Box::new(prev_rvalue.clone()), Box::new(addition.clone()))));
-                    prev_rvalue = addition;
+                    let bitwise = self.parse_bitwise()?;
+                    expr = Expression::And(self.index, Box::new(expr), Box::new(Expression::Equal(self.index, Box::new(prev_rvalue.clone()), Box::new(bitwise.clone()))));
+                    prev_rvalue = bitwise;
                 },
                 Ok(Token::BangEqual) => {
                     self.iter_token();
-                    let addition = self.parse_addition()?;
-                    expr = Expression::And(self.index, Box::new(expr), Box::new(Expression::NotEqual(self.index, Box::new(prev_rvalue.clone()), Box::new(addition.clone()))));
-                    prev_rvalue = addition;
+                    let bitwise = self.parse_bitwise()?;
+                    expr = Expression::And(self.index, Box::new(expr), Box::new(Expression::NotEqual(self.index, Box::new(prev_rvalue.clone()), Box::new(bitwise.clone()))));
+                    prev_rvalue = bitwise;
                 },
                 Ok(Token::LessThan) => {
                     self.iter_token();
-                    let addition = self.parse_addition()?;
-                    expr = Expression::And(self.index, Box::new(expr), Box::new(Expression::LessThan(self.index, Box::new(prev_rvalue.clone()), Box::new(addition.clone()))));
-                    prev_rvalue = addition;
+                    let bitwise = self.parse_bitwise()?;
+                    expr = Expression::And(self.index, Box::new(expr), Box::new(Expression::LessThan(self.index, Box::new(prev_rvalue.clone()), Box::new(bitwise.clone()))));
+                    prev_rvalue = bitwise;
                 },
                 Ok(Token::GreaterThan) => {
                     self.iter_token();
-                    let addition = self.parse_addition()?;
-                    expr = Expression::And(self.index, Box::new(expr), Box::new(Expression::GreaterThan(self.index, Box::new(prev_rvalue.clone()), Box::new(addition.clone()))));
-                    prev_rvalue = addition;
+                    let bitwise = self.parse_bitwise()?;
+                    expr = Expression::And(self.index, Box::new(expr), Box::new(Expression::GreaterThan(self.index, Box::new(prev_rvalue.clone()), Box::new(bitwise.clone()))));
+                    prev_rvalue = bitwise;
                 },
                 Ok(Token::LessThanEqual) => {
                     self.iter_token();
-                    let addition = self.parse_addition()?;
-                    expr = Expression::And(self.index, Box::new(expr), Box::new(Expression::LessThanEqual(self.index, Box::new(prev_rvalue.clone()), Box::new(addition.clone()))));
-                    prev_rvalue = addition;
+                    let bitwise = self.parse_bitwise()?;
+                    expr = Expression::And(self.index, Box::new(expr), Box::new(Expression::LessThanEqual(self.index, Box::new(prev_rvalue.clone()), Box::new(bitwise.clone()))));
+                    prev_rvalue = bitwise;
                 },
                 Ok(Token::GreaterThanEqual) => {
                     self.iter_token();
-                    let addition = self.parse_addition()?;
-                    expr = Expression::And(self.index, Box::new(expr), Box::new(Expression::GreaterThanEqual(self.index, Box::new(prev_rvalue.clone()), Box::new(addition.clone()))));
-                    prev_rvalue = addition;
+                    let bitwise = self.parse_bitwise()?;
+                    expr = Expression::And(self.index, Box::new(expr), Box::new(Expression::GreaterThanEqual(self.index, Box::new(prev_rvalue.clone()), Box::new(bitwise.clone()))));
+                    prev_rvalue = bitwise;
                 },
                 Ok(_) => break,
                 Err(e) => return Err(ParseError::LexError(e)),
@@ -195,6 +244,53 @@ impl Parser {
         Ok(expr)
     }
 
+    fn parse_bitwise(&mut self) -> Result<Expression, ParseError> {
+        let mut expr = self.parse_shift()?;
+        loop {
+            match self.token() {
+                Ok(Token::BitAnd) => {
+                    self.iter_token();
+                    let shift = self.parse_shift()?;
+                    expr = Expression::BitAnd(self.index, Box::new(expr), Box::new(shift));
+                }
+                Ok(Token::BitOr) => {
+                    self.iter_token();
+                    let shift = self.parse_shift()?;
+                    expr = Expression::BitOr(self.index, Box::new(expr), Box::new(shift));
+                }
+                Ok(Token::BitXor) => {
+                    self.iter_token();
+                    let shift = self.parse_shift()?;
+                    expr = Expression::BitXor(self.index, Box::new(expr), Box::new(shift));
+                }
+                Ok(_) => break,
+                Err(e) => return Err(ParseError::LexError(e)),
+            }
+        }
+        Ok(expr)
+    }
+
+    fn parse_shift(&mut self) -> Result<Expression, ParseError> {
+        let mut expr = self.parse_addition()?;
+        loop {
+            match self.token() {
+                Ok(Token::ShiftLeft) => {
+                    self.iter_token();
+                    let addition = self.parse_addition()?;
+                    expr = Expression::ShiftLeft(self.index, Box::new(expr), Box::new(addition));
+                }
+                Ok(Token::ShiftRight) => {
+                    self.iter_token();
+                    let addition = self.parse_addition()?;
+                    expr = Expression::ShiftRight(self.index, Box::new(expr), Box::new(addition));
+                }
+                Ok(_) => break,
+                Err(e) => return Err(ParseError::LexError(e)),
+            }
+        }
+        Ok(expr)
+    }
+
     fn parse_addition(&mut self) -> Result<Expression, ParseError> {
         let mut expr = self.parse_composition()?;
         loop {
@@ -212,6 +308,12 @@ impl Parser {
                 Ok(Token::PlusOrMinus) => {
                     self.iter_token();
                     let composition = self.parse_composition()?;
+                    // `x ± e unc` is a measurement rather than the two values `x + e` and `x - e`
+                    if let Ok(Token::Unc) = self.token() {
+                        self.iter_token();
+                        expr = Expression::Uncertainty(self.index, Box::new(expr), Box::new(composition));
+                        continue;
+                    }
                     expr = Expression::Add(self.index, Box::new(expr), Box::new(Expression::PlusMinus(self.index, Box::new(composition))));
                 }
                 Ok(_) => break,
@@ -247,6 +349,16 @@ impl Parser {
                     let power = self.parse_power()?;
                     expr = Expression::Divide(self.index, Box::new(expr), Box::new(power));
                 }
+                Ok(Token::Div) => {
+                    self.iter_token();
+                    let power = self.parse_power()?;
+                    expr = Expression::IntegerDivide(self.index, Box::new(expr), Box::new(power));
+                }
+                Ok(Token::Mod) => {
+                    self.iter_token();
+                    let power = self.parse_power()?;
+                    expr = Expression::Modulo(self.index, Box::new(expr), Box::new(power));
+                }
                 Ok(_) => break,
                 Err(e) => return Err(ParseError::LexError(e)),
             }
@@ -291,8 +403,12 @@ impl Parser {
     /// Implicit multiplication is treated as a function, where if the "function" part
     /// turns out to be a function value, the function is executed, whereas if it is
     /// a non-function value, the "function" is multiplied by its input.
+    ///
+    /// Powers bind tighter than implicit multiplication, so `3x^2` is `3(x^2)`,
+    /// except that brackets straight after a name are a call, so `f(x)^2` is
+    /// `(f(x))^2` and `2sin(x)` is `2(sin(x))`.
     fn parse_implicit_multiplication(&mut self) -> Result<Expression, ParseError> {
-        let mut expr = self.parse_postfix()?;
+        let mut expr = self.parse_factor()?;
         loop {
             match self.token() {
                 Ok( Token::Number(_)
@@ -301,9 +417,10 @@ impl Parser {
                   | Token::PiConstant
                   | Token::EulerConstant
                   | Token::PhiConstant
-                  | Token::LeftParen) => {
-                    let postfix = self.parse_postfix()?;
-                    expr = Expression::Call(self.index, Box::new(expr), Box::new(postfix));
+                  | Token::LeftParen
+                  | Token::LeftBracket) => {
+                    let factor = self.parse_factor()?;
+                    expr = Expression::Call(self.index, Box::new(expr), Box::new(factor));
                 },
                 Ok(_) => break,
                 Err(e) => return Err(ParseError::LexError(e)),
@@ -312,12 +429,50 @@ impl Parser {
         Ok(expr)
     }
 
+    /// Parses one factor of an implicit multiplication, which is a name or its
+    /// derivative called with brackets or anything else, with its exponent.
+    fn parse_factor(&mut self) -> Result<Expression, ParseError> {
+        let mut expr = self.parse_postfix()?;
+        if let (Expression::Variable(..) | Expression::Derivative(..), Ok(Token::LeftParen | Token::LeftBracket)) = (&expr, self.token()) {
+            let argument = self.parse_postfix()?;
+            expr = Expression::Call(self.index, Box::new(expr), Box::new(argument));
+        }
+        self.parse_exponent_of(expr)
+    }
+
+    /// Parses `^` and an exponent after `expr`, if there is one. The exponent can
+    /// be negated, as in `2^-1`, and powers are right associative.
+    fn parse_exponent_of(&mut self, expr: Expression) -> Result<Expression, ParseError> {
+        match self.token() {
+            Ok(Token::Caret) => {
+                self.iter_token();
+                let exponent = self.parse_exponent()?;
+                Ok(Expression::Power(self.index, Box::new(expr), Box::new(exponent)))
+            },
+            Ok(_) => Ok(expr),
+            Err(e) => Err(ParseError::LexError(e)),
+        }
+    }
+
+    fn parse_exponent(&mut self) -> Result<Expression, ParseError> {
+        match self.token() {
+            Ok(Token::Minus) => {
+                self.iter_token();
+                let exponent = self.parse_exponent()?;
+                Ok(Expression::Negate(self.index, Box::new(exponent)))
+            },
+            Ok(_) => self.parse_factor(),
+            Err(e) => Err(ParseError::LexError(e)),
+        }
+    }
+
     fn parse_postfix(&mut self) -> Result<Expression, ParseError> {
         let mut expr = self.parse_atom()?;
         loop {
             match self.token() {
                 Ok(Token::Bang) => expr = Expression::Factorial(self.index, Box::new(expr)),
                 Ok(Token::Percent) => expr = Expression::Percent(self.index, Box::new(expr)),
+                Ok(Token::Apostrophe) => expr = Expression::Derivative(self.index, Box::new(expr)),
                 Ok(_) => break,
                 Err(e) => return Err(ParseError::LexError(e)),
             }
@@ -329,17 +484,18 @@ impl Parser {
     fn parse_atom(&mut self) -> Result<Expression, ParseError> {
         let expr: Expression = match self.token() {
             Ok(Token::Number(n)) => {
-                let (dividend, divisor) = to_quotient(n);
+                let (dividend, divisor) = to_quotient(n)?;
                 Ok(Expression::Number(self.index, dividend, divisor))
             },
             Ok(Token::Identifier(id)) => Ok(Expression::Variable(self.index, id)),
             Ok(Token::True) => Ok(Expression::Boolean(true)),
             Ok(Token::False) => Ok(Expression::Boolean(false)),
             Ok(Token::ImaginaryConstant) => Ok(Expression::ImaginaryConstant(self.index)),
-            Ok(Token::PiConstant) => Ok(Expression::Number(self.index, 3141592653589793, 100000000000000)),
-            Ok(Token::EulerConstant) => Ok(Expression::Number(self.index, 2718281828459045, 100000000000000)),
-            Ok(Token::PhiConstant) => Ok(Expression::Number(self.index, 1618033988749895, 100000000000000)),
+            Ok(Token::PiConstant) => Ok(Expression::constant(self.index, "pi")),
+            Ok(Token::EulerConstant) => Ok(Expression::constant(self.index, "e")),
+            Ok(Token::PhiConstant) => Ok(Expression::constant(self.index, "phi")),
             Ok(Token::LeftParen) => self.parse_parentheses(),
+            Ok(Token::LeftBracket) => self.parse_list(),
             Ok(_) => Err(ParseError::NumberExpected),
             Err(e) => Err(ParseError::LexError(e)),
         }?;
@@ -349,30 +505,103 @@ impl Parser {
 
     fn parse_parentheses(&mut self) -> Result<Expression, ParseError> {
         self.iter_token();
-        let expr = self.parse()?;
         if let Ok(Token::RightParen) = self.token() {
-            Ok(expr)
-        } else {
-            Err(ParseError::MissingClosingDelimiter)
+            return Ok(Expression::Tuple(self.index, Vec::new()));
+        }
+        self.depth += 1;
+        let expr = self.parse();
+        self.depth -= 1;
+        let expr = expr?;
+        match (self.token(), expr) {
+            (Ok(Token::RightParen), expr) => Ok(expr),
+            // A half-open interval like `(0, 1]`
+            (Ok(Token::RightBracket), Expression::Tuple(_, mut xs)) if xs.len() == 2 => {
+                let (upper, lower) = (xs.remove(1), xs.remove(0));
+                Ok(Expression::Interval(self.index, Box::new(lower), Box::new(upper), false, true))
+            },
+            _ => Err(ParseError::MissingClosingDelimiter),
         }
     }
 }
 
-fn to_quotient(number_string: String) -> (isize, isize) {
-    let mut dividend = String::new();
-    let mut divisor = 1;
-    let mut point_met = false;
-    for char in number_string.chars() {
-        match char {
-            n if n.is_numeric() => {
-                dividend.push(n);
-                if point_met {
-                    divisor *= 10;
-                }
-            },
-            '.' => point_met = true,
-            _ => {},
+impl Parser {
+    /// Parses a list literal like `[1, 2, 3]`, a range like `[1 .. 10]` or
+    /// `[0, 0.5 .. 3]` where the second element sets the step, or an interval
+    /// like `[0, 1)`.
+    fn parse_list(&mut self) -> Result<Expression, ParseError> {
+        self.iter_token();
+        let mut elements = Vec::<Expression>::new();
+        loop {
+            match self.token() {
+                Ok(Token::RightBracket) => return Ok(Expression::List(self.index, elements)),
+                Ok(Token::DotDot) if !elements.is_empty() && elements.len() <= 2 => {
+                    self.iter_token();
+                    let end = self.parse_or()?;
+                    let start = elements.remove(0);
+                    let next = elements.pop().map(Box::new);
+                    return match self.token() {
+                        Ok(Token::RightBracket) => Ok(Expression::Range(self.index, Box::new(start), next, Box::new(end))),
+                        _ => Err(ParseError::MissingClosingDelimiter),
+                    };
+                },
+                Ok(_) => elements.push(self.parse_lambda()?),
+                Err(e) => return Err(ParseError::LexError(e)),
+            }
+            match self.token() {
+                Ok(Token::Comma) => self.iter_token(),
+                Ok(Token::RightBracket | Token::DotDot) => {},
+                // A half-open interval like `[0, 1)`
+                Ok(Token::RightParen) if elements.len() == 2 => {
+                    let (upper, lower) = (elements.remove(1), elements.remove(0));
+                    return Ok(Expression::Interval(self.index, Box::new(lower), Box::new(upper), true, false));
+                },
+                Ok(_) => return Err(ParseError::MissingClosingDelimiter),
+                Err(e) => return Err(ParseError::LexError(e)),
+            }
         }
     }
-    (dividend.parse::<isize>().unwrap(), divisor)
+}
+
+/// Converts a number literal into a quotient, failing if either side of the
+/// quotient is too large to be stored.
+fn to_quotient(number_string: String) -> Result<(isize, isize), ParseError> {
+    let number_string = number_string.replace('_', "");
+    let radix = match number_string.get(..2) {
+        Some("0x") => 16,
+        Some("0o") => 8,
+        Some("0b") => 2,
+        _ => 10,
+    };
+    if radix != 10 {
+        let dividend = isize::from_str_radix(&number_string[2..], radix).map_err(|_| ParseError::NumberTooLarge)?;
+        return Ok((dividend, 1));
+    }
+
+    let (mantissa, exponent) = match number_string.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().map_err(|_| ParseError::NumberTooLarge)?),
+        None => (number_string.as_str(), 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let (fixed, repeating) = match fraction.split_once('(') {
+        Some((fixed, repeating)) => (fixed, repeating.trim_end_matches(')')),
+        None => (fraction, ""),
+    };
+
+    let digits = |digits: String| digits.parse::<i128>().map_err(|_| ParseError::NumberTooLarge);
+    let power = |n: u32| 10i128.checked_pow(n).ok_or(ParseError::NumberTooLarge);
+
+    let whole = digits(format!("{}{}", integer, fixed))?;
+    let mut dividend = whole;
+    let mut divisor = power(fixed.len() as u32)?;
+    if !repeating.is_empty() {
+        // x * 10^(m + r) - x * 10^m leaves only the digits before the repeating block
+        dividend = digits(format!("{}{}{}", integer, fixed, repeating))? - whole;
+        divisor = divisor.checked_mul(power(repeating.len() as u32)? - 1).ok_or(ParseError::NumberTooLarge)?;
+    }
+    if exponent >= 0 {
+        dividend = dividend.checked_mul(power(exponent as u32)?).ok_or(ParseError::NumberTooLarge)?;
+    } else {
+        divisor = divisor.checked_mul(power(exponent.unsigned_abs())?).ok_or(ParseError::NumberTooLarge)?;
+    }
+    narrow(dividend, divisor).ok_or(ParseError::NumberTooLarge)
 }
@@ -0,0 +1,917 @@
+use std::cmp::Ordering;
+use crate::builtins::{arguments, from_f64_pair};
+use crate::eval::{Error, add as add_values, subtract as subtract_values, multiply as multiply_values, divide as divide_values, power as power_values, negate as negate_values, to_real, real_order};
+use crate::format::{self, NumberForm};
+use crate::integers;
+use crate::numeric;
+use crate::values::{Value, reduce, rational_add, rational_multiply, to_f64, gcd as gcd_integers};
+use crate::nodes::Expression;
+use crate::symbolic::{self, to_expression};
+
+/// A product of variables raised to positive powers, sorted by name.
+pub type Monomial = Vec<(String, u32)>;
+
+/// A polynomial in any number of variables with complex rational coefficients,
+/// kept as its non-zero terms from the highest degree down.
+#[derive(Clone, PartialEq)]
+pub struct Polynomial {
+    terms: Vec<(Monomial, Value)>,
+}
+
+fn is_zero(x: &Value) -> bool {
+    matches!(x, Value::ComplexNumber(0, _, 0, _))
+}
+
+fn single(values: Result<Vec<Value>, Error>) -> Result<Value, Error> {
+    values?.into_iter().next().ok_or(Error::MismatchedType)
+}
+
+fn degree(monomial: &Monomial) -> u32 {
+    monomial.iter().map(|(_, k)| k).sum()
+}
+
+fn exponent(monomial: &Monomial, variable: &str) -> u32 {
+    monomial.iter().find(|(x, _)| x == variable).map_or(0, |(_, k)| *k)
+}
+
+/// Orders monomials by degree, then alphabetically by their variables, so that
+/// `x^2` comes before `x y`, which comes before `y^2`.
+fn monomial_order(a: &Monomial, b: &Monomial) -> Ordering {
+    degree(b).cmp(&degree(a)).then_with(|| {
+        let mut variables: Vec<&String> = a.iter().chain(b.iter()).map(|(x, _)| x).collect();
+        variables.sort();
+        variables.iter()
+            .map(|x| exponent(b, x).cmp(&exponent(a, x)))
+            .find(|o| *o != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    })
+}
+
+fn multiply_monomials(a: &Monomial, b: &Monomial) -> Monomial {
+    let mut product = a.clone();
+    for (x, k) in b {
+        match product.iter_mut().find(|(y, _)| y == x) {
+            Some((_, j)) => *j += k,
+            None => product.push((x.clone(), *k)),
+        }
+    }
+    product.sort();
+    product
+}
+
+impl Polynomial {
+    /// Collects like terms, leaving out the ones that cancel.
+    fn new(terms: Vec<(Monomial, Value)>) -> Result<Polynomial, Error> {
+        let mut collected: Vec<(Monomial, Value)> = Vec::new();
+        for (monomial, coefficient) in terms {
+            match collected.iter_mut().find(|(m, _)| *m == monomial) {
+                Some((_, c)) => *c = single(add_values(c.clone(), coefficient))?,
+                None => collected.push((monomial, coefficient)),
+            }
+        }
+        collected.retain(|(_, c)| !is_zero(c));
+        collected.sort_by(|(a, _), (b, _)| monomial_order(a, b));
+        Ok(Polynomial { terms: collected })
+    }
+
+    pub fn variable(name: &str) -> Polynomial {
+        Polynomial { terms: vec![(vec![(name.to_string(), 1)], Value::integer(1))] }
+    }
+
+    fn constant(x: Value) -> Result<Polynomial, Error> {
+        match x {
+            Value::ComplexNumber(..) => Polynomial::new(vec![(Vec::new(), x)]),
+            _ => Err(Error::MismatchedType),
+        }
+    }
+
+    /// The variables of the polynomial in alphabetical order.
+    pub fn variables(&self) -> Vec<String> {
+        let mut variables: Vec<String> = self.terms.iter().flat_map(|(m, _)| m.iter().map(|(x, _)| x.clone())).collect();
+        variables.sort();
+        variables.dedup();
+        variables
+    }
+
+    /// The terms of the polynomial, from the highest degree down.
+    pub fn terms(&self) -> &[(Monomial, Value)] {
+        &self.terms
+    }
+
+    pub fn degree(&self) -> u32 {
+        self.terms.first().map_or(0, |(m, _)| degree(m))
+    }
+
+    /// Lists the coefficients of the powers of `variable` from the constant up,
+    /// failing if any other variable appears.
+    fn dense(&self, variable: &str) -> Result<Vec<Value>, Error> {
+        if self.variables().iter().any(|x| x != variable) {
+            return Err(Error::MismatchedType);
+        }
+        let mut coefficients = vec![Value::integer(0); self.degree() as usize + 1];
+        for (monomial, coefficient) in &self.terms {
+            coefficients[exponent(monomial, variable) as usize] = coefficient.clone();
+        }
+        Ok(coefficients)
+    }
+
+    fn from_dense(coefficients: Vec<Value>, variable: &str) -> Result<Polynomial, Error> {
+        let terms = coefficients.into_iter().enumerate().map(|(k, c)| match k {
+            0 => (Vec::new(), c),
+            k => (vec![(variable.to_string(), k as u32)], c),
+        }).collect();
+        Polynomial::new(terms)
+    }
+
+    /// Turns the polynomial back into a number if it has no variables.
+    pub fn into_value(self) -> Value {
+        match &self.terms[..] {
+            [] => Value::integer(0),
+            [(monomial, c)] if monomial.is_empty() => c.clone(),
+            _ => Value::Polynomial(self),
+        }
+    }
+}
+
+/// Reads a value as a polynomial, where a number is a constant polynomial.
+fn polynomial(x: Value) -> Result<Polynomial, Error> {
+    match x {
+        Value::Polynomial(p) => Ok(p),
+        x => Polynomial::constant(x),
+    }
+}
+
+fn sum(p: &Polynomial, q: &Polynomial) -> Result<Polynomial, Error> {
+    Polynomial::new(p.terms.iter().chain(q.terms.iter()).cloned().collect())
+}
+
+fn product(p: &Polynomial, q: &Polynomial) -> Result<Polynomial, Error> {
+    let mut terms = Vec::new();
+    for (m1, c1) in &p.terms {
+        for (m2, c2) in &q.terms {
+            terms.push((multiply_monomials(m1, m2), single(multiply_values(c1.clone(), c2.clone()))?));
+        }
+    }
+    Polynomial::new(terms)
+}
+
+fn scale(p: &Polynomial, c: &Value) -> Result<Polynomial, Error> {
+    product(p, &Polynomial::constant(c.clone())?)
+}
+
+pub fn add(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    Ok(vec![sum(&polynomial(x)?, &polynomial(y)?)?.into_value()])
+}
+
+pub fn negate(x: Value) -> Result<Vec<Value>, Error> {
+    Ok(vec![scale(&polynomial(x)?, &Value::integer(-1))?.into_value()])
+}
+
+pub fn subtract(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    add(x, single(negate(y))?)
+}
+
+pub fn multiply(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    Ok(vec![product(&polynomial(x)?, &polynomial(y)?)?.into_value()])
+}
+
+/// Divides by a number, or by a polynomial that divides evenly.
+pub fn divide(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    match y {
+        Value::Polynomial(_) => match divide_with_remainder(x, y)? {
+            (quotient, remainder) if is_zero(&remainder) => Ok(vec![quotient]),
+            _ => Err(Error::NotDivisible),
+        },
+        y => {
+            let reciprocal = single(divide_values(Value::integer(1), y))?;
+            Ok(vec![scale(&polynomial(x)?, &reciprocal)?.into_value()])
+        },
+    }
+}
+
+/// Raises a polynomial to a natural number power.
+pub fn power(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    let n = match y {
+        Value::ComplexNumber(n, 1, 0, _) if n >= 0 => n,
+        Value::ComplexNumber(_, 1, 0, _) => return Err(Error::NotDivisible),
+        Value::ComplexNumber(..) => return Err(Error::NotAnInteger),
+        _ => return Err(Error::MismatchedType),
+    };
+    let (mut base, mut result) = (polynomial(x)?, Polynomial::constant(Value::integer(1))?);
+    let mut n = n as usize;
+    while n > 0 {
+        if n & 1 == 1 {
+            result = product(&result, &base)?;
+        }
+        n >>= 1;
+        if n > 0 {
+            base = product(&base, &base)?;
+        }
+    }
+    Ok(vec![result.into_value()])
+}
+
+/// Finds the single variable shared by two polynomials, for the operations that
+/// only work on polynomials in one variable.
+fn common_variable(p: &Polynomial, q: &Polynomial) -> Result<String, Error> {
+    let mut variables = p.variables();
+    variables.extend(q.variables());
+    variables.sort();
+    variables.dedup();
+    match &variables[..] {
+        [x] => Ok(x.clone()),
+        [] => Ok(String::from("x")),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+/// Divides dense coefficient lists, giving the quotient and remainder.
+fn dense_division(mut p: Vec<Value>, q: &[Value]) -> Result<(Vec<Value>, Vec<Value>), Error> {
+    let n = q.iter().rposition(|c| !is_zero(c)).ok_or(Error::DivisionByZero)?;
+    let lead = &q[n];
+    let mut quotient = vec![Value::integer(0); p.len().saturating_sub(n).max(1)];
+    for k in (n..p.len()).rev() {
+        let factor = single(divide_values(p[k].clone(), lead.clone()))?;
+        for (j, c) in q[..=n].iter().enumerate() {
+            p[k - n + j] = single(subtract_values(p[k - n + j].clone(), single(multiply_values(factor.clone(), c.clone()))?))?;
+        }
+        quotient[k - n] = factor;
+    }
+    p.truncate(n.max(1));
+    Ok((quotient, p))
+}
+
+fn divide_with_remainder(x: Value, y: Value) -> Result<(Value, Value), Error> {
+    let (p, q) = (polynomial(x)?, polynomial(y)?);
+    let variable = common_variable(&p, &q)?;
+    let (quotient, remainder) = dense_division(p.dense(&variable)?, &q.dense(&variable)?)?;
+    Ok((
+        Polynomial::from_dense(quotient, &variable)?.into_value(),
+        Polynomial::from_dense(remainder, &variable)?.into_value(),
+    ))
+}
+
+pub fn quotient(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    Ok(vec![divide_with_remainder(x, y)?.0])
+}
+
+pub fn remainder(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    Ok(vec![divide_with_remainder(x, y)?.1])
+}
+
+/// Makes the leading coefficient one.
+fn monic(p: Polynomial) -> Result<Polynomial, Error> {
+    match p.terms.first() {
+        Some((_, lead)) => {
+            let reciprocal = single(divide_values(Value::integer(1), lead.clone()))?;
+            scale(&p, &reciprocal)
+        },
+        None => Ok(p),
+    }
+}
+
+/// Finds the monic greatest common divisor of some polynomials in one variable
+/// with Euclid's algorithm.
+pub fn gcd(x: Value) -> Result<Vec<Value>, Error> {
+    let mut divisor = Polynomial { terms: Vec::new() };
+    for p in arguments(x) {
+        let p = polynomial(p)?;
+        let variable = common_variable(&divisor, &p)?;
+        let a = dense_gcd(divisor.dense(&variable)?, p.dense(&variable)?)?;
+        divisor = monic(Polynomial::from_dense(a, &variable)?)?;
+    }
+    Ok(vec![divisor.into_value()])
+}
+
+/// Evaluates a polynomial with its variables, in alphabetical order, set to the
+/// arguments. The arguments can be polynomials too, which composes them.
+pub fn call(p: &Polynomial, x: Value) -> Result<Vec<Value>, Error> {
+    let variables = p.variables();
+    let xs = match (variables.len(), x) {
+        (1, x) => vec![x],
+        (_, Value::Tuple(xs)) => xs,
+        (_, x) => vec![x],
+    };
+    if xs.len() != variables.len() {
+        return Err(Error::WrongArgumentCount);
+    }
+    let mut result = Value::integer(0);
+    for (monomial, coefficient) in &p.terms {
+        let mut term = coefficient.clone();
+        for (x, k) in monomial {
+            let value = xs[variables.iter().position(|y| y == x).unwrap_or(0)].clone();
+            term = single(multiply_values(term, single(power_values(value, Value::integer(*k as isize)))?))?;
+        }
+        result = single(add_values(result, term))?;
+    }
+    Ok(vec![result])
+}
+
+/// Reads the polynomial argument of `deg` and `coeffs`, with the variable given
+/// as a second argument or else the only one in the polynomial.
+fn polynomial_and_variable(x: Value) -> Result<(Polynomial, String), Error> {
+    match &arguments(x)[..] {
+        [p] => {
+            let p = polynomial(p.clone())?;
+            let variable = common_variable(&p, &p)?;
+            Ok((p, variable))
+        },
+        [p, Value::Polynomial(x)] => match &x.variables()[..] {
+            [variable] if *x == Polynomial::variable(variable) => Ok((polynomial(p.clone())?, variable.clone())),
+            _ => Err(Error::MismatchedType),
+        },
+        [_, _] => Err(Error::MismatchedType),
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+/// Finds the degree of a polynomial, in one variable if one is given.
+pub fn deg(x: Value) -> Result<Vec<Value>, Error> {
+    let single_variable = matches!(&arguments(x.clone())[..], [_, _]);
+    let (p, variable) = match single_variable {
+        true => polynomial_and_variable(x)?,
+        false => (polynomial(arguments(x).remove(0))?, String::new()),
+    };
+    let degree = match single_variable {
+        true => p.terms.iter().map(|(m, _)| exponent(m, &variable)).max().unwrap_or(0),
+        false => p.degree(),
+    };
+    Ok(vec![Value::integer(degree as isize)])
+}
+
+/// Lists the coefficients of a polynomial from the highest power down. For a
+/// polynomial in several variables, the coefficients are polynomials in the
+/// other variables.
+pub fn coeffs(x: Value) -> Result<Vec<Value>, Error> {
+    let (p, variable) = polynomial_and_variable(x)?;
+    let degree = p.terms.iter().map(|(m, _)| exponent(m, &variable)).max().unwrap_or(0);
+    let mut coefficients = vec![Vec::new(); degree as usize + 1];
+    for (monomial, c) in p.terms {
+        let k = exponent(&monomial, &variable);
+        let rest = monomial.into_iter().filter(|(x, _)| *x != variable).collect();
+        coefficients[(degree - k) as usize].push((rest, c));
+    }
+    let coefficients = coefficients.into_iter()
+        .map(|terms| Ok(Polynomial::new(terms)?.into_value()))
+        .collect::<Result<_, Error>>()?;
+    Ok(vec![Value::List(coefficients)])
+}
+
+/// Splits a polynomial that is linear in the `unknowns` into their coefficients,
+/// which must be numbers, and the rest, which can be a polynomial in other
+/// variables. Gives nothing if the polynomial is not linear in them.
+pub fn linear_coefficients(p: &Polynomial, unknowns: &[String]) -> Option<(Vec<Value>, Value)> {
+    let mut coefficients = vec![Value::integer(0); unknowns.len()];
+    let mut rest = Vec::new();
+    for (monomial, c) in &p.terms {
+        match &monomial[..] {
+            [(x, 1)] if unknowns.contains(x) => {
+                coefficients[unknowns.iter().position(|u| u == x)?] = c.clone();
+            },
+            _ if monomial.iter().any(|(x, _)| unknowns.contains(x)) => return None,
+            _ => rest.push((monomial.clone(), c.clone())),
+        }
+    }
+    Some((coefficients, Polynomial::new(rest).ok()?.into_value()))
+}
+
+/// Evaluates a polynomial with rational coefficients at a rational point.
+fn evaluate_rational(coefficients: &[(isize, isize)], x: (isize, isize)) -> Option<(isize, isize)> {
+    coefficients.iter().rev().try_fold((0, 1), |sum, c| rational_add(rational_multiply(sum, x)?, *c))
+}
+
+/// Finds the rational roots of a polynomial in one variable with rational
+/// coefficients by trying every `±p/q` where `p` divides the constant term and
+/// `q` divides the leading coefficient.
+pub fn roots(x: Value) -> Result<Vec<Value>, Error> {
+    let (p, variable) = polynomial_and_variable(x)?;
+    let coefficients = p.dense(&variable)?.iter().map(to_real).collect::<Result<Vec<_>, _>>()?;
+    if coefficients.len() < 2 {
+        return Err(Error::OutOfDomain);
+    }
+    let mut roots = Vec::new();
+    // A root of zero is a factor of the variable, which is divided out first
+    let start = coefficients.iter().position(|c| c.0 != 0).unwrap_or(0);
+    if start > 0 {
+        roots.push((0, 1));
+    }
+    let coefficients = &coefficients[start..];
+    // Clearing the divisors gives integer coefficients with the same roots
+    let multiple = coefficients.iter().try_fold(1, |m, (_, b)| integers::lcm(m, *b)).ok_or(Error::Overflow)?;
+    let integers = coefficients.iter().map(|(a, b)| a.checked_mul(multiple / b)).collect::<Option<Vec<_>>>().ok_or(Error::Overflow)?;
+    let (constant, lead) = (integers[0].unsigned_abs() as u64, integers[integers.len() - 1].unsigned_abs() as u64);
+    if integers.len() > 1 {
+        for p in integers::divisors(constant) {
+            for q in integers::divisors(lead) {
+                for candidate in [(p as isize, q as isize), (-(p as isize), q as isize)] {
+                    let candidate = reduce(candidate.0, candidate.1);
+                    if evaluate_rational(coefficients, candidate) == Some((0, 1)) && !roots.contains(&candidate) {
+                        roots.push(candidate);
+                    }
+                }
+            }
+        }
+    }
+    roots.sort_by(|x, y| real_order(*x, *y));
+    Ok(roots.into_iter().map(|(a, b)| Value::rational(a, b)).collect())
+}
+
+fn conj(x: Value) -> Option<Value> {
+    match x {
+        Value::ComplexNumber(a, b, c, d) => Some(Value::ComplexNumber(a, b, -c, d)),
+        _ => None,
+    }
+}
+
+/// Orders solutions with the real ones first, from the smallest up. Surds are
+/// ordered by their approximate values.
+fn solution_order(x: &Value, y: &Value) -> Ordering {
+    let key = |x: &Value| match symbolic::approximate(x) {
+        Ok(Value::ComplexNumber(a, b, c, d)) => (c != 0, to_f64((a, b)), to_f64((c, d))),
+        _ => (true, 0.0, 0.0),
+    };
+    let ((x_complex, x_re, x_im), (y_complex, y_re, y_im)) = (key(x), key(y));
+    x_complex.cmp(&y_complex).then(x_re.total_cmp(&y_re)).then(x_im.total_cmp(&y_im))
+}
+
+/// Solves `p = 0` for the only variable of `p`. Rational roots are found
+/// exactly and divided out, and a real polynomial that is left is factored
+/// over the rationals. Quadratic factors are solved exactly by the quadratic
+/// formula, with surds for irrational roots, and the rest in floating point by
+/// the cubic and quartic formulas or numerically for higher degrees.
+pub fn solve(p: &Polynomial) -> Result<Vec<Value>, Error> {
+    let variable = common_variable(p, p)?;
+    let mut coefficients = p.dense(&variable)?;
+    let mut solutions = Vec::new();
+    let real = coefficients.iter().all(|c| to_real(c).is_ok());
+    if real {
+        for r in roots(Value::Polynomial(p.clone()))? {
+            // Divide out every factor of x - r, so that no root is found twice
+            let factor = [single(negate_values(r.clone()))?, Value::integer(1)];
+            loop {
+                let (quotient, remainder) = dense_division(coefficients.clone(), &factor)?;
+                if !remainder.iter().all(is_zero) {
+                    break;
+                }
+                coefficients = quotient;
+            }
+            solutions.push(r);
+        }
+    }
+    // A polynomial too large to factor is solved as it is
+    let parts = match real && coefficients.len() > 3 {
+        true => irreducible_factors(&coefficients).unwrap_or_else(|_| vec![coefficients]),
+        false => vec![coefficients],
+    };
+    for part in parts {
+        solutions.extend(solve_dense(&part)?);
+    }
+    solutions.sort_by(solution_order);
+    solutions.dedup();
+    Ok(solutions)
+}
+
+/// Splits a polynomial with rational coefficients into its distinct factors
+/// that are irreducible over the rationals.
+fn irreducible_factors(p: &[Value]) -> Result<Vec<Vec<Value>>, Error> {
+    let mut factors = Vec::new();
+    for (part, _) in square_free(&dense_monic(p.to_vec())?)? {
+        factors.extend(rational_factors(&part)?);
+    }
+    Ok(factors)
+}
+
+/// The square root of a discriminant, which is a surd or `i` times one when
+/// the discriminant is rational.
+fn discriminant_root(discriminant: Value) -> Result<Value, Error> {
+    match to_real(&discriminant) {
+        Ok((a, b)) if a >= 0 => symbolic::surd((a, b)),
+        Ok((a, b)) => single(multiply_values(symbolic::surd((-a, b))?, Value::complex(0, 1, 1, 1))),
+        Err(_) => single(power_values(discriminant, Value::rational(1, 2))),
+    }
+}
+
+/// Solves a polynomial given by its coefficients from the constant term up.
+fn solve_dense(coefficients: &[Value]) -> Result<Vec<Value>, Error> {
+    let mut solutions = Vec::new();
+    match coefficients {
+        [_] => {},
+        [c, b] => solutions.push(single(negate_values(single(divide_values(c.clone(), b.clone()))?))?),
+        [c, b, a] => {
+            let product = single(multiply_values(Value::integer(4), single(multiply_values(a.clone(), c.clone()))?))?;
+            let discriminant = single(subtract_values(single(multiply_values(b.clone(), b.clone()))?, product))?;
+            let root = discriminant_root(discriminant)?;
+            let divisor = single(multiply_values(Value::integer(2), a.clone()))?;
+            for root in [root.clone(), single(negate_values(root))?] {
+                let numerator = single(subtract_values(root, b.clone()))?;
+                solutions.push(single(divide_values(numerator, divisor.clone()))?);
+            }
+        },
+        coefficients => {
+            let complex = coefficients.iter().map(|c| match c {
+                Value::ComplexNumber(a, b, c, d) => Ok(numeric::Complex::new(to_f64((*a, *b)), to_f64((*c, *d)))),
+                _ => Err(Error::MismatchedType),
+            }).collect::<Result<Vec<_>, Error>>()?;
+            // The roots of a real polynomial come in conjugate pairs, which are
+            // kept exactly conjugate by only using the upper one of each
+            let real = complex.iter().all(|c| c.im == 0.0);
+            for z in numeric::polynomial_roots(&complex) {
+                match z.im.abs() < 1e-12 * z.abs().max(1.0) {
+                    true if real => solutions.extend(from_f64_pair(z.re, 0.0)?),
+                    false if real && z.im < 0.0 => {},
+                    false if real => {
+                        let root = from_f64_pair(z.re, z.im)?.remove(0);
+                        solutions.push(root.clone());
+                        solutions.extend(conj(root));
+                    },
+                    _ => solutions.extend(from_f64_pair(z.re, z.im)?),
+                }
+            }
+        },
+    }
+    Ok(solutions)
+}
+
+/// How many candidate factors Kronecker's method tries for one degree before
+/// giving up.
+const MAX_CANDIDATES: usize = 20_000;
+
+/// Drops the zero coefficients at the top of a dense coefficient list.
+fn trim(mut p: Vec<Value>) -> Vec<Value> {
+    while p.len() > 1 && p.last().is_some_and(is_zero) {
+        p.pop();
+    }
+    p
+}
+
+fn dense_scale(p: &[Value], c: &Value) -> Result<Vec<Value>, Error> {
+    p.iter().map(|x| single(multiply_values(x.clone(), c.clone()))).collect()
+}
+
+fn dense_monic(p: Vec<Value>) -> Result<Vec<Value>, Error> {
+    let p = trim(p);
+    let lead = p[p.len() - 1].clone();
+    dense_scale(&p, &single(divide_values(Value::integer(1), lead))?)
+}
+
+fn dense_subtract(p: &[Value], q: &[Value]) -> Result<Vec<Value>, Error> {
+    let zero = Value::integer(0);
+    let difference = (0..p.len().max(q.len()))
+        .map(|k| single(subtract_values(p.get(k).unwrap_or(&zero).clone(), q.get(k).unwrap_or(&zero).clone())))
+        .collect::<Result<_, _>>()?;
+    Ok(trim(difference))
+}
+
+fn dense_product(p: &[Value], q: &[Value]) -> Result<Vec<Value>, Error> {
+    let mut product = vec![Value::integer(0); p.len() + q.len() - 1];
+    for (i, a) in p.iter().enumerate() {
+        for (j, b) in q.iter().enumerate() {
+            product[i + j] = single(add_values(product[i + j].clone(), single(multiply_values(a.clone(), b.clone()))?))?;
+        }
+    }
+    Ok(trim(product))
+}
+
+fn dense_derivative(p: &[Value]) -> Result<Vec<Value>, Error> {
+    match p.len() {
+        1 => Ok(vec![Value::integer(0)]),
+        _ => p.iter().enumerate().skip(1).map(|(k, c)| single(multiply_values(c.clone(), Value::integer(k as isize)))).collect(),
+    }
+}
+
+/// Divides by a polynomial that is known to be a factor.
+fn dense_quotient(p: Vec<Value>, q: &[Value]) -> Result<Vec<Value>, Error> {
+    Ok(trim(dense_division(p, q)?.0))
+}
+
+/// Finds the monic greatest common divisor of two dense coefficient lists.
+fn dense_gcd(mut a: Vec<Value>, mut b: Vec<Value>) -> Result<Vec<Value>, Error> {
+    while b.iter().any(|c| !is_zero(c)) {
+        let (_, remainder) = dense_division(a, &b)?;
+        (a, b) = (b, remainder);
+    }
+    match a.iter().all(is_zero) {
+        true => Ok(trim(a)),
+        false => dense_monic(a),
+    }
+}
+
+/// Substitutes `x + s` for `x`.
+fn dense_shift(p: &[Value], s: &Value) -> Result<Vec<Value>, Error> {
+    let mut shifted = vec![Value::integer(0)];
+    for c in p.iter().rev() {
+        shifted = dense_product(&shifted, &[s.clone(), Value::integer(1)])?;
+        shifted[0] = single(add_values(shifted[0].clone(), c.clone()))?;
+    }
+    Ok(trim(shifted))
+}
+
+fn dense_evaluate(p: &[Value], x: &Value) -> Result<Value, Error> {
+    p.iter().rev().try_fold(Value::integer(0), |sum, c| single(add_values(single(multiply_values(sum, x.clone()))?, c.clone())))
+}
+
+/// Splits a monic polynomial into square-free factors with their
+/// multiplicities by Yun's algorithm.
+fn square_free(p: &[Value]) -> Result<Vec<(Vec<Value>, u32)>, Error> {
+    let derivative = dense_derivative(p)?;
+    let common = dense_gcd(p.to_vec(), derivative.clone())?;
+    let mut b = dense_quotient(p.to_vec(), &common)?;
+    let mut c = dense_quotient(derivative, &common)?;
+    let mut factors = Vec::new();
+    let mut k = 1;
+    while b.len() > 1 {
+        let d = dense_subtract(&c, &dense_derivative(&b)?)?;
+        let a = dense_gcd(b.clone(), d.clone())?;
+        if a.len() > 1 {
+            factors.push((a.clone(), k));
+        }
+        b = dense_quotient(b, &a)?;
+        c = dense_quotient(d, &a)?;
+        k += 1;
+    }
+    Ok(factors)
+}
+
+/// Scales a polynomial with rational coefficients to the one with coprime
+/// integer coefficients and a positive leading coefficient.
+fn primitive(p: &[Value]) -> Result<Vec<Value>, Error> {
+    let coefficients = p.iter().map(to_real).collect::<Result<Vec<_>, _>>()?;
+    let multiple = coefficients.iter().try_fold(1, |m, (_, b)| integers::lcm(m, *b)).ok_or(Error::Overflow)?;
+    let integers = coefficients.iter().map(|(a, b)| a.checked_mul(multiple / b)).collect::<Option<Vec<_>>>().ok_or(Error::Overflow)?;
+    let divisor = integers.iter().try_fold(0, |g, a| gcd_integers(g, *a)).ok_or(Error::Overflow)? * integers[integers.len() - 1].signum();
+    Ok(integers.iter().map(|a| Value::integer(a / divisor)).collect())
+}
+
+/// Makes the polynomial through the points `(x, y)` by Lagrange interpolation.
+fn interpolate(xs: &[isize], ys: &[isize]) -> Result<Vec<Value>, Error> {
+    let mut sum = vec![Value::integer(0)];
+    for (j, (xj, yj)) in xs.iter().zip(ys).enumerate() {
+        let mut basis = vec![Value::integer(1)];
+        let mut divisor = 1isize;
+        for (_, xk) in xs.iter().enumerate().filter(|(k, _)| *k != j) {
+            basis = dense_product(&basis, &[Value::integer(-xk), Value::integer(1)])?;
+            divisor = divisor.checked_mul(xj - xk).ok_or(Error::Overflow)?;
+        }
+        let term = dense_scale(&basis, &Value::rational(*yj, divisor))?;
+        let length = sum.len().max(term.len());
+        sum.resize(length, Value::integer(0));
+        for (s, t) in sum.iter_mut().zip(term) {
+            *s = single(add_values(s.clone(), t))?;
+        }
+    }
+    Ok(trim(sum))
+}
+
+/// Factors a primitive polynomial with no rational roots by Kronecker's
+/// method. A factor of degree `d` is found among the polynomials through
+/// `d + 1` points, where its value at each point divides the polynomial's.
+fn kronecker(p: Vec<Value>) -> Result<Vec<Vec<Value>>, Error> {
+    let n = p.len() - 1;
+    // The points where the value has the fewest divisors leave the fewest
+    // candidates to try
+    let mut points = (-(n as isize)..=n as isize).map(|x| match dense_evaluate(&p, &Value::integer(x))? {
+        Value::ComplexNumber(y, 1, 0, _) if y != 0 => Ok((x, y, integers::divisors(y.unsigned_abs() as u64).len())),
+        _ => Err(Error::MismatchedType),
+    }).collect::<Result<Vec<_>, Error>>()?;
+    points.sort_by_key(|(_, _, count)| *count);
+    for d in 2..=n / 2 {
+        let xs: Vec<isize> = points[..=d].iter().map(|(x, _, _)| *x).collect();
+        let ys: Vec<isize> = points[..=d].iter().map(|(_, y, _)| *y).collect();
+        // A factor is only found up to its sign, which is fixed at the first point
+        let choices: Vec<Vec<isize>> = ys.iter().enumerate().map(|(j, y)| {
+            let divisors = integers::divisors(y.unsigned_abs() as u64).into_iter().map(|d| d as isize);
+            match j {
+                0 => divisors.collect(),
+                _ => divisors.flat_map(|d| [d, -d]).collect(),
+            }
+        }).collect();
+        if choices.iter().try_fold(1usize, |count, c| count.checked_mul(c.len()).filter(|&count| count <= MAX_CANDIDATES)).is_none() {
+            return Err(Error::Overflow);
+        }
+        let mut indices = vec![0; choices.len()];
+        loop {
+            let values: Vec<isize> = indices.iter().zip(&choices).map(|(&i, c)| c[i]).collect();
+            let candidate = interpolate(&xs, &values)?;
+            let integral = candidate.iter().all(|c| matches!(c, Value::ComplexNumber(_, 1, 0, _)));
+            if candidate.len() == d + 1 && integral {
+                let (quotient, remainder) = dense_division(p.clone(), &candidate)?;
+                if remainder.iter().all(is_zero) {
+                    let mut factors = vec![primitive(&candidate)?];
+                    factors.extend(kronecker(primitive(&trim(quotient))?)?);
+                    return Ok(factors);
+                }
+            }
+            // Step to the next combination of values
+            let Some(j) = (0..indices.len()).find(|&j| indices[j] + 1 < choices[j].len()) else {
+                break;
+            };
+            indices[j] += 1;
+            indices[..j].iter_mut().for_each(|i| *i = 0);
+        }
+    }
+    Ok(vec![p])
+}
+
+/// Factors a square-free polynomial with rational coefficients into primitive
+/// factors that are irreducible over the rationals.
+fn rational_factors(p: &[Value]) -> Result<Vec<Vec<Value>>, Error> {
+    let mut p = primitive(p)?;
+    let mut factors = Vec::new();
+    if p.len() > 1 {
+        for root in roots(Polynomial::from_dense(p.clone(), "x")?.into_value())? {
+            let factor = match root {
+                Value::ComplexNumber(a, b, _, _) => vec![Value::integer(-a), Value::integer(b)],
+                _ => return Err(Error::MismatchedType),
+            };
+            p = dense_quotient(p, &factor)?;
+            factors.push(factor);
+        }
+    }
+    if p.len() > 4 {
+        let (cyclotomic, rest) = cyclotomic_factors(p)?;
+        factors.extend(cyclotomic);
+        p = rest;
+    }
+    match p.len() {
+        1 => {},
+        2..=4 => factors.push(p),
+        _ => factors.extend(kronecker(p)?),
+    }
+    Ok(factors)
+}
+
+/// Divides the cyclotomic polynomials `Φ(n)` out of a square-free primitive
+/// polynomial, for `n` up to twice its degree, which covers every factor of
+/// `x^n - 1` and `x^n + 1`. Each is irreducible, and they leave less for
+/// Kronecker's method to split, which is too slow for them. Gives the
+/// factors found and what is left.
+fn cyclotomic_factors(mut p: Vec<Value>) -> Result<(Vec<Vec<Value>>, Vec<Value>), Error> {
+    let mut cyclotomic: Vec<Vec<Value>> = vec![vec![Value::integer(1)]];
+    let mut factors = Vec::new();
+    for n in 1..=2 * (p.len() - 1) {
+        // x^n - 1 is the product of Φ(d) for the divisors d of n
+        let mut phi = vec![Value::integer(0); n + 1];
+        phi[0] = Value::integer(-1);
+        phi[n] = Value::integer(1);
+        for d in (1..n).filter(|d| n % d == 0) {
+            phi = dense_quotient(phi, &cyclotomic[d])?;
+        }
+        if phi.len() <= p.len() {
+            let (quotient, remainder) = dense_division(p.clone(), &phi)?;
+            if remainder.iter().all(is_zero) {
+                p = trim(quotient);
+                factors.push(phi.clone());
+            }
+        }
+        cyclotomic.push(phi);
+    }
+    Ok((factors, p))
+}
+
+fn dense_conj(p: &[Value]) -> Vec<Value> {
+    p.iter().map(|c| conj(c.clone()).unwrap_or_else(|| c.clone())).collect()
+}
+
+/// Factors a square-free polynomial over the Gaussian rationals by Trager's
+/// method: for a shift `s` that makes the norm `p(x - s i) p̄(x + s i)`
+/// square-free, each factor of the norm over the rationals, shifted back, has
+/// a factor of `p` in common with it.
+fn gaussian_factors(p: &[Value]) -> Result<Vec<Vec<Value>>, Error> {
+    let p = dense_monic(p.to_vec())?;
+    if p.len() <= 2 {
+        return Ok(vec![p]);
+    }
+    for s in 1..=8 {
+        let shifted = dense_shift(&p, &Value::complex(0, 1, -s, 1))?;
+        let norm = dense_product(&shifted, &dense_conj(&shifted))?;
+        if dense_gcd(norm.clone(), dense_derivative(&norm)?)?.len() > 1 {
+            continue;
+        }
+        let mut factors = Vec::new();
+        for factor in rational_factors(&norm)? {
+            let common = dense_gcd(p.clone(), dense_shift(&factor, &Value::complex(0, 1, s, 1))?)?;
+            if common.len() > 1 {
+                factors.push(common);
+            }
+        }
+        return Ok(factors);
+    }
+    Ok(vec![p])
+}
+
+/// Factors a polynomial in one variable into irreducible factors over the
+/// rationals, or over the Gaussian rationals if `gaussian` is set or it has
+/// complex coefficients, and writes it as their product.
+pub fn factor(x: Value, gaussian: bool) -> Result<Vec<Value>, Error> {
+    // A polynomial in several variables is left as it is
+    if matches!(&x, Value::Polynomial(p) if p.variables().len() > 1) {
+        return Ok(vec![x]);
+    }
+    let (p, variable) = polynomial_and_variable(x)?;
+    let coefficients = p.dense(&variable)?;
+    let real = coefficients.iter().all(|c| to_real(c).is_ok());
+    let gaussian = gaussian || !real;
+    let mut factors = Vec::new();
+    for (part, k) in square_free(&dense_monic(coefficients.clone())?)? {
+        let parts = match real {
+            true => rational_factors(&part)?,
+            false => vec![part],
+        };
+        for part in parts {
+            match gaussian {
+                true => factors.extend(gaussian_factors(&part)?.into_iter().map(|f| (f, k))),
+                false => factors.push((part, k)),
+            }
+        }
+    }
+    // The constant in front is what is left of the leading coefficient
+    let mut constant = coefficients[coefficients.len() - 1].clone();
+    for (f, k) in &factors {
+        let lead = single(power_values(f[f.len() - 1].clone(), Value::integer(*k as isize)))?;
+        constant = single(divide_values(constant, lead))?;
+    }
+    // Factors go from the lowest degree up, with real ones first, in order of
+    // their constant terms
+    let key = |f: &Vec<Value>| match &f[0] {
+        Value::ComplexNumber(a, b, c, d) => (f.len(), f.iter().any(|c| to_real(c).is_err()), to_f64((*a, *b)), to_f64((*c, *d))),
+        _ => (f.len(), true, 0.0, 0.0),
+    };
+    factors.sort_by(|(f, _), (g, _)| {
+        let ((m, x, a, b), (n, y, c, d)) = (key(f), key(g));
+        m.cmp(&n).then(x.cmp(&y)).then(a.total_cmp(&c)).then(b.total_cmp(&d))
+    });
+    if let ([(f, 1)], Value::ComplexNumber(1, 1, 0, _)) = (&factors[..], &constant) {
+        return Ok(vec![Polynomial::from_dense(f.clone(), &variable)?.into_value()]);
+    }
+    let mut product = match constant {
+        Value::ComplexNumber(1, 1, 0, _) | Value::ComplexNumber(-1, 1, 0, _) => None,
+        ref constant => Some(to_expression(constant)?),
+    };
+    for (f, k) in factors {
+        let mut factor = to_expression(&Polynomial::from_dense(f, &variable)?.into_value())?;
+        if k > 1 {
+            factor = Expression::Power(0, Box::new(factor), Box::new(Expression::Number(0, k as isize, 1)));
+        }
+        product = Some(match product {
+            Some(product) => Expression::Multiply(0, Box::new(product), Box::new(factor)),
+            // A constant of -1 is written as a minus sign on the first factor
+            None if constant == Value::integer(-1) => Expression::Negate(0, Box::new(factor)),
+            None => factor,
+        });
+    }
+    let product = product.unwrap_or(Expression::Number(0, 1, 1));
+    Ok(vec![Value::Symbolic(product)])
+}
+
+/// Reduces the coefficients of a polynomial with `reduce`, for modular arithmetic.
+pub fn map_coefficients(p: Polynomial, reduce: &dyn Fn(Value) -> Result<Value, Error>) -> Result<Value, Error> {
+    let terms = p.terms.into_iter().map(|(m, c)| Ok((m, reduce(c)?))).collect::<Result<_, Error>>()?;
+    Ok(Polynomial::new(terms)?.into_value())
+}
+
+fn write_monomial(monomial: &Monomial) -> String {
+    monomial.iter()
+        .map(|(x, k)| match k {
+            1 => x.clone(),
+            k => format!("{}^{}", x, k),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Writes a term without its sign, like `3x^2` or `3x/2` or `(1 + 2i)x`.
+fn write_term(monomial: &Monomial, coefficient: &Value) -> String {
+    let variables = write_monomial(monomial);
+    match coefficient {
+        Value::ComplexNumber(a, b, 0, d) if monomial.is_empty() => format!("{:?}", Value::ComplexNumber(a.abs(), *b, 0, *d)),
+        Value::ComplexNumber(a, 1, 0, _) if a.abs() == 1 => variables,
+        Value::ComplexNumber(a, 1, 0, _) => format!("{}{}", a.abs(), variables),
+        Value::ComplexNumber(a, b, 0, _) if format::current().number == NumberForm::Fraction && format::current().radix == 10 => {
+            match a.abs() {
+                1 => format!("{}/{}", variables, b),
+                a => format!("{}{}/{}", a, variables, b),
+            }
+        },
+        Value::ComplexNumber(a, b, 0, _) => format!("{:?} {}", Value::rational(a.abs(), *b), variables),
+        c => format!("({:?}){}", c, variables),
+    }
+}
+
+fn is_negative(x: &Value) -> bool {
+    matches!(x, Value::ComplexNumber(a, _, 0, _) if *a < 0)
+}
+
+impl std::fmt::Debug for Polynomial {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (k, (monomial, coefficient)) in self.terms.iter().enumerate() {
+            let sign = match (k, is_negative(coefficient)) {
+                (0, true) => "-",
+                (0, false) => "",
+                (_, true) => " - ",
+                (_, false) => " + ",
+            };
+            write!(f, "{}{}", sign, write_term(monomial, coefficient))?;
+        }
+        Ok(())
+    }
+}
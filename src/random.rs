@@ -0,0 +1,99 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::builtins::elements;
+use crate::eval::{Error, to_integer};
+use crate::values::Value;
+
+/// The divisor of the numbers given by `rand()`.
+const RESOLUTION: u64 = 1_000_000_000_000;
+
+/// A SplitMix64 generator, which is small and fast and gives the same numbers
+/// every time it is given the same seed.
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random { state: seed }
+    }
+
+    /// Seeds a generator from the clock, for sessions that do not set a seed.
+    pub fn from_time() -> Random {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_nanos()).unwrap_or(0);
+        Random::new(time as u64)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Picks a number below `n` uniformly, drawing again when the number drawn
+    /// falls in the uneven part at the top of the range.
+    pub fn below(&mut self, n: u64) -> u64 {
+        let limit = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next();
+            if x < limit {
+                return x % n;
+            }
+        }
+    }
+}
+
+/// Gives a number from 0 up to but not including 1.
+pub fn rand(random: &mut Random, xs: Vec<Value>) -> Result<Vec<Value>, Error> {
+    match xs[..] {
+        [] => Ok(vec![Value::rational(random.below(RESOLUTION) as isize, RESOLUTION as isize)]),
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+/// Gives an integer from `a` to `b`, including both.
+pub fn randint(random: &mut Random, xs: Vec<Value>) -> Result<Vec<Value>, Error> {
+    let (a, b) = match &xs[..] {
+        [a, b] => (to_integer(a)?, to_integer(b)?),
+        _ => return Err(Error::WrongArgumentCount),
+    };
+    if a > b {
+        return Err(Error::OutOfDomain);
+    }
+    let offset = match (b as i128 - a as i128) as u64 {
+        u64::MAX => random.next(),
+        span => random.below(span + 1),
+    };
+    Ok(vec![Value::integer((a as i128 + offset as i128) as isize)])
+}
+
+/// Gives a fraction from 0 up to but not including 1 whose divisor is at most `n`.
+pub fn randrat(random: &mut Random, xs: Vec<Value>) -> Result<Vec<Value>, Error> {
+    let n = match &xs[..] {
+        [n] => to_integer(n)?,
+        _ => return Err(Error::WrongArgumentCount),
+    };
+    if n < 1 {
+        return Err(Error::OutOfDomain);
+    }
+    let divisor = random.below(n as u64) + 1;
+    Ok(vec![Value::rational(random.below(divisor) as isize, divisor as isize)])
+}
+
+/// Picks `n` different elements of a list in a random order.
+pub fn sample(random: &mut Random, xs: Vec<Value>) -> Result<Vec<Value>, Error> {
+    let (mut list, n) = match &xs[..] {
+        [list, n] => (elements(list.clone()), to_integer(n)?),
+        _ => return Err(Error::WrongArgumentCount),
+    };
+    if n < 0 || n as usize > list.len() {
+        return Err(Error::OutOfDomain);
+    }
+    for i in 0..n as usize {
+        let j = i + random.below((list.len() - i) as u64) as usize;
+        list.swap(i, j);
+    }
+    list.truncate(n as usize);
+    Ok(vec![Value::List(list)])
+}
@@ -1,4 +1,7 @@
-use crate::eval::Evaluator;
+use crate::eval::{Evaluator, Error};
+use crate::format;
+use crate::parser::Parser;
+use crate::values::Value;
 use std::io;
 
 pub struct Repl {
@@ -16,38 +19,92 @@ impl Repl {
 
         loop {
             let input = self.get_input();
-            let result = self.evaluator.evaluate(input);
-            match result {
-                Ok(values) => {
-                    let mut values_iter = values.iter().peekable();
-                    while let Some(value) = values_iter.next() {
-                        if values_iter.peek().is_none() {
-                            println!("{:?}\n", value);
-                        } else {
-                            print!("{:?}, ", value);
-                        }
-                    }
-                },
-                Err(_) => {},
+            if let Some(command) = input.strip_prefix(':') {
+                self.run_command(command);
+                continue;
             }
+            let result = self.evaluator.evaluate(input);
+            print_result(result);
+            format::set_override(None);
+        }
+    }
+
+    /// Runs a REPL command, written as `:name arguments`.
+    fn run_command(&mut self, command: &str) {
+        if let Some(code) = command.strip_prefix("simplify ") {
+            let result = Parser::new(code.to_string()).parse()
+                .map_err(Error::ParseError)
+                .and_then(|expr| self.evaluator.simplify(&expr));
+            print_result(result.map(|x| vec![x]));
+            return;
+        }
+        let words: Vec<&str> = command.split_whitespace().collect();
+        match words[..] {
+            ["format", name] => self.set_format(name, None),
+            ["mod", "off"] => self.evaluator.set_modulus(None),
+            ["mod", modulus] => match modulus.parse() {
+                Ok(0) | Err(_) => println!("Error: Expected a non-zero modulus\n"),
+                Ok(modulus) => self.evaluator.set_modulus(Some(modulus)),
+            },
+            ["seed", seed] => match seed.parse() {
+                Ok(seed) => self.evaluator.set_seed(seed),
+                Err(_) => println!("Error: Expected a seed\n"),
+            },
+            ["base", radix] => match radix.parse() {
+                Ok(radix) => self.set_format("base", Some(radix)),
+                Err(_) => println!("Error: Expected a base\n"),
+            },
+            ["format", name, digits] => match digits.parse() {
+                Ok(digits) => self.set_format(name, Some(digits)),
+                Err(_) => println!("Error: Expected a number of digits\n"),
+            },
+            _ => println!("Error: Unknown command `:{}`\n", command),
+        }
+    }
+
+    fn set_format(&mut self, name: &str, digits: Option<usize>) {
+        match format::session().with(name, digits) {
+            Some(new_format) => format::set(new_format),
+            None => match digits {
+                Some(digits) => println!("Error: Unknown format `{} {}`\n", name, digits),
+                None => println!("Error: Unknown format `{}`\n", name),
+            },
         }
     }
 
     fn get_input(&self) -> String {
         let mut buffer = String::new();
         print!("> ");
-        
+
         let _ = io::Write::flush(&mut io::stdout());
-        
-        io::stdin().read_line(&mut buffer).expect("Input Error");
-        
+
+        if io::stdin().read_line(&mut buffer).expect("Input Error") == 0 {
+            std::process::exit(0);
+        }
+
         if let Some('\n') = buffer.chars().next_back() {
             buffer.pop();
         }
         if let Some('\r') = buffer.chars().next_back() {
             buffer.pop();
         }
-        
+
         buffer
     }
-}
\ No newline at end of file
+}
+
+fn print_result(result: Result<Vec<Value>, Error>) {
+    match result {
+        Ok(values) => {
+            let mut values_iter = values.iter().peekable();
+            while let Some(value) = values_iter.next() {
+                if values_iter.peek().is_none() {
+                    println!("{:?}\n", value);
+                } else {
+                    print!("{:?}, ", value);
+                }
+            }
+        },
+        Err(e) => println!("Error: {:?}\n", e),
+    }
+}
@@ -0,0 +1,581 @@
+use crate::eval::{self, Error};
+use crate::nodes::{Expression, FUNCTIONS};
+use crate::values::Value;
+use crate::calculus;
+use crate::symbolic::to_expression;
+
+/// How many times L'Hôpital's rule is applied in a row before a limit is given
+/// up on.
+const MAX_DEPTH: usize = 8;
+
+/// The highest order a series is expanded to. Longer series take too long to
+/// work out, and their coefficients overflow well before this anyway.
+pub const MAX_ORDER: isize = 100;
+
+/// The orders series are worked out to when looking for the leading term of
+/// an expression, tried in turn while the terms cancel out.
+const LIMIT_ORDERS: [i32; 3] = [4, 8, 16];
+
+/// A point on the extended line, where a limit is taken or a series expanded,
+/// or the value of a limit.
+#[derive(Clone, PartialEq)]
+pub enum Extended {
+    NegativeInfinity,
+    Finite(Value),
+    PositiveInfinity,
+}
+
+fn single(values: Result<Vec<Value>, Error>) -> Result<Value, Error> {
+    values?.into_iter().next().ok_or(Error::MismatchedType)
+}
+
+fn is_zero(x: &Value) -> bool {
+    matches!(x, Value::ComplexNumber(0, _, 0, _))
+}
+
+/// The sign of a real number that is not zero.
+fn sign(x: &Value) -> Option<i32> {
+    match x {
+        Value::ComplexNumber(a, _, 0, _) if *a != 0 => Some(a.signum() as i32),
+        _ => None,
+    }
+}
+
+fn add(x: Value, y: Value) -> Result<Value, Error> {
+    single(eval::add(x, y))
+}
+
+fn multiply(x: Value, y: Value) -> Result<Value, Error> {
+    single(eval::multiply(x, y))
+}
+
+fn divide(x: Value, y: Value) -> Result<Value, Error> {
+    single(eval::divide(x, y))
+}
+
+fn call(name: &str, x: Expression) -> Expression {
+    Expression::Call(0, Box::new(Expression::Variable(0, name.to_string())), Box::new(x))
+}
+
+/// A truncated Laurent series `Σ c t^k` in the distance `t` from the point of
+/// expansion, with the coefficients from `t^valuation` up to but not including
+/// `t^order`, which is where it stops being known.
+#[derive(Clone)]
+struct Series {
+    valuation: i32,
+    coefficients: Vec<Value>,
+    order: i32,
+}
+
+impl Series {
+    /// Makes a series from the coefficients from `t^valuation` up, padding them
+    /// with zeros or cutting them off at `order` and dropping the leading zeros.
+    fn new(valuation: i32, mut coefficients: Vec<Value>, order: i32) -> Series {
+        let length = (order - valuation).max(0) as usize;
+        coefficients.resize(length, Value::integer(0));
+        let zeros = coefficients.iter().take_while(|c| is_zero(c)).count();
+        coefficients.drain(..zeros);
+        Series { valuation: (valuation + zeros as i32).min(order), coefficients, order }
+    }
+
+    fn coefficient(&self, k: i32) -> Value {
+        match k - self.valuation {
+            i if i >= 0 && (i as usize) < self.coefficients.len() => self.coefficients[i as usize].clone(),
+            _ => Value::integer(0),
+        }
+    }
+
+    /// The leading coefficient, failing if every known one is zero.
+    fn leading(&self) -> Result<Value, Error> {
+        self.coefficients.first().cloned().ok_or(Error::Indeterminate)
+    }
+
+    /// Multiplies by `t^k`.
+    fn shift(self, k: i32) -> Series {
+        Series { valuation: self.valuation + k, coefficients: self.coefficients, order: self.order + k }
+    }
+
+    fn scale(&self, c: &Value) -> Result<Series, Error> {
+        let coefficients = self.coefficients.iter().map(|x| multiply(x.clone(), c.clone())).collect::<Result<_, _>>()?;
+        Ok(Series::new(self.valuation, coefficients, self.order))
+    }
+
+    fn add(&self, other: &Series) -> Result<Series, Error> {
+        let (valuation, order) = (self.valuation.min(other.valuation), self.order.min(other.order));
+        let coefficients = (valuation..order).map(|k| add(self.coefficient(k), other.coefficient(k))).collect::<Result<_, _>>()?;
+        Ok(Series::new(valuation, coefficients, order))
+    }
+
+    fn negate(&self) -> Result<Series, Error> {
+        self.scale(&Value::integer(-1))
+    }
+
+    fn subtract(&self, other: &Series) -> Result<Series, Error> {
+        self.add(&other.negate()?)
+    }
+
+    fn multiply(&self, other: &Series) -> Result<Series, Error> {
+        let valuation = self.valuation + other.valuation;
+        let order = (self.order + other.valuation).min(other.order + self.valuation);
+        let mut coefficients = Vec::new();
+        for k in valuation..order {
+            let mut sum = Value::integer(0);
+            for i in self.valuation..self.order.min(k - other.valuation + 1) {
+                sum = add(sum, multiply(self.coefficient(i), other.coefficient(k - i))?)?;
+            }
+            coefficients.push(sum);
+        }
+        Ok(Series::new(valuation, coefficients, order))
+    }
+
+    /// Divides one into the series term by term, which needs its leading
+    /// coefficient to be known.
+    fn reciprocal(&self) -> Result<Series, Error> {
+        let c = self.leading()?;
+        let mut coefficients: Vec<Value> = vec![divide(Value::integer(1), c.clone())?];
+        for n in 1..self.coefficients.len() {
+            let mut sum = Value::integer(0);
+            for k in 1..=n {
+                sum = add(sum, multiply(self.coefficients[k].clone(), coefficients[n - k].clone())?)?;
+            }
+            coefficients.push(divide(single(eval::negate(sum))?, c.clone())?);
+        }
+        let length = self.coefficients.len() as i32;
+        Ok(Series::new(-self.valuation, coefficients, length - self.valuation))
+    }
+
+    fn divide(&self, other: &Series) -> Result<Series, Error> {
+        self.multiply(&other.reciprocal()?)
+    }
+
+    fn derivative(&self) -> Result<Series, Error> {
+        let coefficients = self.coefficients.iter().zip(self.valuation..)
+            .map(|(c, k)| multiply(c.clone(), Value::integer(k as isize)))
+            .collect::<Result<_, _>>()?;
+        Ok(Series::new(self.valuation - 1, coefficients, self.order - 1))
+    }
+
+    /// Integrates term by term, with a constant of zero, failing on a `1 / t`
+    /// term, whose integral is a logarithm.
+    fn integral(&self) -> Result<Series, Error> {
+        if self.valuation < 0 {
+            return Err(Error::OutOfDomain);
+        }
+        let coefficients = self.coefficients.iter().zip(self.valuation..)
+            .map(|(c, k)| divide(c.clone(), Value::integer(k as isize + 1)))
+            .collect::<Result<_, _>>()?;
+        Ok(Series::new(self.valuation + 1, coefficients, self.order + 1))
+    }
+}
+
+/// Expands expressions in `x` as series about a point, where `t` is `x - a` at
+/// a finite point `a`, and `1 / x` or `-1 / x` at infinity. A series is only
+/// worked out up to `order`, and `side` is the sign of `t`, which matters for
+/// functions like `abs` and for where a limit is taken from.
+struct Expander<'a> {
+    x: &'a str,
+    point: &'a Extended,
+    side: i32,
+    order: i32,
+    constant: &'a mut dyn FnMut(Expression) -> Result<Value, Error>,
+}
+
+impl Expander<'_> {
+    fn constant(&self, c: Value) -> Series {
+        Series::new(0, vec![c], self.order)
+    }
+
+    /// Applies a function like `sin` to a number.
+    fn apply(&mut self, name: &str, c: &Value) -> Result<Value, Error> {
+        (self.constant)(call(name, to_expression(c)?))
+    }
+
+    /// Splits a series into its constant term and the rest, which tends to
+    /// zero, failing if the series has a pole.
+    fn split(&self, u: &Series) -> Result<(Value, Series), Error> {
+        if u.valuation < 0 {
+            return Err(Error::OutOfDomain);
+        }
+        if u.order <= 0 {
+            return Err(Error::Indeterminate);
+        }
+        let c = u.coefficient(0);
+        Ok((c.clone(), u.subtract(&self.constant(c))?))
+    }
+
+    /// Sums `Σ f(k) w^k` for a series `w` that tends to zero, up to the
+    /// powers of `w` past the order being worked to.
+    fn compose(&self, w: &Series, f: &dyn Fn(usize) -> Result<Value, Error>) -> Result<Series, Error> {
+        let mut sum = self.constant(f(0)?);
+        let mut power = self.constant(Value::integer(1));
+        let mut k = 1;
+        while (k as i32) * w.valuation.max(1) < self.order {
+            power = power.multiply(w)?;
+            sum = sum.add(&power.scale(&f(k)?)?)?;
+            k += 1;
+        }
+        // A `w` known to a lower order than the sum makes all of it known only
+        // that far
+        Ok(Series::new(sum.valuation, sum.coefficients, sum.order.min(w.order)))
+    }
+
+    fn exp(&mut self, u: &Series) -> Result<Series, Error> {
+        let (c, w) = self.split(u)?;
+        let factorial = |k: usize| (1..=k as isize).try_fold(1isize, |p, j| p.checked_mul(j)).ok_or(Error::Overflow);
+        let series = self.compose(&w, &|k| Ok(Value::rational(1, factorial(k)?)))?;
+        series.scale(&self.apply("exp", &c)?)
+    }
+
+    fn ln(&mut self, u: &Series) -> Result<Series, Error> {
+        let c = u.leading()?;
+        if u.valuation != 0 {
+            return Err(Error::OutOfDomain);
+        }
+        let w = u.scale(&divide(Value::integer(1), c.clone())?)?.subtract(&self.constant(Value::integer(1)))?;
+        let series = self.compose(&w, &|k| Ok(match k {
+            0 => Value::integer(0),
+            k => Value::rational(if k % 2 == 1 { 1 } else { -1 }, k as isize),
+        }))?;
+        let ln_c = self.apply("ln", &c)?;
+        series.add(&self.constant(ln_c))
+    }
+
+    /// Finds `sin(u)` and `cos(u)` together from the sine and cosine of the
+    /// constant term and the series of the rest.
+    fn sin_cos(&mut self, u: &Series) -> Result<(Series, Series), Error> {
+        let (c, w) = self.split(u)?;
+        let factorial = |k: usize| (1..=k as isize).try_fold(1isize, |p, j| p.checked_mul(j)).ok_or(Error::Overflow);
+        let sin_w = self.compose(&w, &|k| Ok(match k % 4 {
+            1 => Value::rational(1, factorial(k)?),
+            3 => Value::rational(-1, factorial(k)?),
+            _ => Value::integer(0),
+        }))?;
+        let cos_w = self.compose(&w, &|k| Ok(match k % 4 {
+            0 => Value::rational(1, factorial(k)?),
+            2 => Value::rational(-1, factorial(k)?),
+            _ => Value::integer(0),
+        }))?;
+        let (sin_c, cos_c) = (self.apply("sin", &c)?, self.apply("cos", &c)?);
+        let sin = sin_w.scale(&cos_c)?.add(&cos_w.scale(&sin_c)?)?;
+        let cos = cos_w.scale(&cos_c)?.subtract(&sin_w.scale(&sin_c)?)?;
+        Ok((sin, cos))
+    }
+
+    /// Finds `atan(u)` by integrating its derivative `u' / (1 + u^2)`.
+    fn atan(&mut self, u: &Series) -> Result<Series, Error> {
+        let (c, _) = self.split(u)?;
+        let one = self.constant(Value::integer(1));
+        let derivative = u.derivative()?.divide(&one.add(&u.multiply(u)?)?)?;
+        let atan_c = self.apply("atan", &c)?;
+        derivative.integral()?.add(&self.constant(atan_c))
+    }
+
+    /// Raises a series to a rational power. A fractional power needs the
+    /// leading power of `t` to come out whole.
+    fn power(&mut self, u: &Series, (p, q): (isize, isize)) -> Result<Series, Error> {
+        if q == 1 {
+            return match p {
+                0 => Ok(self.constant(Value::integer(1))),
+                p if p < 0 => self.power(u, (-p, 1))?.reciprocal(),
+                p => {
+                    let half = self.power(u, (p / 2, 1))?;
+                    let square = half.multiply(&half)?;
+                    if p % 2 == 1 { square.multiply(u) } else { Ok(square) }
+                },
+            };
+        }
+        let c = u.leading()?;
+        let v = u.valuation as isize;
+        if (v * p) % q != 0 {
+            return Err(Error::OutOfDomain);
+        }
+        // Where t is negative, t^v is (-1)^v |t|^v, and |t|^(v p / q) is
+        // (-1)^(v p / q) t^(v p / q)
+        let side = |k: isize| Value::integer(if self.side < 0 && k % 2 != 0 { -1 } else { 1 });
+        let leading = multiply(c.clone(), side(v))?;
+        let factor = multiply(single(eval::power(leading, Value::rational(p, q)))?, side(v * p / q))?;
+        let w = Series::new(0, u.coefficients.clone(), u.order - u.valuation)
+            .scale(&divide(Value::integer(1), c)?)?
+            .subtract(&self.constant(Value::integer(1)))?;
+        let exponent = Value::rational(p, q);
+        let binomial = |k: usize| (0..k).try_fold(Value::integer(1), |b, j| {
+            let factor = single(eval::subtract(exponent.clone(), Value::integer(j as isize)))?;
+            divide(multiply(b, factor)?, Value::integer(j as isize + 1))
+        });
+        let series = self.compose(&w, &binomial)?;
+        Ok(series.scale(&factor)?.shift((v * p / q) as i32))
+    }
+
+    fn abs(&self, u: &Series) -> Result<Series, Error> {
+        let s = sign(&u.leading()?).ok_or(Error::MismatchedType)?;
+        match s * self.side.pow(u.valuation.unsigned_abs() % 2) {
+            -1 => u.negate(),
+            _ => Ok(u.clone()),
+        }
+    }
+
+    fn expand(&mut self, expr: &Expression) -> Result<Series, Error> {
+        if !expr.variables().iter().any(|name| name == self.x) {
+            let c = (self.constant)(expr.clone())?;
+            return Ok(self.constant(c));
+        }
+        match expr {
+            Expression::Variable(..) => match self.point {
+                Extended::Finite(a) => Ok(Series::new(0, vec![a.clone(), Value::integer(1)], self.order)),
+                Extended::PositiveInfinity => Ok(Series::new(-1, vec![Value::integer(1)], self.order)),
+                Extended::NegativeInfinity => Ok(Series::new(-1, vec![Value::integer(-1)], self.order)),
+            },
+            Expression::Negate(_, u) => self.expand(u)?.negate(),
+            Expression::Percent(_, u) => self.expand(u)?.scale(&Value::rational(1, 100)),
+            Expression::Add(_, u, v) => self.expand(u)?.add(&self.expand(v)?),
+            Expression::Subtract(_, u, v) => self.expand(u)?.subtract(&self.expand(v)?),
+            Expression::Multiply(_, u, v) => self.expand(u)?.multiply(&self.expand(v)?),
+            Expression::Divide(_, u, v) => self.expand(u)?.divide(&self.expand(v)?),
+            Expression::Power(_, u, v) => {
+                let base = self.expand(u)?;
+                if !v.variables().iter().any(|name| name == self.x) {
+                    if let Value::ComplexNumber(p, q, 0, _) = (self.constant)((**v).clone())? {
+                        return self.power(&base, (p, q));
+                    }
+                }
+                // Any other power is `exp(v ln(u))`
+                let exponent = match &**u {
+                    Expression::Constant(_, "e") => self.expand(v)?,
+                    _ => self.expand(v)?.multiply(&self.ln(&base)?)?,
+                };
+                self.exp(&exponent)
+            },
+            Expression::Call(_, f, u) => match &**f {
+                Expression::Variable(_, name) if FUNCTIONS.contains(&name.as_str()) => {
+                    let u = self.expand(u)?;
+                    match name.as_str() {
+                        "sqrt" => self.power(&u, (1, 2)),
+                        "exp" => self.exp(&u),
+                        "ln" => self.ln(&u),
+                        "log" => {
+                            let ln_10 = (self.constant)(call("ln", Expression::Number(0, 10, 1)))?;
+                            self.ln(&u)?.scale(&divide(Value::integer(1), ln_10)?)
+                        },
+                        "sin" => Ok(self.sin_cos(&u)?.0),
+                        "cos" => Ok(self.sin_cos(&u)?.1),
+                        "tan" => {
+                            let (sin, cos) = self.sin_cos(&u)?;
+                            sin.divide(&cos)
+                        },
+                        "atan" => self.atan(&u),
+                        _ => self.abs(&u),
+                    }
+                },
+                Expression::Variable(..) if matches!(**u, Expression::Tuple(..)) => Err(Error::MismatchedType),
+                // Anything else is implicit multiplication
+                f => self.expand(f)?.multiply(&self.expand(u)?),
+            },
+            _ => Err(Error::MismatchedType),
+        }
+    }
+
+    /// Expands an expression far enough to find its leading term, working to
+    /// higher orders while the terms cancel out.
+    fn expand_leading(&mut self, expr: &Expression) -> Result<Series, Error> {
+        for order in LIMIT_ORDERS {
+            self.order = order;
+            match self.expand(expr) {
+                Ok(series) if !series.coefficients.is_empty() => return Ok(series),
+                Ok(_) | Err(Error::Indeterminate) => {},
+                Err(e) => return Err(e),
+            }
+        }
+        Err(Error::Indeterminate)
+    }
+
+    /// The sign an expression has close to the point, if it is real there.
+    fn approach_sign(&mut self, expr: &Expression) -> Result<i32, Error> {
+        let series = self.expand_leading(expr)?;
+        let s = sign(&series.leading()?).ok_or(Error::NoLimit)?;
+        Ok(s * self.side.pow(series.valuation.unsigned_abs() % 2))
+    }
+
+    /// Finds a limit from the leading term of a series, and otherwise from the
+    /// limits of the parts of the expression, using L'Hôpital's rule on `0 / 0`
+    /// and `∞ / ∞`.
+    fn limit(&mut self, expr: &Expression, depth: usize) -> Result<Extended, Error> {
+        if let Ok(series) = self.expand_leading(expr) {
+            let c = series.leading()?;
+            return match series.valuation {
+                k if k > 0 => Ok(Extended::Finite(Value::integer(0))),
+                0 => Ok(Extended::Finite(c)),
+                k => {
+                    let s = sign(&c).ok_or(Error::NoLimit)? * self.side.pow(k.unsigned_abs() % 2);
+                    Ok(infinity(s))
+                },
+            };
+        }
+        match expr {
+            Expression::Negate(_, u) => negate(self.limit(u, depth)?),
+            Expression::Add(_, u, v) => sum(self.limit(u, depth)?, self.limit(v, depth)?),
+            Expression::Subtract(_, u, v) => sum(self.limit(u, depth)?, negate(self.limit(v, depth)?)?),
+            Expression::Multiply(_, u, v) => {
+                let (a, b) = (self.limit(u, depth)?, self.limit(v, depth)?);
+                if let Some(product) = product(&a, &b)? {
+                    return Ok(product);
+                }
+                // Zero times infinity is written as infinity over the reciprocal
+                // of the zero, which L'Hôpital's rule applies to
+                let (zero, infinite) = if a == Extended::Finite(Value::integer(0)) { (u, v) } else { (v, u) };
+                let reciprocal = Expression::Divide(0, Box::new(Expression::Number(0, 1, 1)), zero.clone());
+                self.l_hopital(infinite, &reciprocal, depth)
+            },
+            Expression::Divide(_, u, v) => {
+                let (a, b) = (self.limit(u, depth)?, self.limit(v, depth)?);
+                match (&a, &b) {
+                    (Extended::Finite(a), Extended::Finite(b)) if !is_zero(b) => Ok(Extended::Finite(divide(a.clone(), b.clone())?)),
+                    (Extended::Finite(a), Extended::Finite(_)) if !is_zero(a) => {
+                        Ok(infinity(sign(a).ok_or(Error::NoLimit)? * self.approach_sign(v)?))
+                    },
+                    (Extended::Finite(_), Extended::Finite(_)) => self.l_hopital(u, v, depth),
+                    (Extended::Finite(_), _) => Ok(Extended::Finite(Value::integer(0))),
+                    (_, Extended::Finite(b)) if is_zero(b) => Ok(infinity(direction(&a) * self.approach_sign(v)?)),
+                    (_, Extended::Finite(b)) => Ok(infinity(direction(&a) * sign(b).ok_or(Error::NoLimit)?)),
+                    _ => self.l_hopital(u, v, depth),
+                }
+            },
+            Expression::Power(_, u, v) => {
+                let exponent = Expression::Multiply(0, v.clone(), Box::new(call("ln", (**u).clone())));
+                match self.limit(&exponent, depth)? {
+                    Extended::Finite(c) => Ok(Extended::Finite(self.apply("exp", &c)?)),
+                    Extended::PositiveInfinity => Ok(Extended::PositiveInfinity),
+                    Extended::NegativeInfinity => Ok(Extended::Finite(Value::integer(0))),
+                }
+            },
+            Expression::Call(_, f, u) => match &**f {
+                Expression::Variable(_, name) if FUNCTIONS.contains(&name.as_str()) => self.function_limit(name, u, depth),
+                _ => Err(Error::NoLimit),
+            },
+            _ => Err(Error::NoLimit),
+        }
+    }
+
+    fn l_hopital(&mut self, u: &Expression, v: &Expression, depth: usize) -> Result<Extended, Error> {
+        if depth >= MAX_DEPTH {
+            return Err(Error::NoLimit);
+        }
+        let (du, dv) = (calculus::derivative(u, self.x)?, calculus::derivative(v, self.x)?);
+        self.limit(&Expression::Divide(0, Box::new(du), Box::new(dv)).simplify()?, depth + 1)
+    }
+
+    fn function_limit(&mut self, name: &str, u: &Expression, depth: usize) -> Result<Extended, Error> {
+        let limit = self.limit(u, depth)?;
+        match (name, limit) {
+            ("ln" | "log", Extended::Finite(c)) if is_zero(&c) => match self.approach_sign(u)? {
+                1 => Ok(Extended::NegativeInfinity),
+                _ => Err(Error::NoLimit),
+            },
+            (name, Extended::Finite(c)) => Ok(Extended::Finite(self.apply(name, &c)?)),
+            ("exp" | "ln" | "log" | "sqrt" | "abs", Extended::PositiveInfinity) => Ok(Extended::PositiveInfinity),
+            ("exp", Extended::NegativeInfinity) => Ok(Extended::Finite(Value::integer(0))),
+            ("abs", Extended::NegativeInfinity) => Ok(Extended::PositiveInfinity),
+            ("atan", infinite) => {
+                let half_pi = (self.constant)(Expression::Divide(0, Box::new(Expression::constant(0, "pi")), Box::new(Expression::Number(0, 2, 1))))?;
+                Ok(Extended::Finite(multiply(half_pi, Value::integer(direction(&infinite) as isize))?))
+            },
+            _ => Err(Error::NoLimit),
+        }
+    }
+}
+
+fn infinity(sign: i32) -> Extended {
+    match sign {
+        s if s < 0 => Extended::NegativeInfinity,
+        _ => Extended::PositiveInfinity,
+    }
+}
+
+/// The sign of an infinite limit.
+fn direction(x: &Extended) -> i32 {
+    match x {
+        Extended::NegativeInfinity => -1,
+        _ => 1,
+    }
+}
+
+fn negate(x: Extended) -> Result<Extended, Error> {
+    match x {
+        Extended::Finite(c) => Ok(Extended::Finite(single(eval::negate(c))?)),
+        Extended::PositiveInfinity => Ok(Extended::NegativeInfinity),
+        Extended::NegativeInfinity => Ok(Extended::PositiveInfinity),
+    }
+}
+
+fn sum(x: Extended, y: Extended) -> Result<Extended, Error> {
+    match (x, y) {
+        (Extended::Finite(a), Extended::Finite(b)) => Ok(Extended::Finite(add(a, b)?)),
+        (Extended::Finite(_), infinite) | (infinite, Extended::Finite(_)) => Ok(infinite),
+        (x, y) if x == y => Ok(x),
+        _ => Err(Error::Indeterminate),
+    }
+}
+
+/// Multiplies two limits, giving nothing for zero times infinity.
+fn product(x: &Extended, y: &Extended) -> Result<Option<Extended>, Error> {
+    match (x, y) {
+        (Extended::Finite(a), Extended::Finite(b)) => Ok(Some(Extended::Finite(multiply(a.clone(), b.clone())?))),
+        (Extended::Finite(c), infinite) | (infinite, Extended::Finite(c)) => match is_zero(c) {
+            true => Ok(None),
+            false => Ok(Some(infinity(sign(c).ok_or(Error::NoLimit)? * direction(infinite)))),
+        },
+        (x, y) => Ok(Some(infinity(direction(x) * direction(y)))),
+    }
+}
+
+/// Finds the limit of an expression in `x` as `x` tends to a point from the
+/// side given by the sign of `side`, which is ignored at infinity. `constant`
+/// evaluates the parts that do not depend on `x`.
+pub fn limit(expr: &Expression, x: &str, point: &Extended, side: i32, constant: &mut dyn FnMut(Expression) -> Result<Value, Error>) -> Result<Extended, Error> {
+    let side = match point {
+        Extended::Finite(_) => side,
+        _ => 1,
+    };
+    let mut expander = Expander { x, point, side, order: LIMIT_ORDERS[0], constant };
+    expander.limit(expr, 0)
+}
+
+/// Expands an expression in `x` as a series about a point up to the `n`th
+/// power of `x - a`, or of `1 / x` at infinity, and writes it as an expression.
+pub fn series(expr: &Expression, x: &str, point: &Extended, n: i32, constant: &mut dyn FnMut(Expression) -> Result<Value, Error>) -> Result<Expression, Error> {
+    let mut expander = Expander { x, point, side: 1, order: n + 1, constant };
+    let mut series = None;
+    // Terms that cancel out need the parts worked out to a higher order
+    for extra in [0, 4, 8, 16] {
+        expander.order = n + 1 + extra;
+        match expander.expand(expr) {
+            Ok(s) if s.order > n => {
+                series = Some(s);
+                break;
+            },
+            Ok(_) | Err(Error::Indeterminate) => {},
+            Err(e) => return Err(e),
+        }
+    }
+    let series = series.ok_or(Error::Indeterminate)?;
+    let variable = Box::new(Expression::Variable(0, x.to_string()));
+    let mut sum = Expression::Number(0, 0, 1);
+    for k in series.valuation..=n {
+        let c = series.coefficient(k);
+        if is_zero(&c) {
+            continue;
+        }
+        // At infinity, `t^k` is `(±1 / x)^k`
+        let (c, base, e) = match point {
+            Extended::Finite(a) if is_zero(a) => (c, variable.clone(), k),
+            Extended::Finite(a) => (c, Box::new(Expression::Subtract(0, variable.clone(), Box::new(to_expression(a)?))), k),
+            infinite => (multiply(c, Value::integer(if direction(infinite) < 0 && k % 2 != 0 { -1 } else { 1 }))?, variable.clone(), -k),
+        };
+        let power = Box::new(Expression::Power(0, base, Box::new(Expression::Number(0, e.abs() as isize, 1))));
+        let term = match e < 0 {
+            true => Expression::Divide(0, Box::new(to_expression(&c)?), power),
+            false => Expression::Multiply(0, Box::new(to_expression(&c)?), power),
+        };
+        sum = Expression::Add(0, Box::new(sum), Box::new(term));
+    }
+    sum.simplify()
+}
@@ -0,0 +1,392 @@
+use std::cmp::Ordering;
+use crate::builtins::{arguments, elements, from_f64};
+use crate::eval::{Error, to_real, real_order, power, multiply};
+use crate::integers;
+use crate::symbolic::surd;
+use crate::values::{Value, to_f64, rational_add, rational_subtract, rational_multiply, rational_divide};
+
+type Rational = (isize, isize);
+
+fn add_rational(x: Rational, y: Rational) -> Result<Rational, Error> {
+    rational_add(x, y).ok_or(Error::Overflow)
+}
+
+fn subtract_rational(x: Rational, y: Rational) -> Result<Rational, Error> {
+    rational_subtract(x, y).ok_or(Error::Overflow)
+}
+
+fn multiply_rational(x: Rational, y: Rational) -> Result<Rational, Error> {
+    rational_multiply(x, y).ok_or(Error::Overflow)
+}
+
+fn divide_rational(x: Rational, y: Rational) -> Result<Rational, Error> {
+    match y {
+        (0, _) => Err(Error::DivisionByZero),
+        y => rational_divide(x, y).ok_or(Error::Overflow),
+    }
+}
+
+fn value((a, b): Rational) -> Value {
+    Value::rational(a, b)
+}
+
+/// Reads a list or tuple of real numbers.
+fn reals(x: Value) -> Result<Vec<Rational>, Error> {
+    elements(x).iter().map(to_real).collect()
+}
+
+/// Reads two lists of real numbers of the same length.
+fn pairs(x: Value) -> Result<(Vec<Rational>, Vec<Rational>), Error> {
+    match &arguments(x)[..] {
+        [xs, ys] => {
+            let (xs, ys) = (reals(xs.clone())?, reals(ys.clone())?);
+            match xs.len() == ys.len() {
+                true => Ok((xs, ys)),
+                false => Err(Error::WrongArgumentCount),
+            }
+        },
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+fn sorted(mut xs: Vec<Rational>) -> Vec<Rational> {
+    xs.sort_by(|x, y| real_order(*x, *y));
+    xs
+}
+
+fn mean_of(xs: &[Rational]) -> Result<Rational, Error> {
+    if xs.is_empty() {
+        return Err(Error::OutOfDomain);
+    }
+    let mut sum = (0, 1);
+    for x in xs {
+        sum = add_rational(sum, *x)?;
+    }
+    divide_rational(sum, (xs.len() as isize, 1))
+}
+
+/// Sums the products of the deviations of `xs` and `ys` from their means.
+fn deviation_products(xs: &[Rational], ys: &[Rational]) -> Result<Rational, Error> {
+    let (x_mean, y_mean) = (mean_of(xs)?, mean_of(ys)?);
+    let mut sum = (0, 1);
+    for (x, y) in xs.iter().zip(ys) {
+        sum = add_rational(sum, multiply_rational(subtract_rational(*x, x_mean)?, subtract_rational(*y, y_mean)?)?)?;
+    }
+    Ok(sum)
+}
+
+/// Finds the variance, dividing by `n - 1` for a sample or `n` for a population.
+fn variance_of(xs: &[Rational], sample: bool) -> Result<Rational, Error> {
+    let n = xs.len() as isize - sample as isize;
+    if n <= 0 {
+        return Err(Error::OutOfDomain);
+    }
+    divide_rational(deviation_products(xs, xs)?, (n, 1))
+}
+
+pub fn mean(x: Value) -> Result<Vec<Value>, Error> {
+    Ok(vec![value(mean_of(&reals(x)?)?)])
+}
+
+pub fn median(x: Value) -> Result<Vec<Value>, Error> {
+    let xs = sorted(reals(x)?);
+    match xs.len() {
+        0 => Err(Error::OutOfDomain),
+        n if n % 2 == 1 => Ok(vec![value(xs[n / 2])]),
+        n => Ok(vec![value(mean_of(&xs[n / 2 - 1..=n / 2])?)]),
+    }
+}
+
+/// Finds the most common values, giving all of them when there is a tie.
+pub fn mode(x: Value) -> Result<Vec<Value>, Error> {
+    let xs = sorted(reals(x)?);
+    let mut counts: Vec<(Rational, usize)> = Vec::new();
+    for x in xs {
+        match counts.last_mut() {
+            Some((y, count)) if real_order(x, *y) == Ordering::Equal => *count += 1,
+            _ => counts.push((x, 1)),
+        }
+    }
+    let most = counts.iter().map(|(_, count)| *count).max().ok_or(Error::OutOfDomain)?;
+    Ok(counts.into_iter().filter(|(_, count)| *count == most).map(|(x, _)| value(x)).collect())
+}
+
+pub fn variance(x: Value, sample: bool) -> Result<Vec<Value>, Error> {
+    Ok(vec![value(variance_of(&reals(x)?, sample)?)])
+}
+
+pub fn stdev(x: Value, sample: bool) -> Result<Vec<Value>, Error> {
+    Ok(vec![surd(variance_of(&reals(x)?, sample)?)?])
+}
+
+/// Finds the `p` quantile by interpolating linearly between the closest ranks.
+pub fn quantile(x: Value) -> Result<Vec<Value>, Error> {
+    let (xs, p) = match &arguments(x)[..] {
+        [xs, p] => (sorted(reals(xs.clone())?), to_real(p)?),
+        _ => return Err(Error::WrongArgumentCount),
+    };
+    if xs.is_empty() || real_order(p, (0, 1)) == Ordering::Less || real_order(p, (1, 1)) == Ordering::Greater {
+        return Err(Error::OutOfDomain);
+    }
+    let (a, b) = multiply_rational((xs.len() as isize - 1, 1), p)?;
+    let rank = a.div_euclid(b);
+    let fraction = subtract_rational((a, b), (rank, 1))?;
+    match xs.get(rank as usize + 1) {
+        Some(next) => {
+            let offset = multiply_rational(fraction, subtract_rational(*next, xs[rank as usize])?)?;
+            Ok(vec![value(add_rational(xs[rank as usize], offset)?)])
+        },
+        None => Ok(vec![value(xs[rank as usize])]),
+    }
+}
+
+/// Finds the sample covariance of two lists.
+pub fn covariance(x: Value) -> Result<Vec<Value>, Error> {
+    let (xs, ys) = pairs(x)?;
+    if xs.len() < 2 {
+        return Err(Error::OutOfDomain);
+    }
+    Ok(vec![value(divide_rational(deviation_products(&xs, &ys)?, (xs.len() as isize - 1, 1))?)])
+}
+
+/// Finds Pearson's correlation coefficient of two lists.
+pub fn correlation(x: Value) -> Result<Vec<Value>, Error> {
+    let (xs, ys) = pairs(x)?;
+    let spread = multiply_rational(deviation_products(&xs, &xs)?, deviation_products(&ys, &ys)?)?;
+    if spread.0 == 0 {
+        return Err(Error::DivisionByZero);
+    }
+    // Dividing by the root of the spread is multiplying by the root over the spread
+    let covariance = divide_rational(deviation_products(&xs, &ys)?, spread)?;
+    multiply(value(covariance), surd(spread)?)
+}
+
+/// Fits a least-squares line to two lists, giving its slope and intercept.
+pub fn linreg(x: Value) -> Result<Vec<Value>, Error> {
+    let (xs, ys) = pairs(x)?;
+    let slope = divide_rational(deviation_products(&xs, &ys)?, deviation_products(&xs, &xs)?)?;
+    let intercept = subtract_rational(mean_of(&ys)?, multiply_rational(slope, mean_of(&xs)?)?)?;
+    Ok(vec![Value::Tuple(vec![value(slope), value(intercept)])])
+}
+
+/// Reads the arguments of a distribution as rationals.
+fn real_arguments(x: Value) -> Result<Vec<Rational>, Error> {
+    arguments(x).iter().map(to_real).collect()
+}
+
+fn natural(x: Rational) -> Result<u64, Error> {
+    match x {
+        (n, 1) if n >= 0 => Ok(n as u64),
+        (_, 1) => Err(Error::OutOfDomain),
+        _ => Err(Error::NotAnInteger),
+    }
+}
+
+fn probability(p: Rational) -> Result<Rational, Error> {
+    match real_order(p, (0, 1)) != Ordering::Less && real_order(p, (1, 1)) != Ordering::Greater {
+        true => Ok(p),
+        false => Err(Error::OutOfDomain),
+    }
+}
+
+fn power_of(x: Rational, k: u64) -> Result<Rational, Error> {
+    let k = isize::try_from(k).map_err(|_| Error::Overflow)?;
+    to_real(&power(value(x), Value::integer(k))?.remove(0))
+}
+
+/// Finds the chance of exactly `k` successes in `n` trials that each succeed
+/// with chance `p`.
+fn binomial_term(n: u64, p: Rational, k: u64) -> Result<Rational, Error> {
+    if k > n {
+        return Ok((0, 1));
+    }
+    let ways = integers::choose(n, k).ok_or(Error::Overflow)?;
+    let chance = multiply_rational(power_of(p, k)?, power_of(subtract_rational((1, 1), p)?, n - k)?)?;
+    multiply_rational((ways, 1), chance)
+}
+
+/// Reads the `n, p, k` arguments of the binomial distribution.
+fn binomial_arguments(x: Value) -> Result<(u64, Rational, u64), Error> {
+    match real_arguments(x)?[..] {
+        [n, p, k] => Ok((natural(n)?, probability(p)?, natural(k)?)),
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+pub fn binompdf(x: Value) -> Result<Vec<Value>, Error> {
+    let (n, p, k) = binomial_arguments(x)?;
+    Ok(vec![value(binomial_term(n, p, k)?)])
+}
+
+pub fn binomcdf(x: Value) -> Result<Vec<Value>, Error> {
+    let (n, p, k) = binomial_arguments(x)?;
+    let mut sum = (0, 1);
+    for i in 0..=k.min(n) {
+        sum = add_rational(sum, binomial_term(n, p, i)?)?;
+    }
+    Ok(vec![value(sum)])
+}
+
+/// Reads the `p, k` arguments of the geometric distribution, where `k` counts
+/// the trials up to and including the first success.
+fn geometric_arguments(x: Value) -> Result<(Rational, u64), Error> {
+    match real_arguments(x)?[..] {
+        [p, k] => match natural(k)? {
+            0 => Err(Error::OutOfDomain),
+            k => Ok((probability(p)?, k)),
+        },
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+pub fn geometpdf(x: Value) -> Result<Vec<Value>, Error> {
+    let (p, k) = geometric_arguments(x)?;
+    Ok(vec![value(multiply_rational(power_of(subtract_rational((1, 1), p)?, k - 1)?, p)?)])
+}
+
+pub fn geometcdf(x: Value) -> Result<Vec<Value>, Error> {
+    let (p, k) = geometric_arguments(x)?;
+    Ok(vec![value(subtract_rational((1, 1), power_of(subtract_rational((1, 1), p)?, k)?)?)])
+}
+
+/// Finds the chance of exactly `k` events, and of at most `k`, when `mean` are
+/// expected. These involve a power of e, so they are only approximate.
+fn poisson(x: Value) -> Result<(f64, f64), Error> {
+    let (mean, k) = match real_arguments(x)?[..] {
+        [mean, _] if mean.0 < 0 => return Err(Error::OutOfDomain),
+        [mean, k] => (to_f64(mean), natural(k)?),
+        _ => return Err(Error::WrongArgumentCount),
+    };
+    let mut term = (-mean).exp();
+    let mut sum = term;
+    // Once a term underflows to zero, so does every one after it
+    for i in 1..=k {
+        if term == 0.0 {
+            break;
+        }
+        term *= mean / i as f64;
+        sum += term;
+    }
+    Ok((term, sum))
+}
+
+pub fn poissonpdf(x: Value) -> Result<Vec<Value>, Error> {
+    Ok(vec![from_f64(poisson(x)?.0)?])
+}
+
+pub fn poissoncdf(x: Value) -> Result<Vec<Value>, Error> {
+    Ok(vec![from_f64(poisson(x)?.1)?])
+}
+
+/// The complementary error function, from its Taylor series near zero and its
+/// continued fraction further out, where the series loses precision.
+fn erfc(x: f64) -> f64 {
+    if x < 0.0 {
+        return 2.0 - erfc(-x);
+    }
+    if x < 2.0 {
+        let (mut sum, mut term) = (0.0, x);
+        for n in 0.. {
+            sum += term / (2 * n + 1) as f64;
+            term *= -x * x / (n + 1) as f64;
+            if term.abs() <= f64::EPSILON * sum.abs() * 1e-2 {
+                break;
+            }
+        }
+        return 1.0 - sum * 2.0 / std::f64::consts::PI.sqrt();
+    }
+    let mut fraction = x;
+    for k in (1..=200).rev() {
+        fraction = x + k as f64 / 2.0 / fraction;
+    }
+    (-x * x).exp() / std::f64::consts::PI.sqrt() / fraction
+}
+
+/// The chance that a standard normal variable is below `z`.
+fn normal_cdf(z: f64) -> f64 {
+    erfc(-z / std::f64::consts::SQRT_2) / 2.0
+}
+
+/// Finds the `z` below which a standard normal variable falls with chance `p`,
+/// starting from Acklam's rational approximation and refining it with Halley's
+/// method.
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [-3.969683028665376e1, 2.209460984245205e2, -2.759285104469687e2,
+        1.38357751867269e2, -3.066479806614716e1, 2.506628277459239];
+    const B: [f64; 5] = [-5.447609879822406e1, 1.615858368580409e2, -1.556989798598866e2,
+        6.680131188771972e1, -1.328068155288572e1];
+    const C: [f64; 6] = [-7.784894002430293e-3, -3.223964580411365e-1, -2.400758277161838,
+        -2.549732539343734, 4.374664141464968, 2.938163982698783];
+    const D: [f64; 4] = [7.784695709041462e-3, 3.224671290700398e-1, 2.445134137142996, 3.754408661907416];
+    let polynomial = |coefficients: &[f64], x: f64| coefficients.iter().fold(0.0, |sum, c| sum * x + c);
+    let tail = |p: f64| {
+        let q = (-2.0 * p.ln()).sqrt();
+        polynomial(&C, q) / (polynomial(&D, q) * q + 1.0)
+    };
+    let mut z = match p {
+        p if p < 0.02425 => tail(p),
+        p if p > 1.0 - 0.02425 => -tail(1.0 - p),
+        p => {
+            let (q, r) = (p - 0.5, (p - 0.5) * (p - 0.5));
+            polynomial(&A, r) * q / (polynomial(&B, r) * r + 1.0)
+        },
+    };
+    for _ in 0..2 {
+        let error = normal_cdf(z) - p;
+        let step = error * (2.0 * std::f64::consts::PI).sqrt() * (z * z / 2.0).exp();
+        z -= step / (1.0 + z * step / 2.0);
+    }
+    z
+}
+
+/// Reads the mean and standard deviation of a normal distribution, which are 0
+/// and 1 unless given.
+fn normal_parameters(parameters: &[Rational]) -> Result<(f64, f64), Error> {
+    match parameters {
+        [] => Ok((0.0, 1.0)),
+        [_, (deviation, _)] if *deviation <= 0 => Err(Error::OutOfDomain),
+        [mean, deviation] => Ok((to_f64(*mean), to_f64(*deviation))),
+        _ => Err(Error::WrongArgumentCount),
+    }
+}
+
+pub fn normalpdf(x: Value) -> Result<Vec<Value>, Error> {
+    let (x, parameters) = match &real_arguments(x)?[..] {
+        [x, parameters @ ..] => (*x, parameters.to_vec()),
+        [] => return Err(Error::WrongArgumentCount),
+    };
+    let (mean, deviation) = normal_parameters(&parameters)?;
+    let z = (to_f64(x) - mean) / deviation;
+    let density = (-z * z / 2.0).exp() / (2.0 * std::f64::consts::PI).sqrt() / deviation;
+    Ok(vec![from_f64(density)?])
+}
+
+/// Finds the chance that a normal variable is between two bounds, or below one
+/// bound when given a single bound or three arguments.
+pub fn normalcdf(x: Value) -> Result<Vec<Value>, Error> {
+    let xs = real_arguments(x)?;
+    let (lower, upper, parameters) = match &xs[..] {
+        [] => return Err(Error::WrongArgumentCount),
+        [upper] | [upper, _, _] => (None, *upper, &xs[1..]),
+        [lower, upper, parameters @ ..] => (Some(*lower), *upper, parameters),
+    };
+    let (mean, deviation) = normal_parameters(parameters)?;
+    let z = |x: Rational| (to_f64(x) - mean) / deviation;
+    let chance = match lower {
+        Some(lower) => normal_cdf(z(upper)) - normal_cdf(z(lower)),
+        None => normal_cdf(z(upper)),
+    };
+    Ok(vec![from_f64(chance)?])
+}
+
+pub fn invnorm(x: Value) -> Result<Vec<Value>, Error> {
+    let (p, parameters) = match &real_arguments(x)?[..] {
+        [p, parameters @ ..] => (to_f64(*p), parameters.to_vec()),
+        [] => return Err(Error::WrongArgumentCount),
+    };
+    let (mean, deviation) = normal_parameters(&parameters)?;
+    if p <= 0.0 || p >= 1.0 {
+        return Err(Error::OutOfDomain);
+    }
+    Ok(vec![from_f64(mean + deviation * normal_quantile(p))?])
+}
@@ -0,0 +1,173 @@
+use crate::eval::{self, Error};
+use crate::nodes::{Expression, FUNCTIONS};
+use crate::values::Value;
+use crate::calculus;
+use crate::integers;
+use crate::numeric;
+use crate::builtins::from_f64_pair;
+
+fn rational(a: isize, b: isize) -> Expression {
+    match a < 0 {
+        true => Expression::Negate(0, Box::new(Expression::Number(0, -a, b))),
+        false => Expression::Number(0, a, b),
+    }
+}
+
+/// Writes a number, polynomial or symbolic value as an expression.
+pub fn to_expression(x: &Value) -> Result<Expression, Error> {
+    match x {
+        Value::ComplexNumber(a, b, c, d) => {
+            let im = Expression::Multiply(0, Box::new(rational(*c, *d)), Box::new(Expression::ImaginaryConstant(0)));
+            Expression::Add(0, Box::new(rational(*a, *b)), Box::new(im)).simplify()
+        },
+        Value::Polynomial(p) => {
+            let mut sum = Expression::Number(0, 0, 1);
+            for (monomial, coefficient) in p.terms() {
+                let mut term = to_expression(coefficient)?;
+                for (name, k) in monomial {
+                    let variable = Box::new(Expression::Variable(0, name.clone()));
+                    let power = Expression::Power(0, variable, Box::new(Expression::Number(0, *k as isize, 1)));
+                    term = Expression::Multiply(0, Box::new(term), Box::new(power));
+                }
+                sum = Expression::Add(0, Box::new(sum), Box::new(term));
+            }
+            sum.simplify()
+        },
+        Value::Symbolic(x) => Ok(x.clone()),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+/// Reads an expression with no variables left as a number.
+fn number(x: &Expression) -> Option<Value> {
+    let apply = |f: &dyn Fn(Value, Value) -> Result<Vec<Value>, Error>, x: &Expression, y: &Expression| {
+        f(number(x)?, number(y)?).ok()?.pop()
+    };
+    match x {
+        Expression::Number(_, a, b) => Some(Value::rational(*a, *b)),
+        Expression::ImaginaryConstant(_) => Some(Value::complex(0, 1, 1, 1)),
+        Expression::Negate(_, x) => eval::negate(number(x)?).ok()?.pop(),
+        Expression::Add(_, x, y) => apply(&eval::add, x, y),
+        Expression::Subtract(_, x, y) => apply(&eval::subtract, x, y),
+        Expression::Multiply(_, x, y) => apply(&eval::multiply, x, y),
+        Expression::Divide(_, x, y) => apply(&eval::divide, x, y),
+        _ => None,
+    }
+}
+
+/// Makes a value of a simplified expression, which is a number if it has no
+/// variables left.
+pub fn from_expression(x: Expression) -> Value {
+    match number(&x) {
+        Some(value) => value,
+        None => Value::Symbolic(x),
+    }
+}
+
+/// Takes the exact square root of a non-negative rational, which is a surd
+/// like `3 * 2^(1/2) / 4` when the root is not rational.
+pub fn surd((a, b): (isize, isize)) -> Result<Value, Error> {
+    if a < 0 {
+        return Err(Error::OutOfDomain);
+    }
+    // The root of a / b is the root of a b over b
+    let (s1, r1) = integers::square_part(a as u64);
+    let (s2, r2) = integers::square_part(b as u64);
+    let r = r1.checked_mul(r2).and_then(|r| isize::try_from(r).ok()).ok_or(Error::Overflow)?;
+    let divisor = s2.checked_mul(r2).and_then(|d| isize::try_from(d).ok()).ok_or(Error::Overflow)?;
+    let coefficient = Expression::Number(0, s1 as isize, divisor);
+    let root = Expression::Power(0, Box::new(Expression::Number(0, r, 1)), Box::new(Expression::Number(0, 1, 2)));
+    Ok(from_expression(Expression::Multiply(0, Box::new(coefficient), Box::new(root)).simplify()?))
+}
+
+/// Approximates a symbolic value with no variables left by a number, leaving
+/// any other value as it is.
+pub fn approximate(x: &Value) -> Result<Value, Error> {
+    match x {
+        Value::Symbolic(expr) => {
+            let z = numeric::evaluate_at(expr, &[]).ok_or(Error::MismatchedType)?;
+            Ok(from_f64_pair(z.re, z.im)?.remove(0))
+        },
+        x => Ok(x.clone()),
+    }
+}
+
+fn combine(x: Value, y: Value, operator: fn(usize, Box<Expression>, Box<Expression>) -> Expression) -> Result<Vec<Value>, Error> {
+    let expr = operator(0, Box::new(to_expression(&x)?), Box::new(to_expression(&y)?));
+    Ok(vec![from_expression(expr.simplify()?)])
+}
+
+pub fn add(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    combine(x, y, Expression::Add)
+}
+
+pub fn subtract(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    combine(x, y, Expression::Subtract)
+}
+
+pub fn multiply(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    combine(x, y, Expression::Multiply)
+}
+
+pub fn divide(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    combine(x, y, Expression::Divide)
+}
+
+pub fn power(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    combine(x, y, Expression::Power)
+}
+
+pub fn negate(x: Value) -> Result<Vec<Value>, Error> {
+    Ok(vec![from_expression(Expression::Negate(0, Box::new(to_expression(&x)?)).simplify()?)])
+}
+
+/// Applies a function like `sin` to a value that has variables, keeping it
+/// symbolic.
+pub fn apply(name: &str, x: Value) -> Result<Vec<Value>, Error> {
+    Ok(vec![from_expression(calculus::call(name, to_expression(&x)?).simplify()?)])
+}
+
+/// The most terms `expand` multiplies out to before giving up.
+const MAX_TERMS: usize = 10_000;
+
+/// Multiplies out the products and whole powers of sums in an expression, and
+/// splits a quotient with a sum on top into a sum of quotients.
+pub fn expand(x: &Expression) -> Result<Expression, Error> {
+    let sum = summands(x)?.into_iter().reduce(|a, b| Expression::Add(0, Box::new(a), Box::new(b)));
+    Ok(sum.unwrap_or(Expression::Number(0, 0, 1)))
+}
+
+/// Expands an expression into the terms of a sum.
+fn summands(x: &Expression) -> Result<Vec<Expression>, Error> {
+    let product = |xs: Vec<Expression>, ys: Vec<Expression>| -> Result<Vec<Expression>, Error> {
+        if xs.len() * ys.len() > MAX_TERMS {
+            return Err(Error::Overflow);
+        }
+        Ok(xs.iter().flat_map(|x| ys.iter().map(|y| Expression::Multiply(0, Box::new(x.clone()), Box::new(y.clone())))).collect())
+    };
+    let negated = |xs: Vec<Expression>| xs.into_iter().map(|x| Expression::Negate(0, Box::new(x))).collect::<Vec<_>>();
+    Ok(match x {
+        Expression::Add(_, x, y) => [summands(x)?, summands(y)?].concat(),
+        Expression::Subtract(_, x, y) => [summands(x)?, negated(summands(y)?)].concat(),
+        Expression::Negate(_, x) => negated(summands(x)?),
+        Expression::Multiply(_, x, y) => product(summands(x)?, summands(y)?)?,
+        Expression::Divide(_, x, y) => {
+            let divisor = expand(y)?;
+            summands(x)?.into_iter().map(|x| Expression::Divide(0, Box::new(x), Box::new(divisor.clone()))).collect()
+        },
+        Expression::Power(_, x, n) => match **n {
+            Expression::Number(_, n, 1) if n > 1 && summands(x)?.len() > 1 => {
+                let terms = summands(x)?;
+                (1..n).try_fold(terms.clone(), |power, _| product(power, terms.clone()))?
+            },
+            _ => vec![Expression::Power(0, Box::new(expand(x)?), Box::new(expand(n)?))],
+        },
+        Expression::Call(_, f, x) => match &**f {
+            Expression::Variable(_, name) if FUNCTIONS.contains(&name.as_str()) => vec![Expression::Call(0, f.clone(), Box::new(expand(x)?))],
+            Expression::Variable(..) if matches!(**x, Expression::Tuple(..)) => vec![Expression::Call(0, f.clone(), Box::new(expand(x)?))],
+            // Anything else is implicit multiplication
+            f => product(summands(f)?, summands(x)?)?,
+        },
+        x => vec![x.try_map(&mut expand)?],
+    })
+}
@@ -0,0 +1,62 @@
+use crate::eval::{Error, subtract, multiply, divide};
+use crate::polynomials::Polynomial;
+use crate::values::Value;
+
+fn is_zero(x: &Value) -> bool {
+    matches!(x, Value::ComplexNumber(0, _, 0, _))
+}
+
+fn single(values: Result<Vec<Value>, Error>) -> Result<Value, Error> {
+    values?.into_iter().next().ok_or(Error::MismatchedType)
+}
+
+/// Solves a linear system exactly by Gauss-Jordan elimination, where each row
+/// holds the coefficients of the unknowns and the constant on the other side of
+/// the equation, which can be a polynomial in other variables. An unknown that
+/// the system leaves free stays itself, and the others are written in it.
+pub fn solve_linear(rows: Vec<(Vec<Value>, Value)>, unknowns: &[String]) -> Result<Vec<Value>, Error> {
+    let mut rows = rows;
+    let mut pivots = Vec::new();
+    for j in 0..unknowns.len() {
+        let r = pivots.len();
+        let Some(p) = (r..rows.len()).find(|&p| !is_zero(&rows[p].0[j])) else {
+            continue;
+        };
+        rows.swap(r, p);
+        let pivot = rows[r].0[j].clone();
+        let (coefficients, constant) = &mut rows[r];
+        for c in coefficients.iter_mut() {
+            *c = single(divide(c.clone(), pivot.clone()))?;
+        }
+        *constant = single(divide(constant.clone(), pivot))?;
+        let (row, constant) = rows[r].clone();
+        for (i, (coefficients, c)) in rows.iter_mut().enumerate() {
+            let factor = coefficients[j].clone();
+            if i == r || is_zero(&factor) {
+                continue;
+            }
+            for (a, b) in coefficients.iter_mut().zip(&row) {
+                *a = single(subtract(a.clone(), single(multiply(factor.clone(), b.clone()))?))?;
+            }
+            *c = single(subtract(c.clone(), single(multiply(factor, constant.clone()))?))?;
+        }
+        pivots.push(j);
+    }
+    // The rows left over have no unknowns, so they hold only if they say 0 = 0
+    if rows[pivots.len()..].iter().any(|(_, c)| !is_zero(c)) {
+        return Err(Error::NoSolution);
+    }
+    let mut solution: Vec<Value> = unknowns.iter().map(|x| Value::Polynomial(Polynomial::variable(x))).collect();
+    for (r, &j) in pivots.iter().enumerate() {
+        let (coefficients, constant) = &rows[r];
+        let mut value = constant.clone();
+        for (k, c) in coefficients.iter().enumerate() {
+            if k != j && !is_zero(c) {
+                let free = Value::Polynomial(Polynomial::variable(&unknowns[k]));
+                value = single(subtract(value, single(multiply(c.clone(), free))?))?;
+            }
+        }
+        solution[j] = value;
+    }
+    Ok(solution)
+}
@@ -5,13 +5,23 @@ pub enum Token {
     And,
     Or,
     Not,
+    In,
+    To,
+    Unc,
+    Div,
+    Mod,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
     True,
     False,
     ImaginaryConstant,
     PiConstant,
     EulerConstant,
     PhiConstant,
-    EOF,
+    Eof,
     Equal,
     Colon,
     LeftParen,
@@ -29,6 +39,7 @@ pub enum Token {
     Percent,
     Bang,
     Dot,
+    DotDot,
     Arrow,
     BigArrow,
     PlusOrMinus,
@@ -45,4 +56,6 @@ pub enum Token {
 #[derive(Clone, Debug)]
 pub enum LexError {
     UnrecognizedSymbol,
+    MissingDigits,
+    FractionalRadix,
 }
@@ -0,0 +1,86 @@
+use crate::eval::{Error, to_real, power as real_power};
+use crate::values::{Value, reduce, rational_add, rational_subtract, rational_multiply, rational_divide};
+
+type Rational = (isize, isize);
+
+/// Makes a measurement `x ± error`, where the error is a standard uncertainty.
+pub fn measurement(x: Value, error: Value) -> Result<Vec<Value>, Error> {
+    let ((a, b), (c, d)) = (to_real(&x)?, to_real(&error)?);
+    Ok(vec![Value::Measurement(a, b, c.abs(), d)])
+}
+
+/// Splits a value into its value and its uncertainty, which is zero for numbers.
+fn parts(x: Value) -> Result<(Rational, Rational), Error> {
+    match x {
+        Value::Measurement(a, b, c, d) => Ok(((a, b), (c, d))),
+        x => Ok((to_real(&x)?, (0, 1))),
+    }
+}
+
+fn measured((a, b): Rational, (c, d): Rational) -> Value {
+    let (a, b) = reduce(a, b);
+    let (c, d) = reduce(c.abs(), d);
+    Value::Measurement(a, b, c, d)
+}
+
+fn multiply_real(x: Rational, y: Rational) -> Result<Rational, Error> {
+    rational_multiply(x, y).ok_or(Error::Overflow)
+}
+
+/// Adds independent errors in quadrature, as `sqrt(x^2 + y^2)`.
+fn combine(x: Rational, y: Rational) -> Result<Rational, Error> {
+    let (a, b) = rational_add(multiply_real(x, x)?, multiply_real(y, y)?).ok_or(Error::Overflow)?;
+    to_real(&real_power(Value::rational(a, b), Value::rational(1, 2))?.remove(0))
+}
+
+pub fn add(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    let ((x, x_error), (y, y_error)) = (parts(x)?, parts(y)?);
+    let sum = rational_add(x, y).ok_or(Error::Overflow)?;
+    Ok(vec![measured(sum, combine(x_error, y_error)?)])
+}
+
+pub fn subtract(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    let ((x, x_error), (y, y_error)) = (parts(x)?, parts(y)?);
+    let difference = rational_subtract(x, y).ok_or(Error::Overflow)?;
+    Ok(vec![measured(difference, combine(x_error, y_error)?)])
+}
+
+pub fn multiply(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    let ((x, x_error), (y, y_error)) = (parts(x)?, parts(y)?);
+    let error = combine(multiply_real(y, x_error)?, multiply_real(x, y_error)?)?;
+    Ok(vec![measured(multiply_real(x, y)?, error)])
+}
+
+pub fn divide(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    let ((x, x_error), (y, y_error)) = (parts(x)?, parts(y)?);
+    if y.0 == 0 {
+        return Err(Error::DivisionByZero);
+    }
+    let quotient = rational_divide(x, y).ok_or(Error::Overflow)?;
+    // The partial derivatives of x / y are 1 / y and -x / y^2
+    let x_part = rational_divide(x_error, y).ok_or(Error::Overflow)?;
+    let y_part = rational_divide(multiply_real(quotient, y_error)?, y).ok_or(Error::Overflow)?;
+    Ok(vec![measured(quotient, combine(x_part, y_part)?)])
+}
+
+pub fn negate(x: Value) -> Result<Vec<Value>, Error> {
+    let ((a, b), error) = parts(x)?;
+    Ok(vec![measured((-a, b), error)])
+}
+
+/// Raises a measurement to an exact power `n`, whose error is `|n x^(n - 1)|`
+/// times the error of `x`.
+pub fn power(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    let (x, x_error) = parts(x)?;
+    let n = match y {
+        Value::Measurement(..) => return Err(Error::MismatchedType),
+        y => to_real(&y)?,
+    };
+    let result = to_real(&real_power(Value::rational(x.0, x.1), Value::rational(n.0, n.1))?.remove(0))?;
+    let (a, b) = rational_subtract(n, (1, 1)).ok_or(Error::Overflow)?;
+    let slope = match x_error {
+        (0, _) => (0, 1),
+        _ => multiply_real(n, to_real(&real_power(Value::rational(x.0, x.1), Value::rational(a, b))?.remove(0))?)?,
+    };
+    Ok(vec![measured(result, multiply_real(slope, x_error)?)])
+}
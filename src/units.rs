@@ -0,0 +1,258 @@
+use std::cmp::Ordering;
+use crate::eval::{Error, real_order};
+use crate::values::{Value, reduce, rational_add, rational_subtract, rational_multiply, rational_divide};
+
+type Rational = (isize, isize);
+
+/// The powers of the metre, kilogram, second, ampere, kelvin, mole and candela
+/// that make up a unit.
+pub type Dimension = [i32; 7];
+
+const LENGTH: Dimension = [1, 0, 0, 0, 0, 0, 0];
+const MASS: Dimension = [0, 1, 0, 0, 0, 0, 0];
+const TIME: Dimension = [0, 0, 1, 0, 0, 0, 0];
+const ENERGY: Dimension = [2, 1, -2, 0, 0, 0, 0];
+const PRESSURE: Dimension = [-1, 1, -2, 0, 0, 0, 0];
+
+/// The named units, with their size in SI base units and whether they take SI
+/// prefixes.
+const UNITS: &[(&str, Rational, Dimension, bool)] = &[
+    ("m", (1, 1), LENGTH, true),
+    ("g", (1, 1000), MASS, true),
+    ("s", (1, 1), TIME, true),
+    ("A", (1, 1), [0, 0, 0, 1, 0, 0, 0], true),
+    ("K", (1, 1), [0, 0, 0, 0, 1, 0, 0], true),
+    ("mol", (1, 1), [0, 0, 0, 0, 0, 1, 0], true),
+    ("cd", (1, 1), [0, 0, 0, 0, 0, 0, 1], true),
+    ("Hz", (1, 1), [0, 0, -1, 0, 0, 0, 0], true),
+    ("N", (1, 1), [1, 1, -2, 0, 0, 0, 0], true),
+    ("Pa", (1, 1), PRESSURE, true),
+    ("J", (1, 1), ENERGY, true),
+    ("W", (1, 1), [2, 1, -3, 0, 0, 0, 0], true),
+    ("C", (1, 1), [0, 0, 1, 1, 0, 0, 0], true),
+    ("V", (1, 1), [2, 1, -3, -1, 0, 0, 0], true),
+    ("Ω", (1, 1), [2, 1, -3, -2, 0, 0, 0], true),
+    ("ohm", (1, 1), [2, 1, -3, -2, 0, 0, 0], true),
+    ("L", (1, 1000), [3, 0, 0, 0, 0, 0, 0], true),
+    ("min", (60, 1), TIME, false),
+    ("h", (3600, 1), TIME, false),
+    ("day", (86400, 1), TIME, false),
+    ("inch", (254, 10000), LENGTH, false),
+    ("ft", (3048, 10000), LENGTH, false),
+    ("yd", (9144, 10000), LENGTH, false),
+    ("mi", (1609344, 1000), LENGTH, false),
+    ("mph", (1609344, 3600000), [1, 0, -1, 0, 0, 0, 0], false),
+    ("oz", (45359237, 1600000000), MASS, false),
+    ("lb", (45359237, 100000000), MASS, false),
+    ("cal", (4184, 1000), ENERGY, false),
+    ("bar", (100000, 1), PRESSURE, false),
+    ("atm", (101325, 1), PRESSURE, false),
+];
+
+/// The SI prefixes that fit in the size of a unit.
+const PREFIXES: &[(&str, Rational)] = &[
+    ("E", (1_000_000_000_000_000_000, 1)),
+    ("P", (1_000_000_000_000_000, 1)),
+    ("T", (1_000_000_000_000, 1)),
+    ("G", (1_000_000_000, 1)),
+    ("M", (1_000_000, 1)),
+    ("k", (1_000, 1)),
+    ("h", (100, 1)),
+    ("da", (10, 1)),
+    ("d", (1, 10)),
+    ("c", (1, 100)),
+    ("m", (1, 1_000)),
+    ("μ", (1, 1_000_000)),
+    ("u", (1, 1_000_000)),
+    ("n", (1, 1_000_000_000)),
+    ("p", (1, 1_000_000_000_000)),
+    ("f", (1, 1_000_000_000_000_000)),
+    ("a", (1, 1_000_000_000_000_000_000)),
+];
+
+/// Finds the size and dimension of a unit symbol like `km`, which is a named
+/// unit with an optional prefix.
+fn definition(symbol: &str) -> Option<(Rational, Dimension)> {
+    if let Some((_, scale, dimension, _)) = UNITS.iter().find(|(name, ..)| *name == symbol) {
+        return Some((*scale, *dimension));
+    }
+    PREFIXES.iter().find_map(|(prefix, factor)| {
+        let name = symbol.strip_prefix(prefix)?;
+        let (_, scale, dimension, _) = UNITS.iter().find(|(unit, _, _, prefixed)| *unit == name && *prefixed)?;
+        Some((rational_multiply(*factor, *scale)?, *dimension))
+    })
+}
+
+/// A product of powers of unit symbols, like `m s^-2`.
+#[derive(Clone, PartialEq)]
+pub struct Unit(Vec<(String, i32)>);
+
+impl Unit {
+    /// Finds the unit called `symbol`, if there is one.
+    pub fn named(symbol: &str) -> Option<Unit> {
+        definition(symbol).map(|_| Unit(vec![(symbol.to_string(), 1)]))
+    }
+
+    fn dimension(&self) -> Dimension {
+        let mut dimension = [0; 7];
+        for (symbol, power) in &self.0 {
+            if let Some((_, base)) = definition(symbol) {
+                for (d, b) in dimension.iter_mut().zip(base) {
+                    *d += b * power;
+                }
+            }
+        }
+        dimension
+    }
+
+    /// Finds the size of the unit in SI base units.
+    fn scale(&self) -> Result<Rational, Error> {
+        let mut scale = (1, 1);
+        for (symbol, power) in &self.0 {
+            let (base, _) = definition(symbol).ok_or(Error::Overflow)?;
+            for _ in 0..power.unsigned_abs() {
+                scale = match power {
+                    p if *p < 0 => rational_divide(scale, base),
+                    _ => rational_multiply(scale, base),
+                }.ok_or(Error::Overflow)?;
+            }
+        }
+        Ok(scale)
+    }
+
+    /// Multiplies this unit by another raised to `power`, cancelling symbols
+    /// whose powers add to zero.
+    fn times(&self, other: &Unit, power: i32) -> Unit {
+        let mut symbols = self.0.clone();
+        for (symbol, p) in &other.0 {
+            match symbols.iter_mut().find(|(s, _)| s == symbol) {
+                Some((_, q)) => *q += p * power,
+                None => symbols.push((symbol.clone(), p * power)),
+            }
+        }
+        symbols.retain(|(_, p)| *p != 0);
+        Unit(symbols)
+    }
+}
+
+impl std::fmt::Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let write_powers = |symbols: Vec<(&String, i32)>| symbols.iter()
+            .map(|(symbol, power)| match power {
+                1 => symbol.to_string(),
+                p => format!("{}^{}", symbol, p),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let above: Vec<_> = self.0.iter().filter(|(_, p)| *p > 0).map(|(s, p)| (s, *p)).collect();
+        let below: Vec<_> = self.0.iter().filter(|(_, p)| *p < 0).map(|(s, p)| (s, -p)).collect();
+        match (above.len(), below.len()) {
+            (0, _) => write!(f, "{}", write_powers(self.0.iter().map(|(s, p)| (s, *p)).collect())),
+            (_, 0) => write!(f, "{}", write_powers(above)),
+            (_, 1) => write!(f, "{}/{}", write_powers(above), write_powers(below)),
+            _ => write!(f, "{}/({})", write_powers(above), write_powers(below)),
+        }
+    }
+}
+
+/// Makes a quantity, which is a plain number when its units cancel.
+fn quantity((a, b): Rational, unit: Unit) -> Result<Value, Error> {
+    if unit.0.is_empty() {
+        return Ok(Value::rational(a, b));
+    }
+    if unit.dimension() == [0; 7] {
+        let (a, b) = rational_multiply((a, b), unit.scale()?).ok_or(Error::Overflow)?;
+        return Ok(Value::rational(a, b));
+    }
+    let (a, b) = reduce(a, b);
+    Ok(Value::Quantity(a, b, unit))
+}
+
+/// Splits a value into a magnitude and a unit, which is empty for numbers.
+fn parts(x: Value) -> Result<(Rational, Unit), Error> {
+    match x {
+        Value::Quantity(a, b, unit) => Ok(((a, b), unit)),
+        Value::ComplexNumber(a, b, 0, _) => Ok(((a, b), Unit(Vec::new()))),
+        Value::ComplexNumber(..) => Err(Error::NotReal),
+        _ => Err(Error::MismatchedType),
+    }
+}
+
+/// Finds the magnitude of `x` in the unit `target`, which must have the same
+/// dimension.
+fn magnitude_in(x: Rational, unit: &Unit, target: &Unit) -> Result<Rational, Error> {
+    if unit.dimension() != target.dimension() {
+        return Err(Error::DimensionMismatch);
+    }
+    let ratio = rational_divide(unit.scale()?, target.scale()?).ok_or(Error::Overflow)?;
+    rational_multiply(x, ratio).ok_or(Error::Overflow)
+}
+
+pub fn multiply(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    let ((x, x_unit), (y, y_unit)) = (parts(x)?, parts(y)?);
+    let magnitude = rational_multiply(x, y).ok_or(Error::Overflow)?;
+    Ok(vec![quantity(magnitude, x_unit.times(&y_unit, 1))?])
+}
+
+pub fn divide(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    let ((x, x_unit), (y, y_unit)) = (parts(x)?, parts(y)?);
+    if y.0 == 0 {
+        return Err(Error::DivisionByZero);
+    }
+    let magnitude = rational_divide(x, y).ok_or(Error::Overflow)?;
+    Ok(vec![quantity(magnitude, x_unit.times(&y_unit, -1))?])
+}
+
+/// Adds two quantities of the same dimension, giving the result in the unit of
+/// the first.
+pub fn add(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    let ((x, x_unit), (y, y_unit)) = (parts(x)?, parts(y)?);
+    let magnitude = rational_add(x, magnitude_in(y, &y_unit, &x_unit)?).ok_or(Error::Overflow)?;
+    Ok(vec![quantity(magnitude, x_unit)?])
+}
+
+pub fn subtract(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    let ((x, x_unit), (y, y_unit)) = (parts(x)?, parts(y)?);
+    let magnitude = rational_subtract(x, magnitude_in(y, &y_unit, &x_unit)?).ok_or(Error::Overflow)?;
+    Ok(vec![quantity(magnitude, x_unit)?])
+}
+
+/// Raises a quantity to an integer power.
+pub fn power(x: Value, y: Value) -> Result<Vec<Value>, Error> {
+    let ((mut x, unit), p) = match (parts(x)?, y) {
+        (x, Value::ComplexNumber(p, 1, 0, _)) => (x, i32::try_from(p).map_err(|_| Error::Overflow)?),
+        (_, Value::ComplexNumber(..)) => return Err(Error::NotAnInteger),
+        _ => return Err(Error::MismatchedType),
+    };
+    if x.0 == 0 && p < 0 {
+        return Err(Error::DivisionByZero);
+    }
+    let mut magnitude = (1, 1);
+    if p < 0 {
+        x = (x.1 * x.0.signum(), x.0.abs());
+    }
+    for _ in 0..p.unsigned_abs() {
+        magnitude = rational_multiply(magnitude, x).ok_or(Error::Overflow)?;
+    }
+    Ok(vec![quantity(magnitude, Unit(Vec::new()).times(&unit, p))?])
+}
+
+/// Converts a quantity into the unit of `target`, such as `km/h`.
+pub fn convert(x: Value, target: Value) -> Result<Vec<Value>, Error> {
+    let ((x, unit), (per, target)) = (parts(x)?, parts(target)?);
+    if per.0 == 0 {
+        return Err(Error::DivisionByZero);
+    }
+    let magnitude = rational_divide(magnitude_in(x, &unit, &target)?, per).ok_or(Error::Overflow)?;
+    let (a, b) = reduce(magnitude.0, magnitude.1);
+    match target.0.is_empty() {
+        true => Ok(vec![Value::rational(a, b)]),
+        false => Ok(vec![Value::Quantity(a, b, target)]),
+    }
+}
+
+/// Orders two quantities of the same dimension.
+pub fn order(x: Value, y: Value) -> Result<Ordering, Error> {
+    let ((x, x_unit), (y, y_unit)) = (parts(x)?, parts(y)?);
+    Ok(real_order(x, magnitude_in(y, &y_unit, &x_unit)?))
+}
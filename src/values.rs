@@ -1,4 +1,8 @@
 use crate::nodes::Expression;
+use crate::format::{self, ComplexForm, NumberForm};
+use crate::units::Unit;
+use crate::intervals::{self, Endpoint};
+use crate::polynomials::Polynomial;
 use gcd::Gcd;
 
 #[derive(Clone, PartialEq)]
@@ -7,26 +11,49 @@ pub enum Value {
     Boolean(bool),
     Function(Expression, Expression),
     Tuple(Vec<Value>),
+    List(Vec<Value>),
+    /// A real magnitude `a / b` in some unit.
+    Quantity(isize, isize, Unit),
+    /// A measured value `a / b` with a standard uncertainty of `c / d`.
+    Measurement(isize, isize, isize, isize),
+    Interval(Endpoint, Endpoint),
+    Polynomial(Polynomial),
+    /// An expression with free variables that is not a polynomial, like `sin(x)`.
+    Symbolic(Expression),
+}
+
+impl Value {
+    /// Creates a complex number `(a / b) + (c / d)i` with both parts in lowest terms.
+    pub fn complex(a: isize, b: isize, c: isize, d: isize) -> Value {
+        let (a, b) = reduce(a, b);
+        let (c, d) = reduce(c, d);
+        Value::ComplexNumber(a, b, c, d)
+    }
+
+    pub fn rational(a: isize, b: isize) -> Value {
+        Value::complex(a, b, 0, 1)
+    }
+
+    pub fn integer(n: isize) -> Value {
+        Value::ComplexNumber(n, 1, 0, 1)
+    }
 }
 
 impl std::fmt::Debug for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::ComplexNumber(a, b, c, d) => match (a, b, c, d) {
-                (_, b, _, d) if *b == 0 && *d == 0               => write!(f, "ERROR: Real and imaginary divisors are zero"),
-                (_, b, _, _) if *b == 0                          => write!(f, "ERROR: Real divisor is zero"),
-                (_, _, _, d) if *d == 0                          => write!(f, "ERROR: Imaginary divisor is zero"),
-                (a, b, c, d) if a % b == 0 && *c == 0 && *d != 0 => write!(f, "{}", a / b),
-                (a, b, c, d) if *c == 0 && *d != 0               => write!(f, "{}/{}", a / gcd(*a, *b), b / gcd(*a, *b)),
-                (a, b, c, d) if *a == 0 && *b != 0 && c == d     => write!(f, "i"),
-                (a, b, c, d) if *a == 0 && *b != 0 && c % d == 0 => write!(f, "{}i", c / d),
-                (a, b, c, d) if *a == 0 && *b != 0               => write!(f, "{}i/{}", c / gcd(*c, *d), d / gcd(*c, *d)),
-                (a, b, c, d) if a % b == 0 && c == d             => write!(f, "{} + i", a / b),
-                (a, b, c, d) if a % b == 0 && c % d == 0         => write!(f, "{} + {}i", a / b, c / d),
-                (a, b, c, d) if c == d                           => write!(f, "{}/{} + i", a / gcd(*a, *b), b / gcd(*a, *b)),
-                (a, b, c, d) if c % d == 0                       => write!(f, "{}/{} + {}i", a / gcd(*a, *b), b / gcd(*a, *b), c / d),
-                (a, b, c, d) if a % b == 0                       => write!(f, "{} + {}i/{}", a / b, c / gcd(*c, *d), d / gcd(*c, *d)),
-                (a, b, c, d)                                     => write!(f, "{}/{} + {}i/{}", a / gcd(*a, *b), b / gcd(*a, *b), c / gcd(*c, *d), d / gcd(*c, *d)),
+                (_, b, _, d) if *b == 0 && *d == 0 => write!(f, "ERROR: Real and imaginary divisors are zero"),
+                (_, b, _, _) if *b == 0            => write!(f, "ERROR: Real divisor is zero"),
+                (_, _, _, d) if *d == 0            => write!(f, "ERROR: Imaginary divisor is zero"),
+                (a, b, c, d) => {
+                    let re = reduce(*a, *b);
+                    let im = reduce(*c, *d);
+                    match format::current().complex {
+                        ComplexForm::Rectangular => write_rectangular(f, re, im),
+                        form => write_polar(f, re, im, form),
+                    }
+                },
             },
             Self::Boolean(true) => write!(f, "true"),
             Self::Boolean(false) => write!(f, "false"),
@@ -43,11 +70,234 @@ impl std::fmt::Debug for Value {
                 }
                 write!(f, ")")
             },
+            Self::List(xs) => {
+                write!(f, "[")?;
+                let mut iter = xs.iter().peekable();
+                while let Some(v) = iter.next() {
+                    if iter.peek().is_none() {
+                        write!(f, "{:?}", v)?;
+                    } else {
+                        write!(f, "{:?}, ", v)?;
+                    }
+                }
+                write!(f, "]")
+            },
+            Self::Quantity(a, b, unit) => write!(f, "{:?} {}", Value::rational(*a, *b), unit),
+            Self::Measurement(a, b, c, d) => write!(f, "{:?} ± {:?}", Value::rational(*a, *b), Value::rational(*c, *d)),
+            Self::Interval(lower, upper) => intervals::write(f, lower, upper),
+            Self::Polynomial(p) => write!(f, "{:?}", p),
+            Self::Symbolic(x) => write!(f, "{}", x),
         }
     }
 }
 
-pub fn gcd(x: isize, y: isize) -> isize {
-    (x.abs() as usize).gcd(y.abs() as usize) as isize
-        * (x / x.abs()) * (y / y.abs())
+fn write_rectangular(f: &mut std::fmt::Formatter<'_>, re: (isize, isize), im: (isize, isize)) -> std::fmt::Result {
+    match (re, im) {
+        (re, (0, _)) => write_rational(f, re),
+        ((0, _), im) => write_imaginary(f, im),
+        (re, (c, d)) if c < 0 => {
+            write_rational(f, re)?;
+            write!(f, " - ")?;
+            write_imaginary(f, (-c, d))
+        },
+        (re, im) => {
+            write_rational(f, re)?;
+            write!(f, " + ")?;
+            write_imaginary(f, im)
+        },
+    }
+}
+
+fn write_rational(f: &mut std::fmt::Formatter<'_>, r: (isize, isize)) -> std::fmt::Result {
+    write!(f, "{}", format::rational(r, format::current()))
+}
+
+fn write_imaginary(f: &mut std::fmt::Formatter<'_>, (c, d): (isize, isize)) -> std::fmt::Result {
+    let current = format::current();
+    if current.number != NumberForm::Fraction || current.radix != 10 {
+        return match format::rational((c, d), current).as_str() {
+            "1" => write!(f, "i"),
+            "-1" => write!(f, "-i"),
+            coefficient if coefficient.contains(' ') => write!(f, "({})i", coefficient),
+            coefficient => write!(f, "{}i", coefficient),
+        };
+    }
+    match c {
+        1 => write!(f, "i")?,
+        -1 => write!(f, "-i")?,
+        c => write!(f, "{}i", c)?,
+    }
+    match d {
+        1 => Ok(()),
+        d => write!(f, "/{}", d),
+    }
+}
+
+/// Writes a complex number as its modulus and argument in the given form. The
+/// modulus is written exactly when it is rational, and the argument is written as
+/// a multiple of π when it is close enough to one.
+fn write_polar(f: &mut std::fmt::Formatter<'_>, re: (isize, isize), im: (isize, isize), form: ComplexForm) -> std::fmt::Result {
+    if re.0 == 0 && im.0 == 0 {
+        return write!(f, "0");
+    }
+    match modulus(re, im) {
+        Some(r) => write_rational(f, r)?,
+        None => write_decimal(f, to_f64(re).hypot(to_f64(im)))?,
+    }
+    let theta = to_f64(im).atan2(to_f64(re));
+    let turns = theta / std::f64::consts::PI;
+    let multiple = approximate_bounded(turns, 12).filter(|&q| (to_f64(q) - turns).abs() < 1e-9);
+    let (sign, angle) = match multiple {
+        Some((0, _)) => ("", String::from("0")),
+        Some((p, q)) => {
+            let sign = if p < 0 { "-" } else { "" };
+            match (p.abs(), q) {
+                (1, 1) => (sign, String::from("π")),
+                (1, q) => (sign, format!("π/{}", q)),
+                (p, 1) => (sign, format!("{}π", p)),
+                (p, q) => (sign, format!("{}π/{}", p, q)),
+            }
+        },
+        None if theta < 0.0 => ("-", decimal(-theta)),
+        None => ("", decimal(theta)),
+    };
+    match form {
+        ComplexForm::Exponential => write!(f, " e^({}i{})", sign, angle),
+        _ => write!(f, "∠{}{}", sign, angle),
+    }
+}
+
+fn decimal(x: f64) -> String {
+    let digits = format!("{:.12}", x);
+    digits.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn write_decimal(f: &mut std::fmt::Formatter<'_>, x: f64) -> std::fmt::Result {
+    write!(f, "{}", decimal(x))
+}
+
+/// Finds the greatest common divisor, or `None` if it is too large to be
+/// stored, as it is for two copies of the smallest `isize`.
+pub fn gcd(x: isize, y: isize) -> Option<isize> {
+    isize::try_from(x.unsigned_abs().gcd(y.unsigned_abs())).ok()
+}
+
+/// Reduces a quotient to lowest terms, keeping the sign on the dividend.
+pub fn reduce(a: isize, b: isize) -> (isize, isize) {
+    match (a, b) {
+        (_, 0) => (a, b),
+        (0, _) => (0, 1),
+        (a, b) => match gcd(a, b) {
+            Some(divisor) => (a / (divisor * b.signum()), b / (divisor * b.signum())),
+            // Only the smallest `isize` over itself has a divisor out of range
+            None => (1, 1),
+        },
+    }
+}
+
+/// Reduces a wide quotient to lowest terms, returning `None` if it does not fit
+/// back into an `isize` quotient.
+pub fn narrow(a: i128, b: i128) -> Option<(isize, isize)> {
+    if b == 0 {
+        return None;
+    }
+    let divisor = (a.unsigned_abs().gcd(b.unsigned_abs()) as i128) * b.signum();
+    let a = isize::try_from(a / divisor).ok()?;
+    let b = isize::try_from(b / divisor).ok()?;
+    Some((a, b))
+}
+
+pub fn rational_add((a1, b1): (isize, isize), (a2, b2): (isize, isize)) -> Option<(isize, isize)> {
+    narrow(a1 as i128 * b2 as i128 + a2 as i128 * b1 as i128, b1 as i128 * b2 as i128)
+}
+
+pub fn rational_subtract((a1, b1): (isize, isize), (a2, b2): (isize, isize)) -> Option<(isize, isize)> {
+    narrow(a1 as i128 * b2 as i128 - a2 as i128 * b1 as i128, b1 as i128 * b2 as i128)
+}
+
+pub fn rational_multiply((a1, b1): (isize, isize), (a2, b2): (isize, isize)) -> Option<(isize, isize)> {
+    narrow(a1 as i128 * a2 as i128, b1 as i128 * b2 as i128)
+}
+
+pub fn rational_divide((a1, b1): (isize, isize), (a2, b2): (isize, isize)) -> Option<(isize, isize)> {
+    narrow(a1 as i128 * b2 as i128, b1 as i128 * a2 as i128)
+}
+
+/// Returns the exact square root of a non-negative integer, if it has one.
+pub fn integer_sqrt(n: isize) -> Option<isize> {
+    if n < 0 {
+        return None;
+    }
+    let (n, mut root) = (n as i128, (n as f64).sqrt() as i128);
+    while root * root > n {
+        root -= 1;
+    }
+    while (root + 1) * (root + 1) <= n {
+        root += 1;
+    }
+    if root * root == n { Some(root as isize) } else { None }
+}
+
+/// Returns the exact `k`th root of a non-negative integer, if it has one.
+pub fn integer_root(n: isize, k: isize) -> Option<isize> {
+    if n < 0 || k < 1 {
+        return None;
+    }
+    let k = u32::try_from(k).ok()?;
+    let root = (n as f64).powf(1.0 / k as f64).round() as i128;
+    [root - 1, root, root + 1].into_iter()
+        .find(|candidate| *candidate >= 0 && candidate.checked_pow(k) == Some(n as i128))
+        .map(|root| root as isize)
+}
+
+/// Returns the exact square root of a non-negative rational, if it has one.
+pub fn rational_sqrt((a, b): (isize, isize)) -> Option<(isize, isize)> {
+    let (a, b) = reduce(a, b);
+    Some((integer_sqrt(a)?, integer_sqrt(b)?))
+}
+
+/// Returns the modulus of `re + im i` if it is rational.
+pub fn modulus(re: (isize, isize), im: (isize, isize)) -> Option<(isize, isize)> {
+    let square = rational_add(rational_multiply(re, re)?, rational_multiply(im, im)?)?;
+    rational_sqrt(square)
+}
+
+pub fn to_f64((a, b): (isize, isize)) -> f64 {
+    a as f64 / b as f64
+}
+
+/// Finds the closest rational to a float, for results that cannot be computed exactly.
+pub fn approximate(x: f64) -> Option<(isize, isize)> {
+    approximate_bounded(x, 1_000_000_000_000)
+}
+
+/// Finds the closest rational to `x` with a divisor no larger than `max_divisor`
+/// by walking the continued fraction expansion of `x`.
+pub fn approximate_bounded(x: f64, max_divisor: i128) -> Option<(isize, isize)> {
+    if !x.is_finite() {
+        return None;
+    }
+    let (mut h0, mut h1) = (0i128, 1i128);
+    let (mut k0, mut k1) = (1i128, 0i128);
+    let mut remainder = x;
+    loop {
+        let term = remainder.floor();
+        if term.abs() > isize::MAX as f64 {
+            break;
+        }
+        let (h2, k2) = (term as i128 * h1 + h0, term as i128 * k1 + k0);
+        if k2 > max_divisor || h2.abs() > isize::MAX as i128 {
+            break;
+        }
+        (h0, h1, k0, k1) = (h1, h2, k1, k2);
+        let fraction = remainder - term;
+        if (h1 as f64 / k1 as f64 - x).abs() <= f64::EPSILON * x.abs() || fraction == 0.0 {
+            break;
+        }
+        remainder = 1.0 / fraction;
+    }
+    if k1 == 0 {
+        return None;
+    }
+    Some((h1 as isize, k1 as isize))
 }